@@ -0,0 +1,101 @@
+//! Builds the PowerShell host and dependency resolver assembly embedded by
+//! `src/pwsh/image.rs`.
+//!
+//! The assembly is compiled into `OUT_DIR` with the .NET SDK when `dotnet` is available,
+//! or on Windows with the `csc.exe` of the .NET Framework. Without either the crate
+//! still builds and the host is compiled from source at runtime instead.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory holding the host sources and project.
+const BOOTSTRAP_DIR: &str = "src/pwsh/bootstrap";
+
+/// Sources compiled into the host, relative to [`BOOTSTRAP_DIR`].
+const SOURCES: [&str; 2] = ["HostBootstrap.cs", "RustClrResolver.cs"];
+
+/// `System.Management.Automation` as installed with Windows PowerShell 5.
+const AUTOMATION_GAC: &str = r"Microsoft.NET\assembly\GAC_MSIL\System.Management.Automation\v4.0_3.0.0.0__31bf3856ad364e35\System.Management.Automation.dll";
+
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(embedded_bootstrap)");
+    println!("cargo::rerun-if-changed={BOOTSTRAP_DIR}");
+    println!("cargo::rerun-if-env-changed=DOCS_RS");
+
+    // docs.rs builds without network access, so the project's packages cannot be restored
+    if env::var_os("DOCS_RS").is_some() {
+        return;
+    }
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let out_dir = out_dir.join("bootstrap");
+
+    let built = dotnet_build(&out_dir).or_else(|error| {
+        if cfg!(windows) {
+            csc(&out_dir).map_err(|csc_error| format!("{error}; {csc_error}"))
+        } else {
+            Err(error)
+        }
+    });
+
+    match built {
+        Ok(()) => println!("cargo::rustc-cfg=embedded_bootstrap"),
+        Err(error) => println!(
+            "cargo::warning=HostBootstrap.dll was not built ({error}); PowerShell will compile its host at runtime"
+        ),
+    }
+}
+
+/// Builds `HostBootstrap.csproj` into `out_dir`, keeping its intermediate files there too.
+fn dotnet_build(out_dir: &Path) -> Result<(), String> {
+    let mut command = Command::new("dotnet");
+    command
+        .arg("build")
+        .arg(Path::new(BOOTSTRAP_DIR).join("HostBootstrap.csproj"))
+        .args(["-c", "Release", "--nologo", "-o"])
+        .arg(out_dir)
+        .arg(format!(
+            "-p:BaseIntermediateOutputPath={}/",
+            out_dir.join("obj").display()
+        ));
+
+    run(&mut command, "dotnet build")
+}
+
+/// Compiles the sources with the .NET Framework compiler shipped with Windows.
+fn csc(out_dir: &Path) -> Result<(), String> {
+    let windir = PathBuf::from(env::var_os("WINDIR").ok_or("WINDIR is not set")?);
+    std::fs::create_dir_all(out_dir).map_err(|error| error.to_string())?;
+
+    let mut command = Command::new(windir.join(r"Microsoft.NET\Framework64\v4.0.30319\csc.exe"));
+    command
+        .args(["/nologo", "/target:library", "/optimize+"])
+        .arg(format!(
+            "/out:{}",
+            out_dir.join("HostBootstrap.dll").display()
+        ))
+        .arg("/reference:System.Core.dll")
+        .arg(format!(
+            "/reference:{}",
+            windir.join(AUTOMATION_GAC).display()
+        ))
+        .args(SOURCES.map(|source| Path::new(BOOTSTRAP_DIR).join(source)));
+
+    run(&mut command, "csc")
+}
+
+/// Runs `command`, forwarding its output to the build log when it fails.
+fn run(command: &mut Command, name: &str) -> Result<(), String> {
+    let output = command
+        .output()
+        .map_err(|error| format!("{name} could not be started: {error}"))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    eprintln!("{}", String::from_utf8_lossy(&output.stdout));
+    eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+    Err(format!("{name} failed with {}", output.status))
+}
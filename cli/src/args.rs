@@ -0,0 +1,261 @@
+use std::time::Duration;
+use clap::{Parser, Subcommand, Args, ArgAction};
+use rustclr::RuntimeVersion;
+
+/// The main command-line interface struct.
+#[derive(Parser, Debug)]
+#[clap(author="joaoviictorti", about="Host CLR and run .NET binaries using Rust", version="1.0")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Available subcommands.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run a .NET assembly.
+    Run(RunArgs),
+
+    /// Describe a .NET assembly without loading it into a CLR.
+    Inspect(InspectArgs),
+
+    /// List the .NET runtimes installed on this machine.
+    Runtimes,
+
+    /// Run PowerShell scripts, or start a REPL when no script is given.
+    Pwsh(PwshArgs),
+
+    /// Evaluate C# snippets, or start a REPL when no snippet is given.
+    Eval(EvalArgs),
+}
+
+/// Options of the `run` subcommand.
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// Path to the .NET assembly file to be executed.
+    #[arg(short, long, required_unless_present = "manifest", help = "Path to the .NET assembly file")]
+    pub file: Option<String>,
+
+    /// TOML manifest declaring the jobs to run instead of a single assembly.
+    #[arg(short, long, conflicts_with_all = ["file", "base64", "compressed", "inputs", "command_line", "runtime_version", "domain", "patch_exit", "no_capture", "timeout"], help = "Run the [[job]] entries of a TOML manifest")]
+    pub manifest: Option<String>,
+
+    /// The file holds the assembly as base64 text.
+    #[arg(long, help = "Decode the file from base64 text")]
+    pub base64: bool,
+
+    /// The file holds the assembly compressed with gzip or raw DEFLATE.
+    #[arg(long, help = "Decompress the file (gzip or raw DEFLATE), after --base64 when both are given")]
+    pub compressed: bool,
+
+    /// Arguments for the .NET program (strings only).
+    #[arg(short, long, action = ArgAction::Append, help = "String arguments for the .NET program")]
+    pub inputs: Vec<String>,
+
+    /// Arguments for the .NET program as a single command line.
+    #[arg(short = 'c', long, conflicts_with = "inputs", help = "Arguments as one command line, split like CommandLineToArgvW")]
+    pub command_line: Option<String>,
+
+    /// Specify the .NET runtime version (e.g., "v2", "v3", "v4").
+    #[arg(short, long, default_value = "v4", value_parser = parse_runtime, help = "Specify .NET runtime version")]
+    pub runtime_version: RuntimeVersion,
+
+    /// Set a custom application domain name.
+    #[arg(short = 'd', long, help = "Set custom application domain name")]
+    pub domain: Option<String>,
+
+    /// Keep `Environment.Exit` from terminating this process.
+    #[arg(short = 'p', long, help = "Patch Environment.Exit so the assembly cannot end the process")]
+    pub patch_exit: bool,
+
+    /// Let the assembly write to the console directly instead of capturing its output.
+    #[arg(long, help = "Do not capture the assembly's console output")]
+    pub no_capture: bool,
+
+    /// Abort the entry point after this many seconds.
+    #[arg(short, long, value_name = "SECONDS", value_parser = parse_timeout, help = "Abort the assembly after this many seconds")]
+    pub timeout: Option<Duration>,
+
+    /// Print the result as JSON.
+    #[arg(long, help = "Print the result as a JSON object")]
+    pub json: bool,
+}
+
+/// Options of the `inspect` subcommand.
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// Path to the .NET assembly file to describe.
+    #[arg(help = "Path to the .NET assembly file")]
+    pub file: String,
+
+    /// Print the report as JSON.
+    #[arg(long, help = "Print the report as a JSON object")]
+    pub json: bool,
+}
+
+/// Options of the `pwsh` subcommand.
+#[derive(Args, Debug)]
+pub struct PwshArgs {
+    /// Script to run; starts a REPL when neither it nor `--file` is given.
+    #[arg(help = "PowerShell script to run")]
+    pub script: Option<String>,
+
+    /// Script file to run.
+    #[arg(short, long, conflicts_with = "script", help = "Path to a PowerShell script file")]
+    pub file: Option<String>,
+
+    /// Stop each script after this many seconds.
+    #[arg(short, long, value_name = "SECONDS", value_parser = parse_timeout, help = "Stop each script after this many seconds")]
+    pub timeout: Option<Duration>,
+
+    /// Run in an AppDomain of its own.
+    #[arg(long, help = "Host PowerShell in its own AppDomain")]
+    pub isolated: bool,
+}
+
+/// Options of the `eval` subcommand.
+#[derive(Args, Debug)]
+pub struct EvalArgs {
+    /// C# expression or method body; starts a REPL when omitted.
+    #[arg(help = "C# expression or statements to evaluate")]
+    pub snippet: Option<String>,
+
+    /// Run the snippet as statements and print nothing.
+    #[arg(short = 'x', long, help = "Run the snippet as statements without a result")]
+    pub exec: bool,
+
+    /// Additional namespaces to import.
+    #[arg(short, long = "using", action = ArgAction::Append, help = "Namespace to import")]
+    pub usings: Vec<String>,
+
+    /// Additional assemblies to reference.
+    #[arg(short = 'R', long = "reference", action = ArgAction::Append, help = "Assembly to reference")]
+    pub references: Vec<String>,
+
+    /// Specify the .NET runtime version (e.g., "v2", "v3", "v4").
+    #[arg(short, long, default_value = "v4", value_parser = parse_runtime, help = "Specify .NET runtime version")]
+    pub runtime_version: RuntimeVersion,
+}
+
+/// Parses a runtime version such as `v4`.
+pub fn parse_runtime(version: &str) -> Result<RuntimeVersion, String> {
+    match version {
+        "v2" => Ok(RuntimeVersion::V2),
+        "v3" => Ok(RuntimeVersion::V3),
+        "v4" => Ok(RuntimeVersion::V4),
+        _ => Err(format!("unknown runtime version `{version}`, expected v2, v3 or v4")),
+    }
+}
+
+/// Parses a positive number of seconds, fractions allowed.
+fn parse_timeout(seconds: &str) -> Result<Duration, String> {
+    seconds
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| format!("`{seconds}` is not a positive number of seconds"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use clap::error::ErrorKind;
+
+    fn parse(args: &[&str]) -> Result<Command, clap::Error> {
+        Cli::try_parse_from(std::iter::once("clr").chain(args.iter().copied())).map(|cli| cli.command)
+    }
+
+    #[test]
+    fn test_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_run_defaults() {
+        let Ok(Command::Run(args)) = parse(&["run", "-f", "Seatbelt.exe"]) else {
+            panic!("expected run");
+        };
+
+        assert_eq!(args.file.as_deref(), Some("Seatbelt.exe"));
+        assert!(args.manifest.is_none());
+        assert!(args.inputs.is_empty() && args.command_line.is_none());
+        assert!(matches!(args.runtime_version, RuntimeVersion::V4));
+        assert!(!args.patch_exit && !args.no_capture && !args.json);
+        assert!(!args.base64 && !args.compressed);
+        assert!(args.timeout.is_none());
+    }
+
+    #[test]
+    fn test_run_options() {
+        let Ok(Command::Run(args)) = parse(&[
+            "run", "-f", "Rubeus.exe", "-i", "triage", "-i", "/nowrap", "-r", "v2", "-d", "Job",
+            "--patch-exit", "--no-capture", "--timeout", "1.5", "--json", "--base64", "--compressed",
+        ]) else {
+            panic!("expected run");
+        };
+
+        assert_eq!(args.inputs, ["triage", "/nowrap"]);
+        assert!(matches!(args.runtime_version, RuntimeVersion::V2));
+        assert_eq!(args.domain.as_deref(), Some("Job"));
+        assert!(args.patch_exit && args.no_capture && args.json);
+        assert!(args.base64 && args.compressed);
+        assert_eq!(args.timeout, Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn test_run_rejects_invalid_values() {
+        let kind = |args: &[&str]| parse(args).map(|_| ()).unwrap_err().kind();
+
+        assert_eq!(kind(&["run"]), ErrorKind::MissingRequiredArgument);
+        assert_eq!(kind(&["run", "-f", "a.exe", "-r", "v5"]), ErrorKind::ValueValidation);
+        assert_eq!(kind(&["run", "-f", "a.exe", "-t", "0"]), ErrorKind::ValueValidation);
+        assert_eq!(kind(&["run", "-f", "a.exe", "-t", "soon"]), ErrorKind::ValueValidation);
+        assert_eq!(kind(&["run", "-f", "a.exe", "-i", "x", "-c", "y z"]), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_run_manifest() {
+        let Ok(Command::Run(args)) = parse(&["run", "--manifest", "jobs.toml", "--json"]) else {
+            panic!("expected run");
+        };
+        assert_eq!(args.manifest.as_deref(), Some("jobs.toml"));
+        assert!(args.file.is_none() && args.json);
+
+        let kind = |args: &[&str]| parse(args).map(|_| ()).unwrap_err().kind();
+        assert_eq!(kind(&["run", "-m", "jobs.toml", "-f", "a.exe"]), ErrorKind::ArgumentConflict);
+        assert_eq!(kind(&["run", "-m", "jobs.toml", "-r", "v2"]), ErrorKind::ArgumentConflict);
+        assert_eq!(kind(&["run", "-m", "jobs.toml", "-t", "5"]), ErrorKind::ArgumentConflict);
+        assert_eq!(kind(&["run", "-m", "jobs.toml", "--compressed"]), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_other_subcommands() {
+        assert!(matches!(parse(&["runtimes"]), Ok(Command::Runtimes)));
+        assert!(matches!(
+            parse(&["inspect", "a.dll", "--json"]),
+            Ok(Command::Inspect(InspectArgs { json: true, .. }))
+        ));
+
+        let Ok(Command::Pwsh(args)) = parse(&["pwsh", "Get-Date", "--isolated"]) else {
+            panic!("expected pwsh");
+        };
+        assert_eq!(args.script.as_deref(), Some("Get-Date"));
+        assert!(args.isolated && args.file.is_none());
+        assert!(parse(&["pwsh", "Get-Date", "-f", "x.ps1"]).is_err());
+
+        let Ok(Command::Eval(args)) = parse(&["eval", "-u", "System.Net", "-R", "System.Xml.dll"]) else {
+            panic!("expected eval");
+        };
+        assert!(args.snippet.is_none() && !args.exec);
+        assert_eq!(args.usings, ["System.Net"]);
+        assert_eq!(args.references, ["System.Xml.dll"]);
+    }
+
+    #[test]
+    fn test_requires_subcommand() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["-f", "a.exe"]).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use rustclr::{CSharpScript, PsValue, RustClrEnv, error::ClrError};
+use crate::{args::EvalArgs, repl};
+
+/// Evaluates one snippet, or reads snippets from stdin when none is given.
+pub fn eval(args: EvalArgs) -> Result<(), ClrError> {
+    let env = RustClrEnv::new(Some(args.runtime_version))?;
+    let script = args.references.iter().fold(
+        args.usings.iter().fold(CSharpScript::new(&env.app_domain), |script, using| script.with_using(using)),
+        |script, reference| script.with_reference(reference),
+    );
+
+    let evaluate = |snippet: &str| -> Result<(), ClrError> {
+        if args.exec {
+            script.exec(snippet)
+        } else {
+            script.eval(snippet).map(|value| println!("{}", render(&value)))
+        }
+    };
+
+    match &args.snippet {
+        Some(snippet) => evaluate(snippet),
+        None => {
+            repl::run("> ", evaluate);
+            Ok(())
+        }
+    }
+}
+
+/// Formats an evaluation result the way the C# interactive window would.
+fn render(value: &PsValue) -> String {
+    match value {
+        PsValue::Null => "null".to_string(),
+        PsValue::Bool(value) => value.to_string(),
+        PsValue::Int(value) => value.to_string(),
+        PsValue::UInt(value) => value.to_string(),
+        PsValue::Float(value) => value.to_string(),
+        PsValue::String(value) | PsValue::DateTime(value) => value.clone(),
+        PsValue::Array(items) => {
+            let items = items.iter().map(render).collect::<Vec<_>>();
+            format!("{{ {} }}", items.join(", "))
+        }
+        PsValue::Hashtable(entries) => {
+            let entries = entries
+                .iter()
+                .map(|(key, value)| format!("[{}, {}]", render(key), render(value)))
+                .collect::<Vec<_>>();
+            format!("{{ {} }}", entries.join(", "))
+        }
+        PsValue::Object { properties, .. } => {
+            let properties = properties
+                .iter()
+                .map(|(name, value)| format!("{name} = {}", render(value)))
+                .collect::<Vec<_>>();
+            format!("{{ {} }}", properties.join(", "))
+        }
+    }
+}
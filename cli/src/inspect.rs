@@ -0,0 +1,19 @@
+use std::fs;
+use rustclr::{AssemblyReport, error::ClrError};
+use crate::args::InspectArgs;
+
+/// Prints what a .NET image contains by reading its metadata, without loading it.
+pub fn inspect(args: InspectArgs) -> Result<(), ClrError> {
+    let data = fs::read(&args.file)
+        .map_err(|err| ClrError::FileReadError(format!("{}: {err}", args.file)))?;
+
+    let report = AssemblyReport::parse(&data)?;
+    if args.json {
+        let json = serde_json::to_string_pretty(&report).map_err(|err| ClrError::Msg(err.to_string()))?;
+        println!("{json}");
+    } else {
+        print!("{report}");
+    }
+
+    Ok(())
+}
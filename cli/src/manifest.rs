@@ -0,0 +1,286 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use rustclr::{RuntimeVersion, codec, error::ClrError};
+use serde::Deserialize;
+use crate::args::{RunArgs, parse_runtime};
+
+/// Jobs declared by the `[[job]]` tables of a TOML manifest, in the order they run.
+///
+/// Relative paths are resolved against the directory holding the manifest.
+#[derive(Debug)]
+pub struct Manifest {
+    pub jobs: Vec<Job>,
+}
+
+/// One run of an assembly, declared by a `[[job]]` table or by the `run` options.
+#[derive(Debug)]
+pub struct Job {
+    /// Name shown in reports; defaults to the file name, or `job N` for embedded assemblies.
+    pub name: String,
+
+    /// Where the assembly comes from.
+    pub source: Source,
+
+    /// Whether the file holds base64 text rather than the assembly itself.
+    pub base64: bool,
+
+    /// Whether the assembly is compressed with gzip or raw DEFLATE.
+    pub compressed: bool,
+
+    /// Assemblies loaded into the domain before the job's assembly.
+    pub dependencies: Vec<PathBuf>,
+
+    /// Arguments for the entry point.
+    pub args: Vec<String>,
+
+    /// Arguments as a single command line, used instead of `args` when set.
+    pub command_line: Option<String>,
+
+    /// Runtime the job runs on.
+    pub runtime_version: RuntimeVersion,
+
+    /// Name of the application domain.
+    pub domain: Option<String>,
+
+    /// Whether the console output is captured instead of written directly.
+    pub capture: bool,
+
+    /// Maximum time the entry point may run.
+    pub timeout: Option<Duration>,
+
+    /// Whether `Environment.Exit` is patched.
+    pub patch_exit: bool,
+}
+
+/// Where a job's assembly comes from.
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    /// Assembly file on disk.
+    File(PathBuf),
+
+    /// Assembly embedded in the manifest as base64.
+    Embedded(Vec<u8>),
+}
+
+/// `[[job]]` tables as written in the file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawManifest {
+    #[serde(default)]
+    job: Vec<RawJob>,
+}
+
+/// A `[[job]]` table before validation.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawJob {
+    name: Option<String>,
+    file: Option<String>,
+    base64: Option<String>,
+    #[serde(default)]
+    compressed: bool,
+    #[serde(default)]
+    dependencies: Vec<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    command_line: Option<String>,
+    runtime: Option<String>,
+    domain: Option<String>,
+    capture: Option<bool>,
+    timeout: Option<f64>,
+    #[serde(default)]
+    patch_exit: bool,
+}
+
+impl Manifest {
+    /// Reads and validates the manifest at `path`.
+    pub fn load(path: &Path) -> Result<Self, ClrError> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| ClrError::FileReadError(format!("{}: {err}", path.display())))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&text, base).map_err(|err| ClrError::Msg(format!("{}: {err}", path.display())))
+    }
+
+    /// Parses and validates a manifest, resolving relative paths against `base`.
+    pub fn parse(text: &str, base: &Path) -> Result<Self, String> {
+        let raw = toml::from_str::<RawManifest>(text).map_err(|err| err.to_string())?;
+        if raw.job.is_empty() {
+            return Err("the manifest declares no [[job]]".to_string());
+        }
+
+        let jobs = raw.job
+            .into_iter()
+            .enumerate()
+            .map(|(index, job)| Job::validate(job, index + 1, base).map_err(|err| format!("job {}: {err}", index + 1)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { jobs })
+    }
+}
+
+impl Job {
+    /// Checks a `[[job]]` table, the `number`th of the manifest.
+    fn validate(raw: RawJob, number: usize, base: &Path) -> Result<Self, String> {
+        let source = match (raw.file, raw.base64) {
+            (Some(file), None) => Source::File(base.join(file)),
+            (None, Some(encoded)) => {
+                // Whitespace is skipped, so long values can be wrapped in a multi-line string
+                let buffer = codec::decode_base64(&encoded).map_err(|err| format!("`base64` does not decode: {err}"))?;
+                Source::Embedded(buffer)
+            }
+            _ => return Err("set exactly one of `file` and `base64`".to_string()),
+        };
+
+        if !raw.args.is_empty() && raw.command_line.is_some() {
+            return Err("`args` and `command_line` cannot both be set".to_string());
+        }
+
+        let runtime_version = raw.runtime.as_deref().map_or(Ok(RuntimeVersion::V4), parse_runtime)?;
+        let timeout = raw.timeout
+            .map(|seconds| {
+                Duration::try_from_secs_f64(seconds)
+                    .ok()
+                    .filter(|timeout| !timeout.is_zero())
+                    .ok_or_else(|| format!("`timeout` of {seconds} is not a positive number of seconds"))
+            })
+            .transpose()?;
+
+        let name = raw.name.unwrap_or_else(|| match &source {
+            Source::File(path) => path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned()),
+            Source::Embedded(_) => format!("job {number}"),
+        });
+
+        Ok(Self {
+            name,
+            source,
+            base64: false,
+            compressed: raw.compressed,
+            dependencies: raw.dependencies.iter().map(|path| base.join(path)).collect(),
+            args: raw.args,
+            command_line: raw.command_line,
+            runtime_version,
+            domain: raw.domain,
+            capture: raw.capture.unwrap_or(true),
+            timeout,
+            patch_exit: raw.patch_exit,
+        })
+    }
+}
+
+impl From<&RunArgs> for Job {
+    /// Describes the single assembly given by the `run` options.
+    fn from(args: &RunArgs) -> Self {
+        let file = args.file.clone().unwrap_or_default();
+        Self {
+            name: file.clone(),
+            source: Source::File(PathBuf::from(file)),
+            base64: args.base64,
+            compressed: args.compressed,
+            dependencies: Vec::new(),
+            args: args.inputs.clone(),
+            command_line: args.command_line.clone(),
+            runtime_version: args.runtime_version,
+            domain: args.domain.clone(),
+            capture: !args.no_capture,
+            timeout: args.timeout,
+            patch_exit: args.patch_exit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Manifest, String> {
+        Manifest::parse(text, Path::new("jobs"))
+    }
+
+    #[test]
+    fn test_parse_jobs() {
+        let manifest = parse(r#"
+            [[job]]
+            name = "triage"
+            file = "Rubeus.exe"
+            dependencies = ["lib/Helper.dll"]
+            args = ["triage", "/nowrap"]
+            runtime = "v2"
+            domain = "Triage"
+            capture = false
+            timeout = 1.5
+            patch_exit = true
+
+            [[job]]
+            file = "bin/Seatbelt.exe.gz"
+            compressed = true
+            command_line = "-group=system"
+        "#).unwrap();
+
+        let [triage, seatbelt] = manifest.jobs.as_slice() else {
+            panic!("expected two jobs");
+        };
+
+        assert_eq!(triage.name, "triage");
+        assert_eq!(triage.source, Source::File(Path::new("jobs").join("Rubeus.exe")));
+        assert_eq!(triage.dependencies, [Path::new("jobs").join("lib/Helper.dll")]);
+        assert_eq!(triage.args, ["triage", "/nowrap"]);
+        assert!(matches!(triage.runtime_version, RuntimeVersion::V2));
+        assert_eq!(triage.domain.as_deref(), Some("Triage"));
+        assert!(!triage.capture && triage.patch_exit);
+        assert!(!triage.base64 && !triage.compressed);
+        assert_eq!(triage.timeout, Some(Duration::from_millis(1500)));
+
+        // Everything but the source is optional
+        assert_eq!(seatbelt.name, "Seatbelt.exe.gz");
+        assert!(seatbelt.compressed);
+        assert_eq!(seatbelt.command_line.as_deref(), Some("-group=system"));
+        assert!(seatbelt.args.is_empty() && seatbelt.dependencies.is_empty());
+        assert!(matches!(seatbelt.runtime_version, RuntimeVersion::V4));
+        assert!(seatbelt.capture && !seatbelt.patch_exit);
+        assert!(seatbelt.domain.is_none() && seatbelt.timeout.is_none());
+    }
+
+    #[test]
+    fn test_embedded_assembly() {
+        let manifest = parse(r#"
+            [[job]]
+            timeout = 30
+            base64 = """
+                TVqQAAMA
+                AAAEAAAA
+            """
+        "#).unwrap();
+
+        let job = &manifest.jobs[0];
+        assert_eq!(job.name, "job 1");
+        assert_eq!(job.source, Source::Embedded(vec![0x4D, 0x5A, 0x90, 0, 3, 0, 0, 0, 4, 0, 0, 0]));
+        assert_eq!(job.timeout, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_rejects_invalid_manifests() {
+        let error = |text: &str| parse(text).unwrap_err();
+
+        assert!(error("").contains("no [[job]]"));
+        assert!(error("[[job]]\nname = \"x\"").contains("job 1: set exactly one of `file` and `base64`"));
+        assert!(error("[[job]]\nfile = \"a.exe\"\nbase64 = \"TVo=\"").contains("exactly one"));
+        assert!(error("[[job]]\nbase64 = \"not base64!\"").contains("`base64` does not decode"));
+        assert!(error("[[job]]\nfile = \"a.exe\"\nargs = [\"x\"]\ncommand_line = \"y\"").contains("cannot both be set"));
+        assert!(error("[[job]]\nfile = \"a.exe\"\nruntime = \"v5\"").contains("unknown runtime version `v5`"));
+        assert!(error("[[job]]\nfile = \"a.exe\"\ntimeout = 0").contains("not a positive number of seconds"));
+        assert!(error("[[job]]\nfile = \"a.exe\"\ntimeout = -1").contains("not a positive number of seconds"));
+
+        // The second job is reported by its position
+        assert!(error("[[job]]\nfile = \"a.exe\"\n[[job]]\nfile = \"b.exe\"\nruntime = \"x\"").starts_with("job 2:"));
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys_and_types() {
+        assert!(parse("[[job]]\nfile = \"a.exe\"\ninputs = [\"x\"]").unwrap_err().contains("unknown field `inputs`"));
+        assert!(parse("[[job]]\nfile = \"a.exe\"\nargs = \"x\"").is_err());
+        assert!(parse("[job]\nfile = \"a.exe\"").is_err());
+        assert!(parse("[[jobs]]\nfile = \"a.exe\"").is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use std::fs;
+use std::io::{self, BufRead};
+use rustclr::{PowerShell, PsHostHandler, error::ClrError};
+use crate::{args::PwshArgs, repl};
+
+/// Answers `Read-Host` from stdin.
+struct Console;
+
+impl PsHostHandler for Console {
+    fn read_line(&self) -> Option<String> {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line).ok()?;
+        Some(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Runs one script, or reads scripts from stdin into a single session when none is given.
+pub fn pwsh(args: PwshArgs) -> Result<(), ClrError> {
+    let script = match (&args.script, &args.file) {
+        (Some(script), _) => Some(script.clone()),
+        (None, Some(file)) => Some(
+            fs::read_to_string(file).map_err(|err| ClrError::FileReadError(format!("{file}: {err}")))?,
+        ),
+        (None, None) => None,
+    };
+
+    let shell = if args.isolated { PowerShell::isolated()? } else { PowerShell::new()? }
+        .with_host_handler(Console)?;
+
+    let execute = |script: &str| -> Result<(), ClrError> {
+        let output = match args.timeout {
+            Some(timeout) => shell.execute_with_timeout(script, timeout)?,
+            None => shell.execute(script)?,
+        };
+        print!("{output}");
+        Ok(())
+    };
+
+    match script {
+        Some(script) => execute(&script),
+        None => {
+            repl::run("PS> ", execute);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::io::{self, Write};
+use rustclr::error::ClrError;
+
+/// Reads inputs from stdin and passes each to `evaluate` until EOF or `exit`.
+///
+/// A line ending with `\` continues on the next one. Errors are printed and the loop
+/// goes on, so one bad input does not end the session.
+pub fn run<F>(prompt: &str, mut evaluate: F)
+where
+    F: FnMut(&str) -> Result<(), ClrError>,
+{
+    let mut input = String::new();
+    let mut line = String::new();
+    loop {
+        print!("{}", if input.is_empty() { prompt } else { ". " });
+        let _ = io::stdout().flush();
+
+        // Stdin is only locked while a line is read, so `evaluate` can read from it too
+        line.clear();
+        if !matches!(io::stdin().read_line(&mut line), Ok(read) if read > 0) {
+            break;
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        if let Some(partial) = line.strip_suffix('\\') {
+            input.push_str(partial);
+            input.push('\n');
+            continue;
+        }
+
+        input.push_str(line);
+        let complete = std::mem::take(&mut input);
+        match complete.trim() {
+            "" => continue,
+            "exit" | "quit" => break,
+            complete => {
+                if let Err(err) = evaluate(complete) {
+                    eprintln!("{err}");
+                }
+            }
+        }
+    }
+}
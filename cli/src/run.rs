@@ -0,0 +1,177 @@
+use std::borrow::Cow;
+use std::path::Path;
+use std::time::Instant;
+use rustclr::{RustClr, codec, error::ClrError};
+use serde_json::{Value, json};
+use crate::args::RunArgs;
+use crate::manifest::{Job, Manifest, Source};
+
+/// Runs a .NET assembly, or the jobs of `--manifest`, and prints the output, or a
+/// JSON summary with `--json`.
+pub fn run(args: RunArgs) -> Result<(), ClrError> {
+    if let Some(manifest) = &args.manifest {
+        return run_manifest(Path::new(manifest), args.json);
+    }
+
+    // Read the .NET assembly file
+    let job = Job::from(&args);
+    let data = assembly(&job)?;
+
+    let started = Instant::now();
+    let result = execute(&job, &data, &[]);
+
+    if args.json {
+        println!("{:#}", report(&job, &result, started));
+    } else if let Ok(Some(output)) = &result {
+        print!("{output}");
+    }
+
+    result.map(|_| ())
+}
+
+/// Runs every job of the manifest at `path` in turn, in the runtime this process
+/// loads once. A failed job does not stop the ones after it.
+fn run_manifest(path: &Path, json: bool) -> Result<(), ClrError> {
+    let manifest = Manifest::load(path)?;
+    let started = Instant::now();
+    let mut reports = Vec::new();
+    let mut failed = 0;
+
+    for job in &manifest.jobs {
+        let job_started = Instant::now();
+        let result = load_and_execute(job);
+
+        if result.is_err() {
+            failed += 1;
+        }
+
+        if json {
+            let mut report = report(job, &result, job_started);
+            report["name"] = json!(job.name);
+            reports.push(report);
+            continue;
+        }
+
+        if manifest.jobs.len() > 1 {
+            println!("==> {} <==", job.name);
+        }
+        match &result {
+            Ok(Some(output)) => print!("{output}"),
+            Ok(None) => {}
+            Err(err) => eprintln!("error: job `{}`: {err}", job.name),
+        }
+    }
+
+    if json {
+        let report = json!({
+            "manifest": path.display().to_string(),
+            "success": failed == 0,
+            "jobs": reports,
+            "elapsed_ms": started.elapsed().as_millis() as u64,
+        });
+        println!("{report:#}");
+    }
+
+    match failed {
+        0 => Ok(()),
+        _ => Err(ClrError::Msg(format!("{failed} of {} jobs failed", manifest.jobs.len()))),
+    }
+}
+
+/// Summarizes a job's result for `--json`.
+fn report(job: &Job, result: &Result<Option<String>, ClrError>, started: Instant) -> Value {
+    let file = match &job.source {
+        Source::File(path) => Some(path.display().to_string()),
+        Source::Embedded(_) => None,
+    };
+    let (output, error) = match result {
+        Ok(output) => (output.as_deref(), None),
+        Err(err) => (None, Some(err.to_string())),
+    };
+
+    json!({
+        "file": file,
+        "success": result.is_ok(),
+        "output": output,
+        "error": error,
+        "elapsed_ms": started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Returns the job's assembly, reading it from disk unless it is embedded, and
+/// undoing its base64 encoding and compression.
+fn assembly(job: &Job) -> Result<Cow<'_, [u8]>, ClrError> {
+    let mut data = match &job.source {
+        Source::File(path) => Cow::Owned(read(path)?),
+        Source::Embedded(buffer) => Cow::Borrowed(buffer.as_slice()),
+    };
+
+    if job.base64 {
+        let text = std::str::from_utf8(&data)
+            .map_err(|_| ClrError::InvalidEncoding("the file is not base64 text".to_string()))?;
+        data = Cow::Owned(codec::decode_base64(text)?);
+    }
+
+    if job.compressed {
+        data = Cow::Owned(decompress(&data)?);
+    }
+
+    Ok(data)
+}
+
+/// Decompresses gzip data, recognized by its magic bytes, or else a raw DEFLATE stream.
+fn decompress(data: &[u8]) -> Result<Vec<u8>, ClrError> {
+    if data.starts_with(&[0x1F, 0x8B]) {
+        codec::gunzip(data)
+    } else {
+        codec::inflate(data)
+    }
+}
+
+/// Reads the job's assembly and dependencies, then runs it.
+fn load_and_execute(job: &Job) -> Result<Option<String>, ClrError> {
+    let data = assembly(job)?;
+    let dependencies = job.dependencies.iter().map(|path| read(path)).collect::<Result<Vec<_>, _>>()?;
+    execute(job, &data, &dependencies)
+}
+
+/// Reads a file with the library's size limit, naming it in the error.
+fn read(path: &Path) -> Result<Vec<u8>, ClrError> {
+    let name = path.to_str().ok_or_else(|| ClrError::FileReadError(format!("{}: path is not valid Unicode", path.display())))?;
+    rustclr::read_file(name)
+}
+
+/// Configures `RustClr` for the job and runs the entry point, returning the captured
+/// output unless capture is off.
+fn execute(job: &Job, data: &[u8], dependencies: &[Vec<u8>]) -> Result<Option<String>, ClrError> {
+    let mut clr = RustClr::new(data)?.with_runtime_version(job.runtime_version);
+
+    if job.capture {
+        clr = clr.with_output();
+    }
+
+    for dependency in dependencies {
+        clr = clr.with_dependency(dependency);
+    }
+
+    if let Some(domain) = &job.domain {
+        clr = clr.with_domain(domain);
+    }
+
+    if let Some(command_line) = &job.command_line {
+        clr = clr.with_command_line(command_line);
+    } else if !job.args.is_empty() {
+        clr = clr.with_args(job.args.iter().map(String::as_str).collect());
+    }
+
+    if job.patch_exit {
+        clr = clr.with_patch_exit();
+    }
+
+    if let Some(timeout) = job.timeout {
+        clr = clr.with_timeout(timeout);
+    }
+
+    let output = clr.run()?;
+    Ok(job.capture.then_some(output))
+}
@@ -0,0 +1,12 @@
+use rustclr::com::{CLRCreateInstance, CLSID_CLRMETAHOST, ICLRMetaHost};
+use rustclr::error::ClrError;
+
+/// Prints the version of every installed .NET Framework runtime.
+pub fn runtimes() -> Result<(), ClrError> {
+    let meta_host = CLRCreateInstance::<ICLRMetaHost>(&CLSID_CLRMETAHOST)?;
+    for version in meta_host.runtimes()?.keys() {
+        println!("{version}");
+    }
+
+    Ok(())
+}
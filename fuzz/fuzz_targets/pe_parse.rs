@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustclr::pe::PeImage;
+
+// Parsing, and every lookup the headers can point at, must fail cleanly on any input
+fuzz_target!(|data: &[u8]| {
+    let Ok(image) = PeImage::parse(data) else {
+        return;
+    };
+
+    for directory in &image.directories {
+        let _ = image.slice(directory.virtual_address, directory.size);
+    }
+
+    for section in &image.sections {
+        let _ = image.rva_to_offset(section.virtual_address);
+        let _ = image.slice(section.virtual_address, section.size_of_raw_data);
+    }
+});
@@ -0,0 +1,306 @@
+//! In-memory compilation of C# source through `System.CodeDom`.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use const_encrypt::obf;
+use windows::Win32::Foundation::VARIANT_BOOL;
+use windows::Win32::System::Variant::VARIANT;
+use windows::core::BSTR;
+
+use super::{Diagnostic, Severity};
+use crate::Invocation;
+use crate::com::{_AppDomain, _Assembly, _MethodInfo, _Type};
+use crate::error::{ClrError, Result};
+use crate::variant::{create_safe_args, create_string_array_variant};
+
+/// Compiles C# source into an assembly loaded in an AppDomain.
+///
+/// Uses `Microsoft.CSharp.CSharpCodeProvider`, i.e. the `csc.exe` shipped with the .NET
+/// Framework, so the language version is whatever that compiler supports (C# 5 on a
+/// stock .NET 4.x install).
+///
+/// # Example
+///
+/// ```
+/// let clr = RustClrEnv::new(None)?;
+/// let assembly = CSharpCompiler::new(&clr.app_domain)
+///     .add_reference("System.Core.dll")
+///     .compile("public static class Adapter { public static int Answer() { return 42; } }")?;
+///
+/// let adapter = assembly.resolve_type("Adapter")?;
+/// let answer = adapter.invoke("Answer", None, None, Invocation::Static)?;
+/// ```
+pub struct CSharpCompiler<'a> {
+    /// AppDomain the compiler runs in and the assembly is loaded into.
+    domain: &'a _AppDomain,
+
+    /// Assemblies referenced by the source, as file names or paths.
+    references: Vec<String>,
+
+    /// Extra `csc.exe` command line options.
+    options: Option<String>,
+
+    /// Whether the assembly is kept in memory instead of written to a temporary file.
+    in_memory: bool,
+}
+
+impl<'a> CSharpCompiler<'a> {
+    /// Creates a compiler referencing `mscorlib.dll` and `System.dll`.
+    pub fn new(domain: &'a _AppDomain) -> Self {
+        Self {
+            domain,
+            references: vec![
+                obf!("mscorlib.dll").to_string(),
+                obf!("System.dll").to_string(),
+            ],
+            options: None,
+            in_memory: true,
+        }
+    }
+
+    /// References another assembly by file name, e.g. `System.Core.dll`, or full path.
+    pub fn add_reference(mut self, reference: &str) -> Self {
+        if !self.references.iter().any(|existing| existing == reference) {
+            self.references.push(reference.to_string());
+        }
+        self
+    }
+
+    /// Passes extra options to `csc.exe`, e.g. `/optimize+ /unsafe`.
+    pub fn compiler_options(mut self, options: &str) -> Self {
+        self.options = Some(options.to_string());
+        self
+    }
+
+    /// Keeps the compiled assembly in memory (the default) or writes it to a temporary
+    /// file and loads it from there.
+    pub fn in_memory(mut self, in_memory: bool) -> Self {
+        self.in_memory = in_memory;
+        self
+    }
+
+    /// Compiles `source` and returns the loaded assembly.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::CompilationFailed`] with every diagnostic the compiler
+    /// reported, warnings included, if any of them is an error.
+    pub fn compile(&self, source: &str) -> Result<_Assembly> {
+        let codedom = CodeDom::load(self.domain)?;
+
+        let parameters = codedom.parameters.construct(vec![])?;
+        codedom.parameters.invoke(
+            &obf!("set_GenerateInMemory").as_str(),
+            Some(parameters.clone()),
+            Some(vec![self.in_memory.into()]),
+            Invocation::Instance,
+        )?;
+
+        if let Some(options) = &self.options {
+            codedom.parameters.invoke(
+                &obf!("set_CompilerOptions").as_str(),
+                Some(parameters.clone()),
+                Some(vec![options.as_str().into()]),
+                Invocation::Instance,
+            )?;
+        }
+
+        let references = codedom
+            .parameters
+            .method_signature(
+                &obf!("System.Collections.Specialized.StringCollection get_ReferencedAssemblies()")
+                    .as_str(),
+            )?
+            .invoke(Some(parameters.clone()), None)?;
+        let add_reference = codedom
+            .string_collection
+            .method_signature(&obf!("Int32 Add(System.String)").as_str())?;
+        for reference in &self.references {
+            let args = create_safe_args(vec![BSTR::from(reference.as_str()).into()])?;
+            add_reference.invoke(Some(references.clone()), Some(&args))?;
+        }
+
+        let provider = codedom.provider.construct(vec![])?;
+        let results = codedom.provider.invoke(
+            &obf!("CompileAssemblyFromSource").as_str(),
+            Some(provider),
+            Some(vec![
+                parameters,
+                create_string_array_variant(vec![source.to_string()])?,
+            ]),
+            Invocation::Instance,
+        )?;
+
+        let errors = codedom
+            .results
+            .method_signature(
+                &obf!("System.CodeDom.Compiler.CompilerErrorCollection get_Errors()").as_str(),
+            )?
+            .invoke(Some(results.clone()), None)?;
+        let diagnostics = codedom.diagnostics(errors)?;
+        if diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            return Err(ClrError::CompilationFailed(diagnostics));
+        }
+
+        let assembly = codedom
+            .results
+            .method_signature(&obf!("System.Reflection.Assembly get_CompiledAssembly()").as_str())?
+            .invoke(Some(results), None)?;
+        _Assembly::from_raw(unsafe { assembly.Anonymous.Anonymous.Anonymous.byref })
+    }
+}
+
+/// `System.CodeDom` types used by [`CSharpCompiler`].
+struct CodeDom {
+    mscorlib: _Assembly,
+    provider: _Type,
+    parameters: _Type,
+    string_collection: _Type,
+    results: _Type,
+    error: _Type,
+}
+
+impl CodeDom {
+    /// Loads `System.dll` into `domain` and resolves the types from it.
+    fn load(domain: &_AppDomain) -> Result<Self> {
+        let mscorlib = domain.get_assembly(&obf!("mscorlib").as_str())?;
+        let load_partial_name = mscorlib
+            .resolve_type(&obf!("System.Reflection.Assembly").as_str())?
+            .method_signature(
+                &obf!("System.Reflection.Assembly LoadWithPartialName(System.String)").as_str(),
+            )?;
+
+        let args = create_safe_args(vec![(&*obf!("System").as_str()).into()])?;
+        let system = load_partial_name.invoke(None, Some(&args))?;
+        let system = unsafe { system.Anonymous.Anonymous.Anonymous.byref };
+        if system.is_null() {
+            return Err(ClrError::Msg(obf!("System assembly not found").to_string()));
+        }
+        let system = _Assembly::from_raw(system)?;
+
+        Ok(Self {
+            provider: system.resolve_type(&obf!("Microsoft.CSharp.CSharpCodeProvider").as_str())?,
+            parameters: system
+                .resolve_type(&obf!("System.CodeDom.Compiler.CompilerParameters").as_str())?,
+            string_collection: system
+                .resolve_type(&obf!("System.Collections.Specialized.StringCollection").as_str())?,
+            results: system
+                .resolve_type(&obf!("System.CodeDom.Compiler.CompilerResults").as_str())?,
+            error: system.resolve_type(&obf!("System.CodeDom.Compiler.CompilerError").as_str())?,
+            mscorlib,
+        })
+    }
+
+    /// Reads every `CompilerError` in a `CompilerErrorCollection`.
+    fn diagnostics(&self, errors: VARIANT) -> Result<Vec<Diagnostic>> {
+        let enumerator = self
+            .mscorlib
+            .resolve_type(&obf!("System.Collections.IEnumerable").as_str())?
+            .method_signature(&obf!("System.Collections.IEnumerator GetEnumerator()").as_str())?
+            .invoke(Some(errors), None)?;
+
+        let ienumerator = self
+            .mscorlib
+            .resolve_type(&obf!("System.Collections.IEnumerator").as_str())?;
+        let move_next = ienumerator.method_signature(&obf!("Boolean MoveNext()").as_str())?;
+        let get_current =
+            ienumerator.method_signature(&obf!("System.Object get_Current()").as_str())?;
+
+        let file = self.getter(&obf!("System.String get_FileName()").as_str())?;
+        let line = self.getter(&obf!("Int32 get_Line()").as_str())?;
+        let column = self.getter(&obf!("Int32 get_Column()").as_str())?;
+        let code = self.getter(&obf!("System.String get_ErrorNumber()").as_str())?;
+        let is_warning = self.getter(&obf!("Boolean get_IsWarning()").as_str())?;
+        let message = self.getter(&obf!("System.String get_ErrorText()").as_str())?;
+
+        let mut diagnostics = Vec::new();
+        loop {
+            let has_next = move_next.invoke(Some(enumerator.clone()), None)?;
+            if unsafe { has_next.Anonymous.Anonymous.Anonymous.boolVal } == VARIANT_BOOL(0) {
+                break;
+            }
+
+            let error = get_current.invoke(Some(enumerator.clone()), None)?;
+            let int = |getter: &_MethodInfo| -> Result<u32> {
+                let value = getter.invoke(Some(error.clone()), None)?;
+                Ok(u32::try_from(unsafe { value.Anonymous.Anonymous.Anonymous.lVal }).unwrap_or(0))
+            };
+            let string = |getter: &_MethodInfo| -> Result<String> {
+                Ok(format!("{}", getter.invoke(Some(error.clone()), None)?))
+            };
+            let warning = is_warning.invoke(Some(error.clone()), None)?;
+
+            diagnostics.push(Diagnostic {
+                file: string(&file)?,
+                line: int(&line)?,
+                column: int(&column)?,
+                code: string(&code)?,
+                severity: if unsafe { warning.Anonymous.Anonymous.Anonymous.boolVal }
+                    == VARIANT_BOOL(0)
+                {
+                    Severity::Error
+                } else {
+                    Severity::Warning
+                },
+                message: string(&message)?,
+            });
+        }
+
+        Ok(diagnostics)
+    }
+
+    /// Resolves a property getter of `CompilerError`.
+    fn getter(&self, signature: &str) -> Result<_MethodInfo> {
+        self.error.method_signature(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile() -> Result<()> {
+        let clr = crate::RustClrEnv::new(None)?;
+        let assembly = CSharpCompiler::new(&clr.app_domain)
+            .add_reference("System.Core.dll")
+            .compiler_options("/optimize+")
+            .compile(
+                "using System.Linq;
+                public static class Adapter {
+                    public static int Sum() { return new[] { 1, 2, 3 }.Sum(); }
+                }",
+            )?;
+
+        let adapter = assembly.resolve_type("Adapter")?;
+        let sum = adapter.invoke("Sum", None, None, Invocation::Static)?;
+        assert_eq!(unsafe { sum.Anonymous.Anonymous.Anonymous.lVal }, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compile_diagnostics() -> Result<()> {
+        let clr = crate::RustClrEnv::new(None)?;
+        let error = CSharpCompiler::new(&clr.app_domain)
+            .compile("public class Broken {\n    int x = 1\n}")
+            .err()
+            .expect("compilation should fail");
+
+        let ClrError::CompilationFailed(diagnostics) = error else {
+            panic!("unexpected error: {error}");
+        };
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.code, "CS1002");
+        assert_eq!(diagnostic.line, 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! Diagnostics reported by the C# compiler.
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use const_encrypt::obf;
+
+/// Severity of a [`Diagnostic`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The source did not compile.
+    Error,
+
+    /// The source compiled, but the compiler flagged a likely mistake.
+    Warning,
+}
+
+/// A `CompilerError` reported while compiling.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Source file the diagnostic points at; empty for in-memory sources.
+    pub file: String,
+
+    /// 1-based line, or `0` when the diagnostic has no location.
+    pub line: u32,
+
+    /// 1-based column, or `0` when the diagnostic has no location.
+    pub column: u32,
+
+    /// Compiler error code, e.g. `CS1002`.
+    pub code: String,
+
+    /// Whether the diagnostic is an error or a warning.
+    pub severity: Severity,
+
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    /// Formats the diagnostic like `csc.exe` does, e.g.
+    /// `(3,12): error CS1002: ; expected`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => obf!("error").to_string(),
+            Severity::Warning => obf!("warning").to_string(),
+        };
+
+        if self.line > 0 {
+            write!(f, "{}({},{}): ", self.file, self.line, self.column)?;
+        } else if !self.file.is_empty() {
+            write!(f, "{}: ", self.file)?;
+        }
+
+        write!(f, "{severity} {}: {}", self.code, self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostic_display() {
+        let mut diagnostic = Diagnostic {
+            file: String::new(),
+            line: 3,
+            column: 12,
+            code: "CS1002".into(),
+            severity: Severity::Error,
+            message: "; expected".into(),
+        };
+        assert_eq!(diagnostic.to_string(), "(3,12): error CS1002: ; expected");
+
+        diagnostic.line = 0;
+        diagnostic.severity = Severity::Warning;
+        diagnostic.file = "Adapter.cs".into();
+        assert_eq!(
+            diagnostic.to_string(),
+            "Adapter.cs: warning CS1002: ; expected"
+        );
+    }
+}
@@ -1,53 +1,58 @@
-use alloc::{ffi::CString, string::ToString, vec, vec::Vec};
+use alloc::{format, vec, vec::Vec};
+use core::iter::once;
 
-use dinvk::{helper::PE, types::IMAGE_NT_HEADERS};
-use windows::Win32::System::Diagnostics::Debug::{
-    IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR, IMAGE_FILE_DLL, IMAGE_FILE_EXECUTABLE_IMAGE,
-    IMAGE_SUBSYSTEM_NATIVE,
-};
 use windows::Win32::{
     Foundation::GENERIC_READ,
     Storage::FileSystem::{
-        CreateFileA, FILE_ATTRIBUTE_NORMAL, FILE_SHARE_READ, GetFileSize, INVALID_FILE_SIZE,
-        OPEN_EXISTING, ReadFile,
+        CreateFileW, FILE_ATTRIBUTE_NORMAL, FILE_SHARE_READ, GetFileSizeEx, OPEN_EXISTING, ReadFile,
     },
 };
-use windows::core::{Owned, PCSTR};
+use windows::core::{Owned, PCWSTR};
 
 use const_encrypt::obf;
 
 use crate::error::{ClrError, Result};
 
-/// Validates whether the given PE buffer represents a valid .NET executable.
+/// Largest file [`read_file`] accepts: 1 GiB, far beyond any real assembly.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1 << 30;
+
+/// Largest single `ReadFile` request, well below the `u32` length it takes.
+const READ_CHUNK: usize = 64 << 20;
+
+/// Reads the entire contents of a file from disk into memory using the Win32 API.
+///
+/// Files larger than [`DEFAULT_MAX_FILE_SIZE`] are rejected; use
+/// [`read_file_with_limit`] to choose another limit.
 ///
 /// # Errors
 ///
-/// Returns a [`ClrError`] variant if the file is not valid or not a .NET assembly.
-pub fn validate_file(buffer: &[u8]) -> Result<()> {
-    let pe = PE::parse(buffer.as_ptr().cast_mut().cast());
-
-    let Some(nt_header) = pe.nt_header() else {
-        return Err(ClrError::InvalidNtHeader);
-    };
-
-    if !is_valid_executable(nt_header) {
-        return Err(ClrError::InvalidExecutable);
-    }
+/// Returns [`ClrError::FileReadError`] if the file cannot be opened or read, or is
+/// too large.
+pub fn read_file(path: &str) -> Result<Vec<u8>> {
+    read_file_with_limit(path, DEFAULT_MAX_FILE_SIZE)
+}
 
-    if !is_dotnet(nt_header) {
-        return Err(ClrError::NotDotNet);
+/// Reads the entire contents of a file, rejecting files larger than `max_size` bytes.
+///
+/// The path is opened as UTF-16, so it may hold any character Windows allows.
+///
+/// # Errors
+///
+/// Returns [`ClrError::FileReadError`] if the path holds a NUL character, the file
+/// cannot be opened or read, is larger than `max_size`, or ends before its reported
+/// size.
+pub fn read_file_with_limit(path: &str, max_size: u64) -> Result<Vec<u8>> {
+    if path.contains('\0') {
+        return Err(ClrError::FileReadError(format!(
+            "{path:?}: {}",
+            obf!("path contains a NUL character")
+        )));
     }
 
-    Ok(())
-}
-
-/// Reads the entire contents of a file from disk into memory using the Win32 API.
-pub fn read_file(name: &str) -> Result<Vec<u8>> {
-    let file_name =
-        CString::new(name).map_err(|_| ClrError::Msg(obf!("invalid cstring").to_string()))?;
+    let wide = path.encode_utf16().chain(once(0)).collect::<Vec<u16>>();
     let h_file = unsafe {
-        CreateFileA(
-            PCSTR::from_raw(file_name.as_ptr().cast()),
+        CreateFileW(
+            PCWSTR(wide.as_ptr()),
             GENERIC_READ.0,
             FILE_SHARE_READ,
             None,
@@ -57,40 +62,133 @@ pub fn read_file(name: &str) -> Result<Vec<u8>> {
         )
     };
 
-    let h_file = h_file.map_err(|_| ClrError::Msg(obf!("failed to open file").to_string()))?;
+    let h_file = h_file.map_err(|err| {
+        ClrError::FileReadError(format!("{path}: {}: {err}", obf!("failed to open file")))
+    })?;
     let h_file = unsafe { Owned::new(h_file) };
 
-    let size = unsafe { GetFileSize(*h_file, None) };
-    if size == INVALID_FILE_SIZE {
-        return Err(ClrError::Msg(obf!("invalid file size").to_string()));
+    let mut size = 0i64;
+    unsafe { GetFileSizeEx(*h_file, &mut size) }.map_err(|err| {
+        ClrError::FileReadError(format!("{path}: {}: {err}", obf!("failed to query size")))
+    })?;
+
+    // A negative size never comes back from the API, but must not wrap either
+    let size = u64::try_from(size).unwrap_or(u64::MAX);
+    if size > max_size {
+        return Err(ClrError::FileReadError(format!(
+            "{path}: {} {size} {} {max_size}",
+            obf!("size of"),
+            obf!("bytes exceeds the limit of")
+        )));
     }
 
-    let mut out = vec![0; size as usize];
-    let mut bytes = 0;
-    unsafe {
-        let _ = ReadFile(*h_file, Some(&mut out), Some(&mut bytes), None);
+    let size = usize::try_from(size).map_err(|_| {
+        ClrError::FileReadError(format!("{path}: {}", obf!("file does not fit in memory")))
+    })?;
+
+    let mut out = vec![0; size];
+    let mut filled = 0;
+    while filled < size {
+        let chunk = (size - filled).min(READ_CHUNK);
+        let mut read = 0;
+        unsafe {
+            ReadFile(
+                *h_file,
+                Some(&mut out[filled..filled + chunk]),
+                Some(&mut read),
+                None,
+            )
+        }
+        .map_err(|err| {
+            ClrError::FileReadError(format!("{path}: {}: {err}", obf!("failed to read file")))
+        })?;
+
+        // Zero bytes before the end means the file shrank after its size was taken
+        if read == 0 {
+            return Err(ClrError::FileReadError(format!(
+                "{path}: {} {filled} {} {size}",
+                obf!("short read of"),
+                obf!("bytes, expected")
+            )));
+        }
+
+        filled += read as usize;
     }
 
     Ok(out)
 }
 
-/// Checks whether the PE headers represent a valid Windows executable.
-fn is_valid_executable(nt_header: *const IMAGE_NT_HEADERS) -> bool {
-    unsafe {
-        let characteristics = (*nt_header).FileHeader.Characteristics;
-        (characteristics & IMAGE_FILE_EXECUTABLE_IMAGE.0 != 0)
-            && (characteristics & IMAGE_FILE_DLL.0 == 0)
-            && (characteristics & IMAGE_SUBSYSTEM_NATIVE.0 == 0)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pe::{Architecture, COM_DESCRIPTOR, CorFlags, PeImage, validate_file};
+    use std::{env, fs};
+
+    #[test]
+    fn test_validate_file_flags() -> Result<()> {
+        let buffer = read_file("files/RustClr/bin/Release/RustClr.exe")?;
+        validate_file(&buffer)?;
+
+        // Locate the Flags field of the CLI header to rewrite it
+        let pe = PeImage::parse(&buffer)?;
+        let com = pe.directory(COM_DESCRIPTOR).unwrap();
+        let flags = pe.rva_to_offset(com.virtual_address)? + 16;
+        let with_flags = |value: CorFlags| {
+            let mut patched = buffer.clone();
+            patched[flags..flags + 4].copy_from_slice(&value.bits().to_le_bytes());
+            validate_file(&patched)
+        };
+
+        assert!(matches!(
+            with_flags(CorFlags::empty()),
+            Err(ClrError::MixedModeUnsupported)
+        ));
+
+        let x86_only = with_flags(CorFlags::ILONLY | CorFlags::REQUIRED_32BIT);
+        if cfg!(target_arch = "x86") {
+            assert!(x86_only.is_ok());
+        } else {
+            assert!(matches!(
+                x86_only,
+                Err(ClrError::ArchitectureMismatch {
+                    required: Architecture::X86,
+                    ..
+                })
+            ));
+        }
+
+        Ok(())
     }
-}
 
-/// Checks if the PE includes a COM Descriptor directory, indicating a .NET assembly.
-///
-/// The COM descriptor is required for the CLR to recognize and load the assembly.
-fn is_dotnet(nt_header: *const IMAGE_NT_HEADERS) -> bool {
-    unsafe {
-        let com_dir = (*nt_header).OptionalHeader.DataDirectory
-            [IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR.0 as usize];
-        com_dir.VirtualAddress != 0 && com_dir.Size != 0
+    #[test]
+    fn test_read_file_unicode_path() -> Result<()> {
+        let path = env::temp_dir().join("rustclr-\u{00e9}\u{4e2d}\u{6587}.bin");
+        let data = (0..=255).cycle().take(70_000).collect::<Vec<u8>>();
+        fs::write(&path, &data).unwrap();
+
+        let read = read_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read?, data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_file_errors() {
+        let path = env::temp_dir().join("rustclr-limit.bin");
+        fs::write(&path, [0; 16]).unwrap();
+
+        let path = path.to_str().unwrap();
+        assert!(read_file_with_limit(path, 16).is_ok());
+        let limited = read_file_with_limit(path, 15);
+        fs::remove_file(path).unwrap();
+
+        for result in [
+            limited,
+            read_file("files/does-not-exist.exe"),
+            read_file("files/RustClr/bin/Release/RustClr.exe\0.txt"),
+        ] {
+            assert!(matches!(result, Err(ClrError::FileReadError(_))));
+        }
     }
 }
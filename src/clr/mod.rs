@@ -1,27 +1,58 @@
 use alloc::{
+    borrow::Cow,
     boxed::Box,
     format,
     string::{String, ToString},
+    sync::Arc,
     vec,
     vec::Vec,
 };
-use core::ptr::null_mut;
+use core::{ptr::null_mut, time::Duration};
 
 use const_encrypt::obf;
 use windows::Win32::System::Variant::VARIANT;
 use windows::core::{Interface, PCWSTR};
 
-use self::file::{read_file, validate_file};
 use self::runtime::{RustClrRuntime, uuid};
+use self::thread::{Event, Wait, WorkerThread};
+use crate::codec;
 use crate::com::*;
 use crate::error::{ClrError, Result};
+use crate::pe::{validate_file, validate_library};
+use crate::pwsh::HOST_IMAGE;
 use crate::variant::{create_safe_args, create_string_array_variant};
 
+mod compiler;
+pub use compiler::CSharpCompiler;
+
+mod diagnostic;
+pub use diagnostic::{Diagnostic, Severity};
+
 mod file;
+pub use file::{DEFAULT_MAX_FILE_SIZE, read_file, read_file_with_limit};
 
 mod runtime;
 pub use runtime::RuntimeVersion;
 
+mod script;
+pub use script::CSharpScript;
+
+mod wrapper;
+
+pub(crate) mod thread;
+pub use thread::CancelHandle;
+
+mod worker;
+pub use crate::sync::worker::{AssemblyId, ClrWorker};
+
+/// How long an aborted entry point is given to unwind before its domain is unloaded.
+const ABORT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Hooks `AssemblyResolve` so references bind to assemblies already loaded from bytes,
+/// which the loader never considers on its own. Matches the full name first and then
+/// the simple name, so a dependency of another version still satisfies the reference.
+const RESOLVER_SOURCE: &str = include_str!("../pwsh/bootstrap/RustClrResolver.cs");
+
 /// Represents a Rust interface to the Common Language Runtime (CLR).
 ///
 /// # Example
@@ -54,6 +85,15 @@ pub struct RustClr<'a> {
 
     /// Arguments to pass to the .NET assembly's `Main` method.
     args: Option<Vec<String>>,
+
+    /// Assemblies loaded into the domain before the executable.
+    dependencies: Vec<&'a [u8]>,
+
+    /// Maximum time the entry point may run before it is aborted.
+    timeout: Option<Duration>,
+
+    /// Handle that lets another thread cancel the run.
+    cancel: Option<CancelHandle>,
 }
 
 impl<'a> RustClr<'a> {
@@ -61,25 +101,34 @@ impl<'a> RustClr<'a> {
     ///
     /// # Errors
     ///
-    /// Returned when the file cannot be read or when the buffer does not represent
-    /// a valid .NET executable.
+    /// Returned when the file cannot be read, when an encoded or compressed source
+    /// does not decode, or when the buffer does not represent a valid .NET executable.
     pub fn new<T: Into<ClrSource<'a>>>(source: T) -> Result<Self> {
         let buffer = match source.into() {
             // Try reading the file
-            ClrSource::File(path) => Box::leak(read_file(path)?.into_boxed_slice()),
+            ClrSource::File(path) => Cow::Owned(read_file(path)?),
 
             // Creates the .NET directly from the buffer
-            ClrSource::Buffer(buffer) => buffer,
+            ClrSource::Buffer(buffer) => Cow::Borrowed(buffer),
+            ClrSource::Owned(buffer) => Cow::Owned(buffer),
+
+            // Decodes the assembly into a buffer of its own
+            ClrSource::Base64(encoded) => Cow::Owned(codec::decode_base64(encoded)?),
+            ClrSource::Deflate(data) => Cow::Owned(codec::inflate(data)?),
+            ClrSource::Gzip(data) => Cow::Owned(codec::gunzip(data)?),
         };
 
         // Checks if it is a valid .NET and EXE file
-        validate_file(buffer)?;
+        validate_file(&buffer)?;
 
         Ok(Self {
             runtime: RustClrRuntime::new(buffer),
             redirect_output: false,
             patch_exit: false,
             args: None,
+            dependencies: Vec::new(),
+            timeout: None,
+            cancel: None,
         })
     }
 
@@ -101,6 +150,26 @@ impl<'a> RustClr<'a> {
         self
     }
 
+    /// Sets the entry point's arguments from a single command line, split the way
+    /// `CommandLineToArgvW` would split it.
+    ///
+    /// The command line holds the arguments only, without the program name. See
+    /// [`cmdline`](crate::cmdline) for the quoting rules.
+    pub fn with_command_line(mut self, command_line: &str) -> Self {
+        self.args = Some(crate::cmdline::split(command_line));
+        self
+    }
+
+    /// Loads `buffer` into the application domain before the assembly runs, so the
+    /// assembly can reference it without it being on disk.
+    ///
+    /// Can be called once per dependency. References are matched by full name first
+    /// and then by simple name.
+    pub fn with_dependency(mut self, buffer: &'a [u8]) -> Self {
+        self.dependencies.push(buffer);
+        self
+    }
+
     /// Enables or disables output redirection.
     pub fn with_output(mut self) -> Self {
         self.redirect_output = true;
@@ -113,77 +182,245 @@ impl<'a> RustClr<'a> {
         self
     }
 
+    /// Sets the maximum time the entry point may run.
+    ///
+    /// When the timeout elapses the managed thread is aborted, the application domain
+    /// is unloaded and [`run`](Self::run) returns [`ClrError::Timeout`], wrapped in
+    /// [`ClrError::AbortFailed`] if the thread is still running after the abort.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Lets the run be cancelled from another thread through `cancel`.
+    ///
+    /// Cancellation aborts the managed thread like a timeout does, and
+    /// [`run`](Self::run) returns [`ClrError::Cancelled`].
+    pub fn with_cancel_handle(mut self, cancel: CancelHandle) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
     /// Loads the .NET assembly and runs its entry point.
     ///
     /// # Errors
     ///
     /// Returned when CLR initialization fails, when the assembly cannot be loaded,
-    /// when `Main` cannot be invoked, or when output capture is enabled but fails.
+    /// when `Main` cannot be invoked, when output capture is enabled but fails, or
+    /// when the run times out or is cancelled.
     pub fn run(&mut self) -> Result<String> {
         // Prepare the CLR environment
         self.runtime.prepare()?;
 
-        // Run assembly in a scope so all COM references are dropped before unload
-        let output = {
-            // Gets the current application domain
+        // Run the assembly; all COM references to the domain are dropped on return
+        let output = if self.timeout.is_some() || self.cancel.is_some() {
+            self.run_bounded()
+        } else {
             let domain = self.runtime.get_app_domain()?;
+            run_entry_point(
+                &domain,
+                &self.runtime.buffer,
+                &self.dependencies,
+                self.args.clone().unwrap_or_default(),
+                self.patch_exit,
+                self.redirect_output,
+            )
+        };
 
-            // Loads the .NET assembly from bytes directly (avoids IHostAssemblyStore dependency)
-            let assembly = domain.load_bytes(self.runtime.buffer)?;
-
-            // Prepares the args for the `Main` method (SafeArray wrapper auto-frees on drop)
-            // Main(string[]) expects a single VARIANT containing VT_ARRAY|VT_BSTR
-            let string_array = create_string_array_variant(self.args.clone().unwrap_or_default())?;
-            let args = create_safe_args(vec![string_array])?;
-
-            // Retrieves the mscorlib library
-            let mscorlib = domain.get_assembly(&obf!("mscorlib").as_str())?;
-
-            // Disables Environment.Exit if patching is enabled
-            if self.patch_exit {
-                runtime::patch_exit(&mscorlib)?;
-            }
-
-            // Optional output redirection
-            let output_manager = if self.redirect_output {
-                let mut manager = ClrOutput::new(&mscorlib);
-                manager.redirect()?;
-                Some(manager)
-            } else {
-                None
-            };
-
-            // Invokes the `Main` method of the assembly
-            let _main_result = assembly.run(&args)?;
-
-            // Capture redirected output before COM objects are dropped
-            let output = match output_manager {
-                Some(manager) => manager.capture()?,
-                None => String::new(),
-            };
-
-            // Force GC before unloading domain to release managed objects
-            let gc = mscorlib.resolve_type(&obf!("System.GC").as_str())?;
-            gc.invoke(&obf!("Collect").as_str(), None, None, Invocation::Static)?;
-            gc.invoke(
-                &obf!("WaitForPendingFinalizers").as_str(),
-                None,
-                None,
-                Invocation::Static,
-            )?;
-            gc.invoke(&obf!("Collect").as_str(), None, None, Invocation::Static)?;
+        // Now unload domain - all COM refs to domain objects are released. A failed
+        // run explains itself better than the unload error it may have caused
+        let unloaded = self.runtime.unload_domain();
+        let output = output?;
+        unloaded?;
+
+        Ok(output)
+    }
 
-            output
-            // domain, assembly, mscorlib, args all drop here
+    /// Runs the entry point on a dedicated thread, aborting it if the timeout
+    /// elapses or the cancel handle is signalled first.
+    fn run_bounded(&mut self) -> Result<String> {
+        let domain = self.runtime.get_app_domain()?;
+        let args = self.args.clone().unwrap_or_default();
+        let patch_exit = self.patch_exit;
+        let redirect_output = self.redirect_output;
+
+        // Loading happens here so the worker only needs the loaded assembly, not the
+        // buffers borrowed by `self`
+        let assembly = load_entry_assembly(&domain, &self.runtime.buffer, &self.dependencies)?;
+
+        // The worker publishes its managed `Thread` so it can be aborted from here,
+        // and raises `ready` once it has so the deadline only starts afterwards
+        let managed_thread = Arc::new(spin::Mutex::new(None));
+        let published = managed_thread.clone();
+        let ready = Arc::new(Event::new(true)?);
+        let raised = ready.clone();
+        let worker_domain = domain.clone();
+
+        // SAFETY: only CLR interfaces are moved to the worker, and those are agile
+        let worker = unsafe {
+            WorkerThread::spawn(move || {
+                *published.lock() = current_managed_thread(&worker_domain)
+                    .ok()
+                    .map(ManagedThread);
+                raised.set();
+                invoke_entry_point(&worker_domain, &assembly, args, patch_exit, redirect_output)
+            })?
         };
 
-        // Now unload domain - all COM refs to domain objects are released
-        self.runtime.unload_domain()?;
+        ready.wait();
+        let error = match worker.wait(self.timeout, self.cancel.as_ref()) {
+            Wait::Finished => return worker.join(),
+            Wait::TimedOut => ClrError::Timeout,
+            Wait::Cancelled => ClrError::Cancelled,
+        };
 
-        Ok(output)
+        // Abort the managed thread and give it a chance to unwind; unloading the
+        // domain afterwards tears down anything that is still running
+        if let Some(ManagedThread(thread)) = managed_thread.lock().take() {
+            let _ = abort_managed_thread(&domain, thread);
+        }
+        match worker.wait(Some(ABORT_GRACE_PERIOD), None) {
+            Wait::Finished => Err(error),
+            _ => Err(ClrError::AbortFailed(Box::new(error))),
+        }
     }
 }
 
+/// The `System.Threading.Thread` object of a bounded run's worker, handed to the
+/// thread that may abort it.
+struct ManagedThread(VARIANT);
+
+// SAFETY: the VARIANT holds a CLR object, whose COM interfaces are agile.
+unsafe impl Send for ManagedThread {}
+
+/// Loads the assembly defining `RustClrResolver` into `domain`: the prebuilt host
+/// image, or the resolver compiled with csc.exe when no image was built with the crate.
+fn resolver_assembly(domain: &_AppDomain) -> Result<_Assembly> {
+    match HOST_IMAGE {
+        Some(image) => {
+            validate_library(image)?;
+            domain.load_bytes(image)
+        }
+        None => CSharpCompiler::new(domain).compile(RESOLVER_SOURCE),
+    }
+}
+
+/// Loads `dependencies` and `buffer` into `domain`, runs the entry point with `args`
+/// and returns the captured output (empty unless `redirect_output` is set).
+fn run_entry_point(
+    domain: &_AppDomain,
+    buffer: &[u8],
+    dependencies: &[&[u8]],
+    args: Vec<String>,
+    patch_exit: bool,
+    redirect_output: bool,
+) -> Result<String> {
+    let assembly = load_entry_assembly(domain, buffer, dependencies)?;
+    invoke_entry_point(domain, &assembly, args, patch_exit, redirect_output)
+}
+
+/// Loads `dependencies` and then `buffer` into `domain`, returning the assembly of `buffer`.
+fn load_entry_assembly(
+    domain: &_AppDomain,
+    buffer: &[u8],
+    dependencies: &[&[u8]],
+) -> Result<_Assembly> {
+    // Dependencies must be resolvable before the entry point's types are loaded
+    if !dependencies.is_empty() {
+        for dependency in dependencies {
+            domain.load_bytes(dependency)?;
+        }
+
+        resolver_assembly(domain)?
+            .resolve_type(&obf!("RustClrResolver").as_str())?
+            .invoke(&obf!("Install").as_str(), None, None, Invocation::Static)?;
+    }
+
+    // Loads the .NET assembly from bytes directly (avoids IHostAssemblyStore dependency)
+    domain.load_bytes(buffer)
+}
+
+/// Runs the entry point of `assembly` with `args` and returns the captured output
+/// (empty unless `redirect_output` is set).
+fn invoke_entry_point(
+    domain: &_AppDomain,
+    assembly: &_Assembly,
+    args: Vec<String>,
+    patch_exit: bool,
+    redirect_output: bool,
+) -> Result<String> {
+    // Prepares the args for the `Main` method (SafeArray wrapper auto-frees on drop)
+    // Main(string[]) expects a single VARIANT containing VT_ARRAY|VT_BSTR
+    let string_array = create_string_array_variant(args)?;
+    let args = create_safe_args(vec![string_array])?;
+
+    // Retrieves the mscorlib library
+    let mscorlib = domain.get_assembly(&obf!("mscorlib").as_str())?;
+
+    // Disables Environment.Exit if patching is enabled
+    if patch_exit {
+        runtime::patch_exit(&mscorlib)?;
+    }
+
+    // Optional output redirection
+    let output_manager = if redirect_output {
+        let mut manager = ClrOutput::new(&mscorlib);
+        manager.redirect()?;
+        Some(manager)
+    } else {
+        None
+    };
+
+    // Invokes the `Main` method of the assembly
+    let _main_result = assembly.run(&args)?;
+
+    // Capture redirected output before COM objects are dropped
+    let output = match output_manager {
+        Some(manager) => manager.capture()?,
+        None => String::new(),
+    };
+
+    // Force GC before unloading domain to release managed objects
+    let gc = mscorlib.resolve_type(&obf!("System.GC").as_str())?;
+    gc.invoke(&obf!("Collect").as_str(), None, None, Invocation::Static)?;
+    gc.invoke(
+        &obf!("WaitForPendingFinalizers").as_str(),
+        None,
+        None,
+        Invocation::Static,
+    )?;
+    gc.invoke(&obf!("Collect").as_str(), None, None, Invocation::Static)?;
+
+    Ok(output)
+    // assembly, mscorlib, args all drop here
+}
+
+/// Returns the `System.Threading.Thread` object of the calling thread.
+fn current_managed_thread(domain: &_AppDomain) -> Result<VARIANT> {
+    let mscorlib = domain.get_assembly(&obf!("mscorlib").as_str())?;
+    let thread = mscorlib.resolve_type(&obf!("System.Threading.Thread").as_str())?;
+    thread.invoke(
+        &obf!("get_CurrentThread").as_str(),
+        None,
+        None,
+        Invocation::Static,
+    )
+}
+
+/// Raises a `ThreadAbortException` in the given managed thread.
+fn abort_managed_thread(domain: &_AppDomain, thread: VARIANT) -> Result<()> {
+    let mscorlib = domain.get_assembly(&obf!("mscorlib").as_str())?;
+    let thread_type = mscorlib.resolve_type(&obf!("System.Threading.Thread").as_str())?;
+    thread_type.invoke(
+        &obf!("Abort").as_str(),
+        Some(thread),
+        None,
+        Invocation::Instance,
+    )?;
+
+    Ok(())
+}
+
 impl Drop for RustClr<'_> {
     fn drop(&mut self) {
         if let Some(cor_runtime_host) = &self.runtime.cor_runtime_host {
@@ -369,6 +606,19 @@ impl RustClrEnv {
             app_domain,
         })
     }
+
+    /// Evaluates a C# expression or method body in this environment's AppDomain and
+    /// converts the result, e.g. `eval_csharp("return DateTime.Now.Ticks;")`.
+    ///
+    /// See [`CSharpScript`] for the namespaces imported and how snippets are wrapped.
+    pub fn eval_csharp(&self, snippet: &str) -> Result<crate::PsValue> {
+        CSharpScript::new(&self.app_domain).eval(snippet)
+    }
+
+    /// Runs C# statements in this environment's AppDomain.
+    pub fn exec_csharp(&self, statements: &str) -> Result<()> {
+        CSharpScript::new(&self.app_domain).exec(statements)
+    }
 }
 
 impl Drop for RustClrEnv {
@@ -398,10 +648,25 @@ pub enum Invocation {
 #[derive(Clone)]
 pub enum ClrSource<'a> {
     /// File indicated by a string representing the file path.
+    ///
+    /// Read with [`read_file`], which rejects files over [`DEFAULT_MAX_FILE_SIZE`];
+    /// read larger ones with [`read_file_with_limit`] and pass them as [`Owned`](Self::Owned).
     File(&'a str),
 
     /// In-memory buffer containing the data.
     Buffer(&'a [u8]),
+
+    /// Buffer handed over to the `RustClr`, which frees it when dropped.
+    Owned(Vec<u8>),
+
+    /// Assembly encoded as base64 text; see [`codec::decode_base64`].
+    Base64(&'a str),
+
+    /// Assembly compressed as a raw DEFLATE stream; see [`codec::inflate`].
+    Deflate(&'a [u8]),
+
+    /// Assembly compressed with gzip; see [`codec::gunzip`].
+    Gzip(&'a [u8]),
 }
 
 impl<'a> From<&'a str> for ClrSource<'a> {
@@ -428,6 +693,12 @@ impl<'a> From<&'a Vec<u8>> for ClrSource<'a> {
     }
 }
 
+impl From<Vec<u8>> for ClrSource<'_> {
+    fn from(buffer: Vec<u8>) -> Self {
+        ClrSource::Owned(buffer)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -455,6 +726,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_with_command_line() -> Result<()> {
+        let output = RustClr::new("files/RustClr/bin/Release/RustClr.exe")?
+            .with_command_line(r#"rustclr "two words" C:\path\"#)
+            .with_output()
+            .run()?;
+
+        assert!(output.contains("- two words") && output.contains(r"- C:\path\"));
+        Ok(())
+    }
+
     #[test]
     fn test_without_args() -> Result<()> {
         let output = RustClr::new("files/RustClr/bin/Release/RustClr.exe")?
@@ -476,4 +758,17 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_owned_and_encoded_sources() -> Result<()> {
+        let buffer = read_file("files/RustClr/bin/Release/RustClr.exe")?;
+        let encoded = codec::encode_base64(&buffer);
+
+        for source in [ClrSource::Owned(buffer), ClrSource::Base64(&encoded)] {
+            let output = RustClr::new(source)?.with_output().run()?;
+            assert!(output.contains("[CLR] No args provided"));
+        }
+
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 use alloc::{
+    borrow::Cow,
     format,
     string::{String, ToString},
     vec::Vec,
@@ -16,8 +17,9 @@ use crate::error::{ClrError, Result};
 /// Holds the runtime state and execution configuration for the CLR.
 #[derive(Default, Clone)]
 pub struct RustClrRuntime<'a> {
-    /// Raw buffer containing the loaded .NET assembly.
-    pub buffer: &'a [u8],
+    /// Raw buffer containing the loaded .NET assembly, owned when it was read or
+    /// decoded by `RustClr` and freed on drop.
+    pub buffer: Cow<'a, [u8]>,
 
     /// Version of the .NET runtime to load.
     pub runtime_version: Option<RuntimeVersion>,
@@ -34,7 +36,7 @@ pub struct RustClrRuntime<'a> {
 
 impl<'a> RustClrRuntime<'a> {
     /// Creates a new `RustClrRuntime`.
-    pub fn new(buffer: &'a [u8]) -> Self {
+    pub fn new(buffer: Cow<'a, [u8]>) -> Self {
         Self {
             buffer,
             runtime_version: None,
@@ -157,7 +159,7 @@ impl<'a> RustClrRuntime<'a> {
 }
 
 /// Represents the .NET runtime versions supported by RustClr.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum RuntimeVersion {
     /// .NET Framework 2.0.
     V2,
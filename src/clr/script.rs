@@ -0,0 +1,306 @@
+//! Evaluation of C# snippets through a generated wrapper class.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use const_encrypt::obf;
+use windows::Win32::System::Variant::VARIANT;
+
+use super::CSharpCompiler;
+use super::wrapper::{Snippet, WRAPPER_CLASS, Wrapper};
+use crate::com::{_AppDomain, _MethodInfo, _Type};
+use crate::error::{ClrError, Result};
+use crate::pwsh::scalar;
+use crate::variant::create_safe_args;
+use crate::{Invocation, PsValue};
+
+/// Namespaces imported by every snippet unless replaced with [`CSharpScript::with_usings`].
+const DEFAULT_USINGS: [&str; 5] = [
+    "System",
+    "System.Collections.Generic",
+    "System.IO",
+    "System.Linq",
+    "System.Text",
+];
+
+/// Number of object levels converted into [`PsValue`]s.
+const CONVERSION_DEPTH: usize = 2;
+
+/// Compiles and runs C# snippets that are not complete programs.
+///
+/// The snippet becomes the body of a static method in a generated class, so it can
+/// use any statement a method can. Compiler diagnostics are remapped to the lines of
+/// the snippet; problems in the generated code itself are reported without a line.
+///
+/// # Example
+///
+/// ```
+/// let clr = RustClrEnv::new(None)?;
+/// let script = CSharpScript::new(&clr.app_domain).with_using("System.Diagnostics");
+///
+/// let id = script.eval("Process.GetCurrentProcess().Id")?;
+/// script.exec("Console.WriteLine(Environment.MachineName);")?;
+/// ```
+pub struct CSharpScript<'a> {
+    /// AppDomain snippets are compiled into and run in.
+    domain: &'a _AppDomain,
+
+    /// Namespaces imported by the wrapper.
+    usings: Vec<String>,
+
+    /// Assemblies referenced besides `mscorlib.dll`, `System.dll` and `System.Core.dll`.
+    references: Vec<String>,
+}
+
+impl<'a> CSharpScript<'a> {
+    /// Creates a script host importing `System`, `System.Collections.Generic`,
+    /// `System.IO`, `System.Linq` and `System.Text`.
+    pub fn new(domain: &'a _AppDomain) -> Self {
+        Self {
+            domain,
+            usings: DEFAULT_USINGS.iter().map(ToString::to_string).collect(),
+            references: Vec::new(),
+        }
+    }
+
+    /// Imports another namespace.
+    pub fn with_using(mut self, namespace: &str) -> Self {
+        if !self.usings.iter().any(|using| using == namespace) {
+            self.usings.push(namespace.to_string());
+        }
+        self
+    }
+
+    /// Replaces the imported namespaces.
+    pub fn with_usings<I, S>(mut self, namespaces: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.usings = namespaces.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// References another assembly by file name or full path.
+    pub fn with_reference(mut self, reference: &str) -> Self {
+        self.references.push(reference.to_string());
+        self
+    }
+
+    /// Evaluates `snippet` and converts its result into a [`PsValue`].
+    ///
+    /// The snippet is compiled as a single expression, e.g. `DateTime.Now.Ticks`, and
+    /// if that fails as a method body that must `return` a value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::CompilationFailed`] with lines relative to `snippet` if it
+    /// does not compile either way, or the exception it threw if it fails at runtime.
+    /// The diagnostics are those of the body when the snippet contains a `;`, and of
+    /// the expression otherwise.
+    pub fn eval(&self, snippet: &str) -> Result<PsValue> {
+        let value = match self.run(snippet, Snippet::Expression) {
+            Err(ClrError::CompilationFailed(expression)) => self
+                .run(snippet, Snippet::Body)
+                .map_err(|error| match error {
+                    ClrError::CompilationFailed(_) if !snippet.contains(';') => {
+                        ClrError::CompilationFailed(expression)
+                    }
+                    error => error,
+                })?,
+            result => result?,
+        };
+
+        Converter::new(self.domain)?.convert(value, CONVERSION_DEPTH)
+    }
+
+    /// Runs `statements` for their side effects.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`CSharpScript::eval`].
+    pub fn exec(&self, statements: &str) -> Result<()> {
+        self.run(statements, Snippet::Statements).map(|_| ())
+    }
+
+    /// Compiles `snippet` and calls the generated method.
+    fn run(&self, snippet: &str, kind: Snippet) -> Result<VARIANT> {
+        let wrapper = Wrapper::new(&self.usings, snippet, kind);
+
+        let compiler = self.references.iter().fold(
+            CSharpCompiler::new(self.domain).add_reference(&obf!("System.Core.dll").as_str()),
+            |compiler, reference| compiler.add_reference(reference),
+        );
+        let assembly = compiler
+            .compile(&wrapper.source)
+            .map_err(|error| match error {
+                ClrError::CompilationFailed(diagnostics) => {
+                    ClrError::CompilationFailed(wrapper.remap(diagnostics))
+                }
+                error => error,
+            })?;
+
+        assembly.resolve_type(WRAPPER_CLASS)?.invoke(
+            &obf!("Run").as_str(),
+            None,
+            None,
+            Invocation::Static,
+        )
+    }
+}
+
+/// Converts a snippet's result without relying on PowerShell being installed.
+struct Converter {
+    /// `IDictionary` and `IEnumerable`, used for type tests.
+    dictionary: _Type,
+    enumerable: _Type,
+
+    /// `Type.IsInstanceOfType`.
+    is_instance_of_type: _MethodInfo,
+
+    /// Members of `IDictionary`.
+    get_keys: _MethodInfo,
+    get_item: _MethodInfo,
+
+    /// Members of `IEnumerable` and `IEnumerator`.
+    get_enumerator: _MethodInfo,
+    move_next: _MethodInfo,
+    get_current: _MethodInfo,
+
+    /// `Object.ToString`.
+    to_string: _MethodInfo,
+}
+
+impl Converter {
+    fn new(domain: &_AppDomain) -> Result<Self> {
+        let mscorlib = domain.get_assembly(&obf!("mscorlib").as_str())?;
+        let dictionary = mscorlib.resolve_type(&obf!("System.Collections.IDictionary").as_str())?;
+        let enumerable = mscorlib.resolve_type(&obf!("System.Collections.IEnumerable").as_str())?;
+        let enumerator = mscorlib.resolve_type(&obf!("System.Collections.IEnumerator").as_str())?;
+        let object = mscorlib.resolve_type(&obf!("System.Object").as_str())?;
+        let ty = mscorlib.resolve_type(&obf!("System.Type").as_str())?;
+
+        Ok(Self {
+            is_instance_of_type: ty
+                .method_signature(&obf!("Boolean IsInstanceOfType(System.Object)").as_str())?,
+            get_keys: dictionary
+                .method_signature(&obf!("System.Collections.ICollection get_Keys()").as_str())?,
+            get_item: dictionary
+                .method_signature(&obf!("System.Object get_Item(System.Object)").as_str())?,
+            get_enumerator: enumerable.method_signature(
+                &obf!("System.Collections.IEnumerator GetEnumerator()").as_str(),
+            )?,
+            move_next: enumerator.method_signature(&obf!("Boolean MoveNext()").as_str())?,
+            get_current: enumerator
+                .method_signature(&obf!("System.Object get_Current()").as_str())?,
+            to_string: object.method_signature(&obf!("System.String ToString()").as_str())?,
+            dictionary,
+            enumerable,
+        })
+    }
+
+    /// Converts primitives directly, dictionaries and sequences `depth` levels deep, and
+    /// anything else into its string form.
+    fn convert(&self, value: VARIANT, depth: usize) -> Result<PsValue> {
+        if let Some(scalar) = scalar(&value) {
+            return Ok(scalar);
+        }
+
+        if depth > 0 && self.is_instance(&self.dictionary, &value)? {
+            let keys = self.get_keys.invoke(Some(value.clone()), None)?;
+            let mut entries = Vec::new();
+            for key in self.enumerate(keys)? {
+                let args = create_safe_args(vec![key.clone()])?;
+                let item = self.get_item.invoke(Some(value.clone()), Some(&args))?;
+                entries.push((
+                    self.convert(key, depth - 1)?,
+                    self.convert(item, depth - 1)?,
+                ));
+            }
+            return Ok(PsValue::Hashtable(entries));
+        }
+
+        if depth > 0 && self.is_instance(&self.enumerable, &value)? {
+            return self
+                .enumerate(value)?
+                .into_iter()
+                .map(|item| self.convert(item, depth - 1))
+                .collect::<Result<Vec<_>>>()
+                .map(PsValue::Array);
+        }
+
+        let value = self.to_string.invoke(Some(value), None)?;
+        Ok(PsValue::String(value.to_string()))
+    }
+
+    /// Tests whether `value` is an instance of `ty`.
+    fn is_instance(&self, ty: &_Type, value: &VARIANT) -> Result<bool> {
+        let args = create_safe_args(vec![value.clone()])?;
+        let result = self
+            .is_instance_of_type
+            .invoke(Some(ty.to_variant()), Some(&args))?;
+
+        Ok(unsafe { result.Anonymous.Anonymous.Anonymous.boolVal }.as_bool())
+    }
+
+    /// Collects every element of an `IEnumerable`.
+    fn enumerate(&self, collection: VARIANT) -> Result<Vec<VARIANT>> {
+        let enumerator = self.get_enumerator.invoke(Some(collection), None)?;
+        let mut items = Vec::new();
+        loop {
+            let has_next = self.move_next.invoke(Some(enumerator.clone()), None)?;
+            if !unsafe { has_next.Anonymous.Anonymous.Anonymous.boolVal }.as_bool() {
+                break;
+            }
+            items.push(self.get_current.invoke(Some(enumerator.clone()), None)?);
+        }
+
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RustClrEnv;
+
+    #[test]
+    fn test_eval() -> Result<()> {
+        let clr = RustClrEnv::new(None)?;
+        let script = CSharpScript::new(&clr.app_domain);
+
+        assert_eq!(script.eval("6 * 7")?, PsValue::Int(42));
+        assert_eq!(
+            script
+                .eval("var words = new[] { \"a\", \"b\" };\nreturn string.Join(\",\", words);")?,
+            PsValue::String("a,b".into())
+        );
+        assert_eq!(
+            script.eval("string.Join(\";\", new[] { \"a\", \"b\" })")?,
+            PsValue::String("a;b".into())
+        );
+        assert_eq!(
+            script.eval("Enumerable.Range(1, 3)")?,
+            PsValue::Array(vec![PsValue::Int(1), PsValue::Int(2), PsValue::Int(3)])
+        );
+        script.exec("Environment.SetEnvironmentVariable(\"RUSTCLR_EVAL\", \"1\");")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_eval_reports_snippet_lines() -> Result<()> {
+        let clr = RustClrEnv::new(None)?;
+        let error = CSharpScript::new(&clr.app_domain)
+            .eval("var a = 1;\nvar b = a +;\nreturn b;")
+            .expect_err("snippet should not compile");
+
+        let ClrError::CompilationFailed(diagnostics) = error else {
+            panic!("unexpected error: {error}");
+        };
+        assert_eq!(diagnostics[0].line, 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+//! Native worker threads, deadlines and cancellation used to bound managed execution.
+
+use alloc::{boxed::Box, string::ToString, sync::Arc, vec};
+use core::{ffi::c_void, time::Duration};
+
+use const_encrypt::obf;
+use spin::Mutex;
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::System::SystemInformation::GetTickCount64;
+use windows::Win32::System::Threading::{
+    CreateEventW, CreateThread, INFINITE, ResetEvent, SetEvent, THREAD_CREATION_FLAGS,
+    WaitForMultipleObjects, WaitForSingleObject,
+};
+use windows::core::{Owned, PCWSTR};
+
+use crate::error::{ClrError, Result};
+use crate::sync::Wake;
+
+/// Handle used to cancel a running execution from another thread.
+///
+/// Cloning the handle is cheap; all clones refer to the same cancellation state.
+///
+/// # Example
+///
+/// ```
+/// use rustclr::{CancelHandle, RustClr};
+///
+/// let cancel = CancelHandle::new()?;
+/// let mut clr = RustClr::new("examples/sample.exe")?
+///     .with_cancel_handle(cancel.clone());
+///
+/// // From any other thread: cancel.cancel();
+/// let output = clr.run()?;
+/// ```
+#[derive(Clone)]
+pub struct CancelHandle(Arc<Event>);
+
+impl CancelHandle {
+    /// Creates a new, non-cancelled `CancelHandle`.
+    pub fn new() -> Result<Self> {
+        Ok(Self(Arc::new(Event::new(true)?)))
+    }
+
+    /// Requests cancellation of every execution observing this handle.
+    pub fn cancel(&self) {
+        self.0.set();
+    }
+
+    /// Returns `true` once [`CancelHandle::cancel`] has been called.
+    pub fn is_cancelled(&self) -> bool {
+        unsafe { WaitForSingleObject(self.0.0, 0) == WAIT_OBJECT_0 }
+    }
+
+    /// Clears a previous cancellation so the handle can be reused.
+    pub fn reset(&self) {
+        unsafe {
+            let _ = ResetEvent(self.0.0);
+        }
+    }
+}
+
+/// Owned Win32 event object.
+pub(crate) struct Event(HANDLE);
+
+impl Event {
+    /// Creates a non-signalled event; auto-reset events wake a single waiter.
+    pub fn new(manual_reset: bool) -> Result<Self> {
+        let event = unsafe { CreateEventW(None, manual_reset, false, PCWSTR::null()) }
+            .map_err(|err| ClrError::ApiError(obf!("CreateEventW").to_string(), err.code().0))?;
+
+        Ok(Self(event))
+    }
+
+    /// Signals the event.
+    pub fn set(&self) {
+        unsafe {
+            let _ = SetEvent(self.0);
+        }
+    }
+
+    /// Blocks until the event is signalled.
+    pub fn wait(&self) {
+        unsafe { WaitForSingleObject(self.0, INFINITE) };
+    }
+}
+
+// SAFETY: kernel event handles may be signalled and waited on from any thread.
+unsafe impl Send for Event {}
+unsafe impl Sync for Event {}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+/// [`Wake`] flag backed by an auto-reset Win32 event.
+pub(crate) struct Signal(Event);
+
+impl Signal {
+    /// Creates a lowered flag; usable as a [`NewWake`](crate::sync::NewWake).
+    pub fn boxed() -> Result<Box<dyn Wake>> {
+        Ok(Box::new(Self(Event::new(false)?)))
+    }
+}
+
+impl Wake for Signal {
+    fn raise(&self) {
+        self.0.set();
+    }
+
+    fn wait(&self) {
+        self.0.wait();
+    }
+}
+
+/// Point in time after which a bounded operation must give up.
+pub(crate) struct Deadline(Option<u64>);
+
+impl Deadline {
+    /// Creates a deadline `timeout` from now, or one that never expires.
+    pub fn after(timeout: Option<Duration>) -> Self {
+        Self(timeout.map(|timeout| {
+            let millis = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+            unsafe { GetTickCount64() }.saturating_add(millis)
+        }))
+    }
+
+    /// Returns `true` once the deadline has passed.
+    pub fn expired(&self) -> bool {
+        self.0.is_some_and(|end| unsafe { GetTickCount64() } >= end)
+    }
+}
+
+/// Outcome of waiting on a [`WorkerThread`].
+pub(crate) enum Wait {
+    /// The thread ran to completion and its result can be joined.
+    Finished,
+
+    /// The timeout elapsed first.
+    TimedOut,
+
+    /// The [`CancelHandle`] was signalled first.
+    Cancelled,
+}
+
+/// A native thread running a single closure whose result is collected by [`WorkerThread::join`].
+pub(crate) struct WorkerThread<T> {
+    /// Handle of the native thread.
+    handle: Owned<HANDLE>,
+
+    /// Slot the closure writes its result into before the thread exits.
+    result: Arc<Mutex<Option<Result<T>>>>,
+}
+
+impl<T: 'static> WorkerThread<T> {
+    /// Spawns `f` on a new native thread.
+    ///
+    /// # Safety
+    ///
+    /// `f` is not required to be `Send` so it can capture COM interfaces. The caller
+    /// must only move agile objects, such as the CLR's own interfaces, into it.
+    pub unsafe fn spawn<F>(f: F) -> Result<Self>
+    where
+        F: FnOnce() -> Result<T> + 'static,
+    {
+        let result = Arc::new(Mutex::new(None));
+        let slot = result.clone();
+        let start: Box<Box<dyn FnOnce()>> = Box::new(Box::new(move || {
+            let value = f();
+            *slot.lock() = Some(value);
+        }));
+
+        let param = Box::into_raw(start);
+        let handle = unsafe {
+            CreateThread(
+                None,
+                0,
+                Some(thread_start),
+                Some(param as *const c_void),
+                THREAD_CREATION_FLAGS(0),
+                None,
+            )
+        };
+
+        match handle {
+            Ok(handle) => Ok(Self {
+                handle: unsafe { Owned::new(handle) },
+                result,
+            }),
+            Err(err) => {
+                // The thread never started, so the closure is still ours to free
+                drop(unsafe { Box::from_raw(param) });
+                Err(ClrError::ApiError(
+                    obf!("CreateThread").to_string(),
+                    err.code().0,
+                ))
+            }
+        }
+    }
+
+    /// Waits until the thread exits, `timeout` elapses or `cancel` is signalled.
+    pub fn wait(&self, timeout: Option<Duration>, cancel: Option<&CancelHandle>) -> Wait {
+        let millis = timeout.map_or(INFINITE, |timeout| {
+            u32::try_from(timeout.as_millis()).map_or(INFINITE - 1, |ms| ms.min(INFINITE - 1))
+        });
+
+        let mut handles = vec![*self.handle];
+        if let Some(cancel) = cancel {
+            handles.push(cancel.0.0);
+        }
+
+        let event = unsafe { WaitForMultipleObjects(&handles, false, millis) };
+        if event == WAIT_OBJECT_0 {
+            Wait::Finished
+        } else if event.0 == WAIT_OBJECT_0.0 + 1 {
+            Wait::Cancelled
+        } else {
+            Wait::TimedOut
+        }
+    }
+
+    /// Waits for the thread to exit and returns the closure's result.
+    pub fn join(self) -> Result<T> {
+        unsafe { WaitForSingleObject(*self.handle, INFINITE) };
+        self.result.lock().take().unwrap_or(Err(ClrError::Msg(
+            obf!("worker thread exited without a result").to_string(),
+        )))
+    }
+}
+
+/// Entry point of every [`WorkerThread`].
+unsafe extern "system" fn thread_start(param: *mut c_void) -> u32 {
+    let start = unsafe { Box::from_raw(param as *mut Box<dyn FnOnce()>) };
+    start();
+    0
+}
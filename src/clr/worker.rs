@@ -0,0 +1,134 @@
+//! The thread behind a [`ClrWorker`], hosting a real runtime.
+
+use alloc::{string::ToString, sync::Arc, vec::Vec};
+
+use const_encrypt::obf;
+use windows::Win32::System::Com::{COINIT_MULTITHREADED, CoInitializeEx, CoUninitialize};
+use windows::core::BSTR;
+
+use super::thread::{Signal, WorkerThread};
+use super::{RuntimeVersion, RustClrEnv, run_entry_point};
+use crate::Invocation;
+use crate::com::_Assembly;
+use crate::error::{ClrError, Result};
+use crate::sync::worker::{AssemblyId, Backend, ClrWorker, Request, Response, channel, serve};
+
+impl ClrWorker {
+    /// Starts a new worker thread hosting the requested runtime version.
+    ///
+    /// # Errors
+    ///
+    /// Returned when the thread cannot be created or when the runtime fails to start
+    /// on it.
+    pub fn spawn(runtime_version: Option<RuntimeVersion>) -> Result<Self> {
+        let (worker, mailbox) = channel(Signal::boxed)?;
+        let started = Arc::new(mailbox.reply()?);
+        let report = started.clone();
+
+        // SAFETY: only owned Rust values cross into the worker; every COM object is
+        // created and released on the worker thread itself
+        let thread = unsafe {
+            WorkerThread::spawn(move || {
+                let startup = Apartment::enter().and_then(|apartment| {
+                    let env = RustClrEnv::new(runtime_version)?;
+                    Ok((apartment, ClrBackend::new(env)))
+                });
+
+                // The apartment outlives the backend so COM is torn down last
+                match startup {
+                    Ok((_apartment, backend)) => {
+                        report.send(Ok(Response::Started));
+                        serve(backend, &mailbox);
+                    }
+                    Err(err) => report.send(Err(err)),
+                }
+
+                Ok(())
+            })?
+        };
+
+        // The thread runs detached; its lifetime is tied to the mailbox instead
+        drop(thread);
+        started.recv()?;
+
+        Ok(worker)
+    }
+}
+
+/// Backend hosting a real runtime.
+struct ClrBackend {
+    /// Runtime and application domain owned by the worker.
+    env: RustClrEnv,
+
+    /// Assemblies loaded so far, indexed by [`AssemblyId`].
+    assemblies: Vec<_Assembly>,
+}
+
+impl ClrBackend {
+    fn new(env: RustClrEnv) -> Self {
+        Self {
+            env,
+            assemblies: Vec::new(),
+        }
+    }
+}
+
+impl Backend for ClrBackend {
+    fn handle(&mut self, request: Request) -> Result<Response> {
+        match request {
+            Request::Load(buffer) => {
+                let assembly = self.env.app_domain.load_bytes(&buffer)?;
+                self.assemblies.push(assembly);
+                Ok(Response::Loaded(AssemblyId(self.assemblies.len() - 1)))
+            }
+            Request::Invoke {
+                assembly,
+                type_name,
+                method,
+                args,
+            } => {
+                let assembly = self
+                    .assemblies
+                    .get(assembly.0)
+                    .ok_or(ClrError::Msg(obf!("unknown assembly id").to_string()))?;
+                let args = args
+                    .iter()
+                    .map(|arg| BSTR::from(arg.as_str()).into())
+                    .collect();
+                let result = assembly.resolve_type(&type_name)?.invoke(
+                    &method,
+                    None,
+                    Some(args),
+                    Invocation::Static,
+                )?;
+
+                Ok(Response::Output(result.to_string()))
+            }
+            Request::Run { buffer, args } => {
+                run_entry_point(&self.env.app_domain, &buffer, &[], args, true, true)
+                    .map(Response::Output)
+            }
+            Request::Shutdown => Ok(Response::Stopped),
+        }
+    }
+}
+
+/// Keeps the calling thread in the multithreaded COM apartment while alive.
+struct Apartment;
+
+impl Apartment {
+    fn enter() -> Result<Self> {
+        let hr = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) };
+        if hr.is_err() {
+            return Err(ClrError::ApiError(obf!("CoInitializeEx").to_string(), hr.0));
+        }
+
+        Ok(Self)
+    }
+}
+
+impl Drop for Apartment {
+    fn drop(&mut self) {
+        unsafe { CoUninitialize() };
+    }
+}
@@ -0,0 +1,169 @@
+//! C# source generated around the snippets run by `CSharpScript`.
+
+use alloc::{string::String, vec::Vec};
+
+use super::Diagnostic;
+
+/// Name of the generated class holding the snippet.
+pub const WRAPPER_CLASS: &str = "RustClrSnippet";
+
+/// How a snippet is placed in the generated method.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Snippet {
+    /// Returned from a method returning `object`.
+    Expression,
+
+    /// Body of a method returning `object`.
+    Body,
+
+    /// Body of a method returning `void`.
+    Statements,
+}
+
+/// C# source generated around a snippet.
+pub struct Wrapper {
+    /// Complete compilation unit.
+    pub source: String,
+
+    /// 1-based line of `source` holding the first line of the snippet.
+    first_line: u32,
+
+    /// Number of lines in the snippet.
+    lines: u32,
+}
+
+impl Wrapper {
+    /// Generates the class around `snippet`, which starts on a line of its own so
+    /// columns need no adjusting.
+    pub fn new(usings: &[String], snippet: &str, kind: Snippet) -> Self {
+        let mut source = String::new();
+        for using in usings {
+            source.push_str("using ");
+            source.push_str(using);
+            source.push_str(";\n");
+        }
+
+        source.push_str("public static class ");
+        source.push_str(WRAPPER_CLASS);
+        source.push_str("\n{\n");
+        source.push_str(match kind {
+            Snippet::Statements => "    public static void Run()\n    {\n",
+            Snippet::Expression | Snippet::Body => "    public static object Run()\n    {\n",
+        });
+        if kind == Snippet::Expression {
+            source.push_str("return\n");
+        }
+
+        let first_line = line_count(&source) + 1;
+        source.push_str(snippet);
+        source.push('\n');
+
+        if kind == Snippet::Expression {
+            source.push_str(";\n");
+        }
+        source.push_str("    }\n}\n");
+
+        Self {
+            source,
+            first_line,
+            lines: line_count(snippet) + 1,
+        }
+    }
+
+    /// Makes `diagnostics` point into the snippet instead of the generated source.
+    pub fn remap(&self, mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        for diagnostic in &mut diagnostics {
+            // The snippet has no file; the compiler reports a temporary one
+            diagnostic.file.clear();
+
+            match diagnostic
+                .line
+                .checked_sub(self.first_line)
+                .filter(|&line| line < self.lines)
+            {
+                Some(line) => diagnostic.line = line + 1,
+                None => {
+                    diagnostic.line = 0;
+                    diagnostic.column = 0;
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// Counts the line breaks in `text`.
+fn line_count(text: &str) -> u32 {
+    text.bytes().filter(|&byte| byte == b'\n').count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use super::*;
+    use crate::clr::Severity;
+
+    fn diagnostic(line: u32, column: u32) -> Diagnostic {
+        Diagnostic {
+            file: "C:\\Temp\\x1y2z3.0.cs".into(),
+            line,
+            column,
+            code: "CS1002".into(),
+            severity: Severity::Error,
+            message: "; expected".into(),
+        }
+    }
+
+    #[test]
+    fn test_wrapper_places_snippet_on_its_own_lines() {
+        let usings = ["System".to_string(), "System.Linq".to_string()];
+        let wrapper = Wrapper::new(&usings, "var x = 1;\nreturn x;", Snippet::Body);
+
+        let lines = wrapper.source.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "using System;");
+        assert_eq!(lines[1], "using System.Linq;");
+        assert_eq!(lines[wrapper.first_line as usize - 1], "var x = 1;");
+        assert_eq!(lines[wrapper.first_line as usize], "return x;");
+        assert_eq!(wrapper.lines, 2);
+        assert!(wrapper.source.contains("public static object Run()"));
+    }
+
+    #[test]
+    fn test_wrapper_kinds() {
+        let expression = Wrapper::new(&[], "DateTime.Now.Ticks", Snippet::Expression);
+        let lines = expression.source.lines().collect::<Vec<_>>();
+        assert_eq!(lines[expression.first_line as usize - 2], "return");
+        assert_eq!(
+            lines[expression.first_line as usize - 1],
+            "DateTime.Now.Ticks"
+        );
+        assert_eq!(lines[expression.first_line as usize], ";");
+
+        let statements = Wrapper::new(&[], "Console.WriteLine(1);", Snippet::Statements);
+        assert!(statements.source.contains("public static void Run()"));
+        assert!(!statements.source.contains("return"));
+    }
+
+    #[test]
+    fn test_remap() {
+        let wrapper = Wrapper::new(&[], "int a = 1\nint b = 2;\nreturn a + b;", Snippet::Body);
+        let first = wrapper.first_line;
+
+        let remapped = wrapper.remap(vec![
+            diagnostic(first, 10),
+            diagnostic(first + 2, 5),
+            diagnostic(first + 3, 1),
+            diagnostic(first - 1, 4),
+            diagnostic(0, 0),
+        ]);
+
+        let positions = remapped
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.column))
+            .collect::<Vec<_>>();
+        assert_eq!(positions, [(1, 10), (3, 5), (0, 0), (0, 0), (0, 0)]);
+        assert!(remapped.iter().all(|diagnostic| diagnostic.file.is_empty()));
+    }
+}
@@ -0,0 +1,237 @@
+//! Splitting and quoting of Windows command lines.
+//!
+//! [`split`] follows `CommandLineToArgvW`, which the CRT's `main` and the .NET
+//! Framework's `Main(string[])` both agree with for everything after the program name:
+//!
+//! - Arguments are separated by spaces and tabs outside of double quotes.
+//! - `2n` backslashes followed by `"` produce `n` backslashes, and the quote opens or
+//!   closes a quoted section.
+//! - `2n + 1` backslashes followed by `"` produce `n` backslashes and a literal `"`.
+//! - Backslashes not followed by `"` are literal.
+//! - `""` inside a quoted section produces a literal `"` and closes the section. Since
+//!   Visual Studio 2008 the CRT keeps the section open instead; [`quote`] never emits
+//!   this sequence, so its output splits the same way under both.
+
+use alloc::{string::String, vec::Vec};
+
+/// Splits `command_line` into arguments.
+///
+/// The command line holds arguments only; unlike `CommandLineToArgvW`, the first one
+/// is not parsed as a program name.
+///
+/// # Example
+///
+/// ```
+/// use rustclr::cmdline::split;
+///
+/// let args = split(r#"triage /consoleoutfile:"C:\a b\out.txt""#);
+/// assert_eq!(args, ["triage", r"/consoleoutfile:C:\a b\out.txt"]);
+/// ```
+pub fn split(command_line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut started = false;
+
+    // Quotes seen in the current run; odd while a quoted section is open
+    let mut quotes = 0;
+    let mut backslashes = 0;
+
+    let mut chars = command_line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' if quotes == 0 => {
+                if started {
+                    args.push(core::mem::take(&mut arg));
+                    started = false;
+                }
+                backslashes = 0;
+                continue;
+            }
+            '\\' => {
+                arg.push('\\');
+                backslashes += 1;
+            }
+            '"' => {
+                // Half of the backslashes escape the other half
+                arg.truncate(arg.len() - backslashes / 2);
+                if backslashes % 2 == 0 {
+                    quotes += 1;
+                } else {
+                    arg.pop();
+                    arg.push('"');
+                }
+                backslashes = 0;
+
+                // Every third quote of a run is literal
+                while chars.next_if_eq(&'"').is_some() {
+                    quotes += 1;
+                    if quotes == 3 {
+                        arg.push('"');
+                        quotes = 0;
+                    }
+                }
+                if quotes == 2 {
+                    quotes = 0;
+                }
+            }
+            c => {
+                arg.push(c);
+                backslashes = 0;
+            }
+        }
+
+        started = true;
+    }
+
+    if started {
+        args.push(arg);
+    }
+
+    args
+}
+
+/// Quotes `arg` so that [`split`] returns it unchanged.
+///
+/// Arguments without whitespace or quotes are returned as they are.
+pub fn quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+        return arg.into();
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Escape the backslashes and the quote itself
+                quoted.extend(core::iter::repeat_n('\\', backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            c => {
+                quoted.extend(core::iter::repeat_n('\\', backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+
+    // Backslashes before the closing quote must not escape it
+    quoted.extend(core::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Builds a command line from `args`, quoting each one as needed.
+pub fn join<I, S>(args: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut command_line = String::new();
+    for arg in args {
+        if !command_line.is_empty() {
+            command_line.push(' ');
+        }
+        command_line.push_str(&quote(arg.as_ref()));
+    }
+
+    command_line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_split_documented_examples() {
+        // Examples from "Parsing C++ command-line arguments" on Microsoft Learn
+        assert_eq!(split(r#""a b c" d e"#), ["a b c", "d", "e"]);
+        assert_eq!(split(r#""ab\"c" "\\" d"#), [r#"ab"c"#, r"\", "d"]);
+        assert_eq!(split(r#"a\\\b d"e f"g h"#), [r"a\\\b", "de fg", "h"]);
+        assert_eq!(split(r#"a\\\"b c d"#), [r#"a\"b"#, "c", "d"]);
+        assert_eq!(split(r#"a\\\\"b c" d e"#), [r"a\\b c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_split_whitespace_and_empty_arguments() {
+        assert!(split("").is_empty());
+        assert!(split(" \t ").is_empty());
+        assert_eq!(split("  a\t\tb  "), ["a", "b"]);
+        assert_eq!(split(r#"a "" b"#), ["a", "", "b"]);
+        assert_eq!(split("\"\""), [""]);
+        assert_eq!(split("\""), [""]);
+    }
+
+    #[test]
+    fn test_split_quote_runs() {
+        // `""` inside quotes is a literal quote that closes the section
+        assert_eq!(split(r#"a"b"" c d"#), [r#"ab""#, "c", "d"]);
+        assert_eq!(split(r#""a""b""#), [r#"a"b"#]);
+        assert_eq!(split("\"\"\""), ["\""]);
+        assert_eq!(split("\"\"\"\"\"\""), ["\"\""]);
+    }
+
+    #[test]
+    fn test_split_request_example() {
+        assert_eq!(
+            split(r#"triage /consoleoutfile:"C:\a b\out.txt""#),
+            ["triage", r"/consoleoutfile:C:\a b\out.txt"]
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("plain"), "plain");
+        assert_eq!(quote(r"C:\dir\"), r"C:\dir\");
+        assert_eq!(quote(""), r#""""#);
+        assert_eq!(quote("a b"), r#""a b""#);
+        assert_eq!(quote(r"C:\a b\"), r#""C:\a b\\""#);
+        assert_eq!(quote(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote(r#"\"x"#), r#""\\\"x""#);
+        assert_eq!(join(["triage", "a b", ""]), r#"triage "a b" """#);
+    }
+
+    fn arg() -> impl Strategy<Value = String> {
+        // Bias towards the characters the rules treat specially
+        prop::collection::vec(
+            prop_oneof![
+                4 => prop::sample::select(vec![' ', '\t', '"', '\\', 'a', 'é']),
+                1 => any::<char>(),
+            ],
+            0..12,
+        )
+        .prop_map(|chars| chars.into_iter().collect())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_join_then_split_round_trips(args in prop::collection::vec(arg(), 0..6)) {
+            prop_assert_eq!(split(&join(&args)), args);
+        }
+
+        #[test]
+        fn prop_quote_is_a_single_argument(arg in arg()) {
+            prop_assert_eq!(split(&quote(&arg)), [arg]);
+        }
+
+        #[test]
+        fn prop_split_is_stable(line in "[ a\\\\\"\t]{0,24}") {
+            // Re-joining what was split must describe the same arguments
+            let args = split(&line);
+            prop_assert_eq!(split(&join(&args)), args);
+        }
+
+        #[test]
+        fn prop_split_never_yields_unquoted_whitespace(line in "[ ab\t]{0,24}") {
+            for arg in split(&line) {
+                prop_assert!(!arg.is_empty() && !arg.contains([' ', '\t']), "{:?}", arg);
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use alloc::{string::String, string::ToString, vec::Vec};
+
+use const_encrypt::obf;
+
+use crate::error::{ClrError, Result};
+
+/// Standard base64 alphabet (RFC 4648, section 4).
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Decodes base64 text.
+///
+/// Accepts the standard and the URL-safe alphabet, with or without `=` padding.
+/// ASCII whitespace is skipped, so wrapped text decodes as is.
+///
+/// # Errors
+///
+/// Returns [`ClrError::InvalidEncoding`] for characters outside the alphabet, data
+/// after the padding, or a length no encoder produces.
+pub fn decode_base64(encoded: &str) -> Result<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3);
+    let mut quad = 0u32;
+    let mut digits = 0;
+    let mut padding = 0;
+
+    for byte in encoded.bytes() {
+        if byte.is_ascii_whitespace() {
+            continue;
+        }
+
+        if byte == b'=' {
+            padding += 1;
+            continue;
+        }
+
+        if padding > 0 {
+            return Err(invalid(obf!("data after base64 padding").as_str()));
+        }
+
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(invalid(obf!("invalid base64 character").as_str())),
+        };
+
+        quad = quad << 6 | u32::from(value);
+        digits += 1;
+        if digits == 4 {
+            decoded.extend_from_slice(&quad.to_be_bytes()[1..]);
+            quad = 0;
+            digits = 0;
+        }
+    }
+
+    // A final group of 2 or 3 digits holds 1 or 2 bytes; padding, when present,
+    // must complete it
+    match digits {
+        0 if padding == 0 => {}
+        2 if padding == 0 || padding == 2 => decoded.push((quad >> 4) as u8),
+        3 if padding == 0 || padding == 1 => {
+            decoded.extend_from_slice(&((quad >> 2) as u16).to_be_bytes());
+        }
+        _ => return Err(invalid(obf!("invalid base64 length").as_str())),
+    }
+
+    Ok(decoded)
+}
+
+/// Encodes `data` as padded base64 with the standard alphabet.
+pub fn encode_base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let mut group = [0; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * index) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Builds the error returned for malformed input.
+fn invalid(reason: &str) -> ClrError {
+    ClrError::InvalidEncoding(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_rfc4648_vectors() {
+        let vectors: [(&[u8], &str); 7] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"fooba", "Zm9vYmE="),
+            (b"foobar", "Zm9vYmFy"),
+        ];
+
+        for (data, encoded) in vectors {
+            assert_eq!(encode_base64(data), encoded);
+            assert_eq!(decode_base64(encoded).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_lenient_input() {
+        assert_eq!(decode_base64("Zm9v\r\nYmE=\n").unwrap(), b"fooba");
+        assert_eq!(decode_base64("Zm9vYmE").unwrap(), b"fooba");
+        assert_eq!(decode_base64("Zg").unwrap(), b"f");
+        assert_eq!(
+            decode_base64("-_8=").unwrap(),
+            decode_base64("+/8=").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_input() {
+        for encoded in [
+            "Z",
+            "Zm9vY",
+            "Zg=",
+            "Zm8==",
+            "Zg==Zg==",
+            "Zm9v!",
+            "Zm9v\u{e9}",
+            "=",
+        ] {
+            assert!(
+                matches!(decode_base64(encoded), Err(ClrError::InvalidEncoding(_))),
+                "{encoded:?} decoded"
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(data in proptest::collection::vec(any::<u8>(), 0..512)) {
+            prop_assert_eq!(decode_base64(&encode_base64(&data)).unwrap(), data);
+        }
+    }
+}
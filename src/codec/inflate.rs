@@ -0,0 +1,648 @@
+use alloc::{string::ToString, vec, vec::Vec};
+
+use const_encrypt::obf;
+
+use crate::error::{ClrError, Result};
+
+/// Largest output [`inflate`] and [`gunzip`] produce: 1 GiB, the same limit `read_file`
+/// puts on assemblies read from disk.
+pub const DEFAULT_MAX_OUTPUT_SIZE: usize = 1 << 30;
+
+/// Longest Huffman code allowed by DEFLATE.
+const MAX_BITS: usize = 15;
+
+/// Literal/length symbols, including the two a valid stream never uses.
+const MAX_LITERALS: usize = 288;
+
+/// Distance symbols, including the two a valid stream never uses.
+const MAX_DISTANCES: usize = 32;
+
+/// Base lengths and extra bits of length symbols 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances and extra bits of distance symbols 0 to 29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order in which the code lengths of the code length alphabet are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Gzip member header flags (RFC 1952, section 2.3.1).
+const GZIP_FHCRC: u8 = 0x02;
+const GZIP_FEXTRA: u8 = 0x04;
+const GZIP_FNAME: u8 = 0x08;
+const GZIP_FCOMMENT: u8 = 0x10;
+const GZIP_RESERVED: u8 = 0xE0;
+
+/// CRC-32 lookup table for the reflected polynomial `0xEDB88320`.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+/// Decompresses a raw DEFLATE stream (RFC 1951), as written by .NET's `DeflateStream`.
+///
+/// Output larger than [`DEFAULT_MAX_OUTPUT_SIZE`] is rejected; use
+/// [`inflate_with_limit`] to choose another limit.
+///
+/// # Errors
+///
+/// Returns [`ClrError::InvalidEncoding`] when the stream is truncated or malformed, or
+/// decompresses to more than the limit.
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>> {
+    inflate_with_limit(data, DEFAULT_MAX_OUTPUT_SIZE)
+}
+
+/// Decompresses a raw DEFLATE stream, rejecting output larger than `max_size` bytes.
+///
+/// # Errors
+///
+/// Same as [`inflate`], with `max_size` as the limit.
+pub fn inflate_with_limit(data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len().saturating_mul(2).min(max_size));
+    Inflater::new(data, max_size).run(&mut output)?;
+    Ok(output)
+}
+
+/// Decompresses gzip data (RFC 1952), as written by `gzip` or .NET's `GZipStream`.
+///
+/// Concatenated members are decompressed one after another, and the CRC-32 and size
+/// stored after each member are checked. Output larger than [`DEFAULT_MAX_OUTPUT_SIZE`]
+/// in total is rejected; use [`gunzip_with_limit`] to choose another limit.
+///
+/// # Errors
+///
+/// Returns [`ClrError::InvalidEncoding`] when a header is invalid, the compressed data
+/// is truncated or malformed, a checksum does not match, or the members decompress to
+/// more than the limit.
+pub fn gunzip(data: &[u8]) -> Result<Vec<u8>> {
+    gunzip_with_limit(data, DEFAULT_MAX_OUTPUT_SIZE)
+}
+
+/// Decompresses gzip data, rejecting output larger than `max_size` bytes in total.
+///
+/// # Errors
+///
+/// Same as [`gunzip`], with `max_size` as the limit.
+pub fn gunzip_with_limit(data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len().saturating_mul(2).min(max_size));
+    let mut rest = data;
+
+    loop {
+        let start = output.len();
+        let body = gzip_header(rest)?;
+
+        let mut inflater = Inflater::new(&rest[body..], max_size);
+        inflater.run(&mut output)?;
+        let trailer = body + inflater.consumed();
+
+        let footer = rest.get(trailer..trailer + 8).ok_or_else(truncated)?;
+        let crc = u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]);
+        let size = u32::from_le_bytes([footer[4], footer[5], footer[6], footer[7]]);
+        if crc != crc32(&output[start..]) || size != (output.len() - start) as u32 {
+            return Err(invalid(obf!("gzip checksum mismatch").as_str()));
+        }
+
+        rest = &rest[trailer + 8..];
+        if rest.is_empty() {
+            return Ok(output);
+        }
+    }
+}
+
+/// Validates a gzip member header and returns the offset of its compressed data.
+fn gzip_header(data: &[u8]) -> Result<usize> {
+    let header = data.get(..10).ok_or_else(truncated)?;
+    if header[..3] != [0x1F, 0x8B, 0x08] {
+        return Err(invalid(obf!("not gzip data").as_str()));
+    }
+
+    let flags = header[3];
+    if flags & GZIP_RESERVED != 0 {
+        return Err(invalid(obf!("reserved gzip flags set").as_str()));
+    }
+
+    let mut offset = 10;
+    if flags & GZIP_FEXTRA != 0 {
+        let length = data.get(offset..offset + 2).ok_or_else(truncated)?;
+        offset += 2 + usize::from(u16::from_le_bytes([length[0], length[1]]));
+    }
+
+    // The file name and comment are zero-terminated
+    for flag in [GZIP_FNAME, GZIP_FCOMMENT] {
+        if flags & flag != 0 {
+            let field = data.get(offset..).ok_or_else(truncated)?;
+            offset += field
+                .iter()
+                .position(|&byte| byte == 0)
+                .ok_or_else(truncated)?
+                + 1;
+        }
+    }
+
+    if flags & GZIP_FHCRC != 0 {
+        offset += 2;
+    }
+
+    if offset > data.len() {
+        return Err(truncated());
+    }
+
+    Ok(offset)
+}
+
+/// Computes the CRC-32 used by gzip.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Canonical Huffman code, stored as the number of codes of each length and the
+/// symbols ordered by code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Builds the code described by the code length of each symbol (0 for unused).
+    ///
+    /// Incomplete codes are accepted, as streams with a single distance code use
+    /// them; reading an unassigned code fails instead.
+    fn new(lengths: &[u8]) -> Result<Self> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+
+        // Each length doubles the codes available; using more than exist is an error
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(invalid(obf!("oversubscribed Huffman code").as_str()));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                let offset = &mut offsets[usize::from(length)];
+                symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    /// Reads one symbol, a bit at a time from the first bit of its code.
+    fn decode(&self, bits: &mut Bits) -> Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.take(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(invalid(obf!("invalid Huffman code").as_str()))
+    }
+}
+
+/// Reads a DEFLATE stream least significant bit first.
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    /// Reads `count` bits, at most 16.
+    fn take(&mut self, count: u32) -> Result<u32> {
+        while self.count < count {
+            let byte = *self.data.get(self.position).ok_or_else(truncated)?;
+            self.buffer |= u32::from(byte) << self.count;
+            self.position += 1;
+            self.count += 8;
+        }
+
+        let value = self.buffer & ((1 << count) - 1);
+        self.buffer >>= count;
+        self.count -= count;
+        Ok(value)
+    }
+
+    /// Drops the bits left in the current byte.
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+
+    /// Reads `length` whole bytes; the reader must be aligned.
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(length).ok_or_else(truncated)?;
+        let bytes = self.data.get(self.position..end).ok_or_else(truncated)?;
+        self.position = end;
+        Ok(bytes)
+    }
+}
+
+/// Decoder for one DEFLATE stream.
+struct Inflater<'a> {
+    bits: Bits<'a>,
+
+    /// Largest the output may grow to, including bytes already in it.
+    max_size: usize,
+}
+
+impl<'a> Inflater<'a> {
+    fn new(data: &'a [u8], max_size: usize) -> Self {
+        Self {
+            bits: Bits::new(data),
+            max_size,
+        }
+    }
+
+    /// Bytes of input read up to the end of the final block.
+    ///
+    /// Fewer than 8 bits are ever left buffered, so the partly read last byte counts
+    /// as consumed, and the padding bits after the final block are skipped with it.
+    fn consumed(&self) -> usize {
+        self.bits.position
+    }
+
+    /// Fails unless `count` more bytes fit in `output` without exceeding the limit.
+    fn ensure_room(&self, output: &[u8], count: usize) -> Result<()> {
+        if output.len().saturating_add(count) > self.max_size {
+            return Err(invalid(
+                obf!("decompressed data exceeds the size limit").as_str(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Decodes blocks into `output` until the final one.
+    fn run(&mut self, output: &mut Vec<u8>) -> Result<()> {
+        loop {
+            let last = self.bits.take(1)? == 1;
+            match self.bits.take(2)? {
+                0 => self.stored(output)?,
+                1 => {
+                    let (literals, distances) = fixed_codes()?;
+                    self.codes(output, &literals, &distances)?;
+                }
+                2 => {
+                    let (literals, distances) = self.dynamic_codes()?;
+                    self.codes(output, &literals, &distances)?;
+                }
+                _ => return Err(invalid(obf!("invalid DEFLATE block type").as_str())),
+            }
+
+            if last {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Copies an uncompressed block.
+    fn stored(&mut self, output: &mut Vec<u8>) -> Result<()> {
+        self.bits.align();
+        let header = self.bits.bytes(4)?;
+        let length = u16::from_le_bytes([header[0], header[1]]);
+        let complement = u16::from_le_bytes([header[2], header[3]]);
+        if length != !complement {
+            return Err(invalid(obf!("corrupt stored block length").as_str()));
+        }
+
+        let length = usize::from(length);
+        self.ensure_room(output, length)?;
+        output.extend_from_slice(self.bits.bytes(length)?);
+        Ok(())
+    }
+
+    /// Reads the code lengths of a dynamic block and builds its codes.
+    fn dynamic_codes(&mut self) -> Result<(Huffman, Huffman)> {
+        let literal_count = self.bits.take(5)? as usize + 257;
+        let distance_count = self.bits.take(5)? as usize + 1;
+        let length_count = self.bits.take(4)? as usize + 4;
+        if literal_count > MAX_LITERALS - 2 || distance_count > MAX_DISTANCES - 2 {
+            return Err(invalid(obf!("too many DEFLATE codes").as_str()));
+        }
+
+        let mut code_lengths = [0u8; 19];
+        for &index in &CODE_LENGTH_ORDER[..length_count] {
+            code_lengths[index] = self.bits.take(3)? as u8;
+        }
+        let code_lengths = Huffman::new(&code_lengths)?;
+
+        // Literal/length and distance code lengths form one run-length coded sequence
+        let mut lengths = [0u8; MAX_LITERALS + MAX_DISTANCES];
+        let total = literal_count + distance_count;
+        let mut index = 0;
+        while index < total {
+            let symbol = code_lengths.decode(&mut self.bits)?;
+            let (length, repeat) = match symbol {
+                0..=15 => (symbol as u8, 1),
+                16 => {
+                    let previous = *index
+                        .checked_sub(1)
+                        .and_then(|previous| lengths.get(previous))
+                        .ok_or_else(|| {
+                            invalid(obf!("repeat without a previous length").as_str())
+                        })?;
+                    (previous, 3 + self.bits.take(2)? as usize)
+                }
+                17 => (0, 3 + self.bits.take(3)? as usize),
+                _ => (0, 11 + self.bits.take(7)? as usize),
+            };
+
+            if index + repeat > total {
+                return Err(invalid(obf!("code lengths overflow").as_str()));
+            }
+            lengths[index..index + repeat].fill(length);
+            index += repeat;
+        }
+
+        if lengths[256] == 0 {
+            return Err(invalid(obf!("missing end-of-block code").as_str()));
+        }
+
+        Ok((
+            Huffman::new(&lengths[..literal_count])?,
+            Huffman::new(&lengths[literal_count..total])?,
+        ))
+    }
+
+    /// Decodes literals and back-references until the end-of-block symbol.
+    fn codes(
+        &mut self,
+        output: &mut Vec<u8>,
+        literals: &Huffman,
+        distances: &Huffman,
+    ) -> Result<()> {
+        loop {
+            let symbol = usize::from(literals.decode(&mut self.bits)?);
+            match symbol {
+                0..=255 => {
+                    self.ensure_room(output, 1)?;
+                    output.push(symbol as u8);
+                }
+                256 => return Ok(()),
+                _ => {
+                    let index = symbol - 257;
+                    let (&base, &extra) = LENGTH_BASE
+                        .get(index)
+                        .zip(LENGTH_EXTRA.get(index))
+                        .ok_or_else(|| invalid(obf!("invalid length symbol").as_str()))?;
+                    let length = usize::from(base) + self.bits.take(u32::from(extra))? as usize;
+
+                    let index = usize::from(distances.decode(&mut self.bits)?);
+                    let (&base, &extra) =
+                        DISTANCE_BASE
+                            .get(index)
+                            .zip(DISTANCE_EXTRA.get(index))
+                            .ok_or_else(|| invalid(obf!("invalid distance symbol").as_str()))?;
+                    let distance = usize::from(base) + self.bits.take(u32::from(extra))? as usize;
+
+                    if distance > output.len() {
+                        return Err(invalid(
+                            obf!("distance beyond the start of the output").as_str(),
+                        ));
+                    }
+
+                    self.ensure_room(output, length)?;
+
+                    // The source may overlap the bytes being written, so copy one at a time
+                    let start = output.len() - distance;
+                    for offset in 0..length {
+                        output.push(output[start + offset]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Builds the fixed codes of block type 1.
+fn fixed_codes() -> Result<(Huffman, Huffman)> {
+    let mut lengths = [0u8; MAX_LITERALS];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    Ok((
+        Huffman::new(&lengths)?,
+        Huffman::new(&[5; MAX_DISTANCES - 2])?,
+    ))
+}
+
+/// Builds the error returned for malformed input.
+fn invalid(reason: &str) -> ClrError {
+    ClrError::InvalidEncoding(reason.to_string())
+}
+
+/// Builds the error returned when the input ends too early.
+fn truncated() -> ClrError {
+    invalid(obf!("compressed data is truncated").as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Wraps a raw DEFLATE stream in a minimal gzip member.
+    fn gzip(data: &[u8], level: u8) -> Vec<u8> {
+        let mut member = vec![0x1F, 0x8B, 0x08, 0, 0, 0, 0, 0, 0, 0xFF];
+        member.extend(miniz_oxide::deflate::compress_to_vec(data, level));
+        member.extend(crc32(data).to_le_bytes());
+        member.extend((data.len() as u32).to_le_bytes());
+        member
+    }
+
+    fn sample() -> Vec<u8> {
+        let mut data = b"MZ\x90\x00This program cannot be run in DOS mode.\r\n".repeat(40);
+        data.extend((0..=255u8).cycle().take(3000));
+        data
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_block_types() {
+        let data = sample();
+
+        // Level 0 writes stored blocks, low levels fixed codes, higher ones dynamic codes
+        for level in [0, 1, 6, 10] {
+            let compressed = miniz_oxide::deflate::compress_to_vec(&data, level);
+            assert_eq!(inflate(&compressed).unwrap(), data, "level {level}");
+        }
+
+        // A single fixed-code block holding "a", as written by zlib
+        assert_eq!(inflate(&[0x4B, 0x04, 0x00]).unwrap(), b"a");
+        assert_eq!(inflate(&[0x03, 0x00]).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_gzip() {
+        let data = sample();
+        assert_eq!(gunzip(&gzip(&data, 6)).unwrap(), data);
+
+        // Optional header fields are skipped
+        let mut member = vec![
+            0x1F,
+            0x8B,
+            0x08,
+            GZIP_FEXTRA | GZIP_FNAME | GZIP_FCOMMENT | GZIP_FHCRC,
+        ];
+        member.extend([0; 6]);
+        member.extend([3, 0, 1, 2, 3]);
+        member.extend(b"sample.exe\0comment\0");
+        member.extend([0xAB, 0xCD]);
+        member.extend(&gzip(&data, 1)[10..]);
+        assert_eq!(gunzip(&member).unwrap(), data);
+
+        // Concatenated members decompress back to back
+        let mut members = gzip(b"first ", 6);
+        members.extend(gzip(b"second", 0));
+        assert_eq!(gunzip(&members).unwrap(), b"first second");
+    }
+
+    #[test]
+    fn test_rejects_corrupt_gzip() {
+        let member = gzip(&sample(), 6);
+
+        let mut bad_crc = member.clone();
+        let crc = bad_crc.len() - 8;
+        bad_crc[crc] ^= 1;
+        assert!(matches!(
+            gunzip(&bad_crc),
+            Err(ClrError::InvalidEncoding(_))
+        ));
+
+        let mut bad_magic = member.clone();
+        bad_magic[1] = 0;
+        assert!(gunzip(&bad_magic).is_err());
+
+        let mut reserved = member.clone();
+        reserved[3] = 0x20;
+        assert!(gunzip(&reserved).is_err());
+
+        assert!(gunzip(&member[..member.len() - 1]).is_err());
+        assert!(gunzip(&[]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_malformed_deflate() {
+        // Reserved block type 3
+        assert!(inflate(&[0x07]).is_err());
+
+        // Stored block whose length and complement disagree
+        assert!(inflate(&[0x01, 0x05, 0x00, 0x00, 0x00]).is_err());
+
+        // Back-reference before the start of the output: length 3, distance 1
+        assert!(inflate(&[0x03, 0x02]).is_err());
+
+        // Empty input and streams cut short
+        assert!(inflate(&[]).is_err());
+        let compressed = miniz_oxide::deflate::compress_to_vec(&sample(), 6);
+        for length in [1, compressed.len() / 2, compressed.len() - 1] {
+            assert!(inflate(&compressed[..length]).is_err(), "length {length}");
+        }
+    }
+
+    #[test]
+    fn test_output_limit() {
+        let data = sample();
+        let compressed = miniz_oxide::deflate::compress_to_vec(&data, 6);
+        assert_eq!(inflate_with_limit(&compressed, data.len()).unwrap(), data);
+        assert!(matches!(
+            inflate_with_limit(&compressed, data.len() - 1),
+            Err(ClrError::InvalidEncoding(_))
+        ));
+
+        // Stored blocks are checked before they are copied
+        let stored = miniz_oxide::deflate::compress_to_vec(&data, 0);
+        assert!(inflate_with_limit(&stored, 100).is_err());
+
+        // The limit covers all gzip members together
+        let mut members = gzip(b"first ", 6);
+        members.extend(gzip(b"second", 0));
+        assert_eq!(gunzip_with_limit(&members, 12).unwrap(), b"first second");
+        assert!(gunzip_with_limit(&members, 11).is_err());
+
+        // Back-references stop at the limit rather than once fully expanded
+        let bomb = miniz_oxide::deflate::compress_to_vec(&vec![0; 1 << 20], 10);
+        assert!(inflate_with_limit(&bomb, 1 << 16).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(data in proptest::collection::vec(0u8..8, 0..4096), level in 0u8..=10) {
+            let compressed = miniz_oxide::deflate::compress_to_vec(&data, level);
+            prop_assert_eq!(inflate(&compressed).unwrap(), data.clone());
+            prop_assert_eq!(gunzip(&gzip(&data, level)).unwrap(), data);
+        }
+
+        #[test]
+        fn test_arbitrary_input_does_not_panic(data in proptest::collection::vec(any::<u8>(), 0..256)) {
+            let _ = inflate(&data);
+            let _ = gunzip(&data);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+//! Decoders for assemblies stored base64-encoded or compressed.
+//!
+//! They back [`ClrSource::Base64`](crate::ClrSource::Base64),
+//! [`ClrSource::Deflate`](crate::ClrSource::Deflate) and
+//! [`ClrSource::Gzip`](crate::ClrSource::Gzip), and need nothing beyond `alloc`.
+//!
+//! # Example
+//!
+//! ```
+//! use rustclr::codec;
+//!
+//! let encoded = codec::encode_base64(b"MZ");
+//! assert_eq!(encoded, "TVo=");
+//! assert_eq!(codec::decode_base64(&encoded)?, b"MZ");
+//! # Ok::<(), rustclr::error::ClrError>(())
+//! ```
+
+mod base64;
+pub use base64::{decode_base64, encode_base64};
+
+mod inflate;
+pub use inflate::{
+    DEFAULT_MAX_OUTPUT_SIZE, gunzip, gunzip_with_limit, inflate, inflate_with_limit,
+};
@@ -1,5 +1,6 @@
 use alloc::{
     string::{String, ToString},
+    vec,
     vec::Vec,
 };
 use core::{ffi::c_void, ops::Deref, ptr::null_mut};
@@ -13,7 +14,9 @@ use windows::core::{BSTR, GUID, HRESULT, IUnknown, Interface};
 use const_encrypt::obf;
 
 use super::{_Assembly, _Type};
+use crate::Invocation;
 use crate::error::{ClrError, Result};
+use crate::type_name::{TypeModifier, TypeName};
 use crate::variant::create_safe_array_buffer;
 
 /// This struct represents the COM `_AppDomain` interface.
@@ -58,6 +61,84 @@ impl _AppDomain {
         Err(ClrError::Msg(obf!("Assembly Not Found").to_string()))
     }
 
+    /// Resolves a type by its CLR reflection name across the assemblies loaded in the
+    /// AppDomain, closing generic types such as ``System.Collections.Generic.List`1[[System.String]]``.
+    ///
+    /// Assembly-qualified names are loaded on demand when the assembly is not loaded yet.
+    #[inline]
+    pub fn resolve_type(&self, name: &str) -> Result<_Type> {
+        let type_name = TypeName::parse(name)?;
+        self.resolve_type_name(&type_name)
+    }
+
+    /// Resolves an already parsed [`TypeName`] within the AppDomain.
+    pub fn resolve_type_name(&self, type_name: &TypeName) -> Result<_Type> {
+        let mut ty = self.find_type(&type_name.name, type_name.assembly.as_deref())?;
+
+        if type_name.is_generic() {
+            let args = type_name
+                .generic_args
+                .iter()
+                .map(|arg| self.resolve_type_name(arg))
+                .collect::<Result<Vec<_>>>()?;
+            ty = ty.make_generic(&args.iter().collect::<Vec<_>>())?;
+        }
+
+        for modifier in &type_name.modifiers {
+            let (method, args) = match modifier {
+                TypeModifier::SzArray => (obf!("MakeArrayType").to_string(), None),
+                TypeModifier::Array(rank) => (
+                    obf!("MakeArrayType").to_string(),
+                    Some(vec![(*rank as i32).into()]),
+                ),
+                TypeModifier::Pointer => (obf!("MakePointerType").to_string(), None),
+                TypeModifier::ByRef => (obf!("MakeByRefType").to_string(), None),
+            };
+
+            let result =
+                ty.GetType()?
+                    .invoke(&method, Some(ty.to_variant()), args, Invocation::Instance)?;
+            ty = _Type::from_variant(&result)?;
+        }
+
+        Ok(ty)
+    }
+
+    /// Looks up a type definition by its full name, restricted to `assembly` when given.
+    fn find_type(&self, name: &str, assembly: Option<&str>) -> Result<_Type> {
+        let not_found = || ClrError::TypeNotFound(name.to_string());
+        let simple_name = |full_name: &str| -> String {
+            full_name
+                .split(',')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+
+        let assemblies = self.assemblies()?;
+        match assembly {
+            Some(assembly) => {
+                let wanted = simple_name(assembly);
+                let loaded = assemblies
+                    .into_iter()
+                    .find(|(full_name, _)| simple_name(full_name).eq_ignore_ascii_case(&wanted))
+                    .map(|(_, assembly)| assembly);
+
+                let assembly = match loaded {
+                    Some(assembly) => assembly,
+                    None => self.load_name(assembly)?,
+                };
+
+                assembly.resolve_type(name).map_err(|_| not_found())
+            }
+            None => assemblies
+                .into_iter()
+                .find_map(|(_, assembly)| assembly.resolve_type(name).ok())
+                .ok_or_else(not_found),
+        }
+    }
+
     /// Retrieves all assemblies currently loaded in the AppDomain.
     #[inline]
     pub fn assemblies(&self) -> Result<Vec<(String, _Assembly)>> {
@@ -49,6 +49,13 @@ impl _Assembly {
         self.CreateInstance(type_name.as_ptr())
     }
 
+    /// Creates an instance of a type within the assembly, passing `args` to the
+    /// constructor whose parameters best match them.
+    #[inline]
+    pub fn create_instance_with(&self, name: &str, args: Vec<VARIANT>) -> Result<VARIANT> {
+        self.resolve_type(name)?.construct(args)
+    }
+
     /// Retrieves all types within the assembly.
     #[inline]
     pub fn types(&self) -> Result<Vec<String>> {
@@ -103,6 +110,12 @@ impl _Assembly {
             .map_err(|_| ClrError::CastingError(obf!("_Assembly").to_string()))
     }
 
+    /// Wraps the assembly in a VARIANT so it can be passed as an argument to managed code.
+    #[inline]
+    pub fn to_variant(&self) -> VARIANT {
+        VARIANT::from(IUnknown::clone(self))
+    }
+
     /// Retrieves the string representation of the assembly.
     #[inline]
     pub fn ToString(&self) -> Result<String> {
@@ -152,7 +165,8 @@ impl _Assembly {
         let hr = unsafe {
             (Interface::vtable(self).GetType_2)(Interface::as_raw(self), name, &mut result)
         };
-        if hr.is_ok() {
+        // `Assembly.GetType(string)` returns null rather than failing for unknown names
+        if hr.is_ok() && !result.is_null() {
             _Type::from_raw(result as *mut c_void)
         } else {
             Err(ClrError::ApiError(obf!("GetType_2").to_string(), hr.0))
@@ -0,0 +1,239 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use core::{ffi::c_void, ops::Deref, ptr::null_mut};
+
+use windows::Win32::System::Com::GetErrorInfo;
+use windows::Win32::System::Variant::{VARIANT, VT_I4};
+use windows::core::{BSTR, GUID, HRESULT, IUnknown, Interface};
+
+use const_encrypt::obf;
+
+use super::_Type;
+use crate::error::{ClrError, Result};
+
+/// This struct represents the COM `_Exception` interface.
+#[repr(C)]
+#[derive(Clone)]
+pub struct _Exception(windows::core::IUnknown);
+
+impl _Exception {
+    /// Takes the managed exception left behind on the calling thread by the last
+    /// failed call into the CLR, if any.
+    ///
+    /// The CLR publishes the thrown exception as the thread's COM error info, which
+    /// this consumes; calling it twice for the same failure returns `None`.
+    #[inline]
+    pub fn last() -> Option<_Exception> {
+        let error_info = unsafe { GetErrorInfo(0) }.ok()?;
+        error_info.cast::<_Exception>().ok()
+    }
+
+    /// Converts the exception and its inner exceptions into a [`ClrError::ManagedException`].
+    ///
+    /// `TargetInvocationException` wrappers added by reflection are skipped so the error
+    /// describes the exception actually thrown by the invoked code. `hresult` is used when
+    /// the exception's own HRESULT cannot be read.
+    pub fn to_error(&self, hresult: i32) -> ClrError {
+        let type_name = self
+            .GetType()
+            .and_then(|ty| ty.ToString())
+            .unwrap_or_default();
+        let inner = self.get_InnerException().ok().flatten();
+
+        if type_name == &*obf!("System.Reflection.TargetInvocationException").as_str()
+            && let Some(inner) = &inner
+        {
+            return inner.to_error(hresult);
+        }
+
+        ClrError::ManagedException {
+            type_name,
+            message: self.get_Message().unwrap_or_default(),
+            stack_trace: self.get_StackTrace().unwrap_or_default(),
+            hresult: self.hresult().unwrap_or(hresult),
+            inner: inner.map(|inner| Box::new(inner.to_error(hresult))),
+        }
+    }
+
+    /// Reads `Exception.HResult`, which is not part of the `_Exception` interface.
+    fn hresult(&self) -> Option<i32> {
+        let property = self
+            .GetType()
+            .ok()?
+            .property(&obf!("HResult").as_str())
+            .ok()?;
+        let value = property
+            .value(Some(VARIANT::from(IUnknown::clone(self))), None)
+            .ok()?;
+
+        let inner = unsafe { &value.Anonymous.Anonymous };
+        if inner.vt != VT_I4 {
+            return None;
+        }
+
+        Some(unsafe { inner.Anonymous.lVal })
+    }
+
+    /// Creates an `_Exception` instance from a raw COM interface pointer.
+    #[inline]
+    pub fn from_raw(raw: *mut c_void) -> Result<_Exception> {
+        let iunknown = unsafe { IUnknown::from_raw(raw) };
+        iunknown
+            .cast::<_Exception>()
+            .map_err(|_| ClrError::CastingError(obf!("_Exception").to_string()))
+    }
+
+    /// Retrieves the string representation of the exception (equivalent to `ToString` in .NET).
+    #[inline]
+    pub fn ToString(&self) -> Result<String> {
+        unsafe {
+            let mut result: *const u16 = core::ptr::null();
+            let hr = (Interface::vtable(self).get_ToString)(Interface::as_raw(self), &mut result);
+            if hr.is_ok() {
+                let bstr = BSTR::from_raw(result);
+                Ok(bstr.to_string())
+            } else {
+                Err(ClrError::ApiError(obf!("ToString").to_string(), hr.0))
+            }
+        }
+    }
+
+    /// Retrieves the runtime type of the exception.
+    #[inline]
+    pub fn GetType(&self) -> Result<_Type> {
+        let mut result = null_mut();
+        let hr = unsafe { (Interface::vtable(self).GetType)(Interface::as_raw(self), &mut result) };
+        if hr.is_ok() {
+            _Type::from_raw(result as *mut c_void)
+        } else {
+            Err(ClrError::ApiError(obf!("GetType").to_string(), hr.0))
+        }
+    }
+
+    /// Retrieves the message that describes the exception.
+    #[inline]
+    pub fn get_Message(&self) -> Result<String> {
+        unsafe {
+            let mut result: *const u16 = core::ptr::null();
+            let hr = (Interface::vtable(self).get_Message)(Interface::as_raw(self), &mut result);
+            if hr.is_ok() {
+                let bstr = BSTR::from_raw(result);
+                Ok(bstr.to_string())
+            } else {
+                Err(ClrError::ApiError(obf!("get_Message").to_string(), hr.0))
+            }
+        }
+    }
+
+    /// Retrieves the exception that caused this one, if any.
+    #[inline]
+    pub fn get_InnerException(&self) -> Result<Option<_Exception>> {
+        let mut result = null_mut();
+        let hr = unsafe {
+            (Interface::vtable(self).get_InnerException)(Interface::as_raw(self), &mut result)
+        };
+        if hr.is_ok() {
+            if result.is_null() {
+                Ok(None)
+            } else {
+                _Exception::from_raw(result as *mut c_void).map(Some)
+            }
+        } else {
+            Err(ClrError::ApiError(
+                obf!("get_InnerException").to_string(),
+                hr.0,
+            ))
+        }
+    }
+
+    /// Retrieves the managed stack trace captured when the exception was thrown.
+    #[inline]
+    pub fn get_StackTrace(&self) -> Result<String> {
+        unsafe {
+            let mut result: *const u16 = core::ptr::null();
+            let hr = (Interface::vtable(self).get_StackTrace)(Interface::as_raw(self), &mut result);
+            if hr.is_ok() {
+                let bstr = BSTR::from_raw(result);
+                Ok(bstr.to_string())
+            } else {
+                Err(ClrError::ApiError(obf!("get_StackTrace").to_string(), hr.0))
+            }
+        }
+    }
+
+    /// Retrieves the name of the application or object that caused the error.
+    #[inline]
+    pub fn get_Source(&self) -> Result<String> {
+        unsafe {
+            let mut result: *const u16 = core::ptr::null();
+            let hr = (Interface::vtable(self).get_Source)(Interface::as_raw(self), &mut result);
+            if hr.is_ok() {
+                let bstr = BSTR::from_raw(result);
+                Ok(bstr.to_string())
+            } else {
+                Err(ClrError::ApiError(obf!("get_Source").to_string(), hr.0))
+            }
+        }
+    }
+
+    /// Retrieves the innermost exception of the chain.
+    #[inline]
+    pub fn GetBaseException(&self) -> Result<_Exception> {
+        let mut result = null_mut();
+        let hr = unsafe {
+            (Interface::vtable(self).GetBaseException)(Interface::as_raw(self), &mut result)
+        };
+        if hr.is_ok() {
+            _Exception::from_raw(result as *mut c_void)
+        } else {
+            Err(ClrError::ApiError(
+                obf!("GetBaseException").to_string(),
+                hr.0,
+            ))
+        }
+    }
+}
+
+unsafe impl Interface for _Exception {
+    type Vtable = _Exception_Vtbl;
+    const IID: GUID = GUID::from_u128(0xb36b5c63_42ef_38bc_a07e_0b34c98f164a);
+}
+
+impl Deref for _Exception {
+    type Target = windows::core::IUnknown;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { core::mem::transmute(self) }
+    }
+}
+
+type BSTR_PTR = *const u16;
+
+#[repr(C)]
+pub struct _Exception_Vtbl {
+    pub base__: windows::core::IUnknown_Vtbl,
+
+    // IDispatch methods
+    GetTypeInfoCount: *const c_void,
+    GetTypeInfo: *const c_void,
+    GetIDsOfNames: *const c_void,
+    Invoke: *const c_void,
+
+    // Methods specific to the COM interface
+    get_ToString: unsafe extern "system" fn(this: *mut c_void, pRetVal: *mut BSTR_PTR) -> HRESULT,
+    Equals: *const c_void,
+    GetHashCode: *const c_void,
+    GetType: unsafe extern "system" fn(this: *mut c_void, pRetVal: *mut *mut _Type) -> HRESULT,
+    get_Message: unsafe extern "system" fn(this: *mut c_void, pRetVal: *mut BSTR_PTR) -> HRESULT,
+    get_InnerException:
+        unsafe extern "system" fn(this: *mut c_void, pRetVal: *mut *mut _Exception) -> HRESULT,
+    get_TargetSite: *const c_void,
+    get_StackTrace: unsafe extern "system" fn(this: *mut c_void, pRetVal: *mut BSTR_PTR) -> HRESULT,
+    get_HelpLink: *const c_void,
+    put_HelpLink: *const c_void,
+    get_Source: unsafe extern "system" fn(this: *mut c_void, pRetVal: *mut BSTR_PTR) -> HRESULT,
+    put_Source: *const c_void,
+    GetObjectData: *const c_void,
+    GetBaseException:
+        unsafe extern "system" fn(this: *mut c_void, pRetVal: *mut *mut _Exception) -> HRESULT,
+}
@@ -13,9 +13,9 @@ use windows::core::{BSTR, GUID, HRESULT, IUnknown, Interface};
 use const_encrypt::obf;
 
 use crate::Invocation;
-use crate::com::{_MethodInfo, _PropertyInfo};
+use crate::com::{_Exception, _MethodInfo, _PropertyInfo};
 use crate::error::{ClrError, Result};
-use crate::variant::create_safe_args;
+use crate::variant::{create_safe_args, unknown_from_variant};
 
 /// This struct represents the COM `_Type` interface.
 #[repr(C)]
@@ -126,6 +126,53 @@ impl _Type {
             })
     }
 
+    /// Creates an instance of the type by calling the constructor that best matches `args`.
+    ///
+    /// Overload resolution is performed by the default .NET binder, exactly as
+    /// `Activator.CreateInstance(Type, object[])` does, so the argument types decide
+    /// which constructor is selected. An empty `args` calls the parameterless constructor.
+    #[inline]
+    pub fn construct(&self, args: Vec<VARIANT>) -> Result<VARIANT> {
+        let flags = BindingFlags::CreateInstance
+            | BindingFlags::Public
+            | BindingFlags::NonPublic
+            | BindingFlags::Instance;
+
+        // The member name is ignored by `InvokeMember` when `CreateInstance` is set,
+        // but it must not be null
+        let ctor_name = BSTR::from(&*obf!(".ctor").as_str());
+        let args_array = create_safe_args(args)?;
+        self.InvokeMember_3(
+            ctor_name.as_ptr(),
+            flags,
+            VARIANT::default(),
+            args_array.as_ptr(),
+        )
+        .map_err(|err| match err {
+            ClrError::ApiError(_, hr) => ClrError::Message(alloc::format!(
+                "{} '{}': HRESULT {hr:#x}",
+                obf!("constructor invocation failed"),
+                self.ToString().unwrap_or_default()
+            )),
+            other => other,
+        })
+    }
+
+    /// Closes a generic type definition over the given type arguments
+    /// (equivalent to `Type.MakeGenericType` in .NET).
+    #[inline]
+    pub fn make_generic(&self, type_args: &[&_Type]) -> Result<_Type> {
+        let args = type_args.iter().map(|ty| ty.to_variant()).collect();
+        let result = self.GetType()?.invoke(
+            &obf!("MakeGenericType").as_str(),
+            Some(self.to_variant()),
+            Some(args),
+            Invocation::Instance,
+        )?;
+
+        _Type::from_variant(&result)
+    }
+
     /// Retrieves all methods of the type.
     #[inline]
     pub fn methods(&self) -> Result<Vec<(String, _MethodInfo)>> {
@@ -241,6 +288,20 @@ impl _Type {
             .map_err(|_| ClrError::CastingError(obf!("_Type").to_string()))
     }
 
+    /// Creates an `_Type` from a VARIANT holding a `System.Type` object.
+    #[inline]
+    pub fn from_variant(variant: &VARIANT) -> Result<_Type> {
+        unknown_from_variant(variant)?
+            .cast::<_Type>()
+            .map_err(|_| ClrError::CastingError(obf!("_Type").to_string()))
+    }
+
+    /// Wraps the type in a VARIANT so it can be passed as an argument to managed code.
+    #[inline]
+    pub fn to_variant(&self) -> VARIANT {
+        VARIANT::from(IUnknown::clone(self))
+    }
+
     /// Retrieves the string representation of the type.
     #[inline]
     pub fn ToString(&self) -> Result<String> {
@@ -256,6 +317,18 @@ impl _Type {
         }
     }
 
+    /// Retrieves the runtime type of this `System.Type` object (usually `System.RuntimeType`).
+    #[inline]
+    pub fn GetType(&self) -> Result<_Type> {
+        let mut result = null_mut();
+        let hr = unsafe { (Interface::vtable(self).GetType)(Interface::as_raw(self), &mut result) };
+        if hr.is_ok() {
+            _Type::from_raw(result as *mut c_void)
+        } else {
+            Err(ClrError::ApiError(obf!("GetType").to_string(), hr.0))
+        }
+    }
+
     /// Retrieves all properties matching the specified `BindingFlags`.
     #[inline]
     pub fn GetProperties(&self, bindingAttr: BindingFlags) -> Result<*mut SAFEARRAY> {
@@ -309,6 +382,9 @@ impl _Type {
     }
 
     /// Invokes a method (static or instance) by name on the specified type or object.
+    ///
+    /// When the member throws, the managed exception is returned as
+    /// [`ClrError::ManagedException`].
     #[inline]
     pub fn InvokeMember_3(
         &self,
@@ -331,7 +407,10 @@ impl _Type {
             if hr.is_ok() {
                 Ok(result)
             } else {
-                Err(ClrError::ApiError(obf!("InvokeMember_3").to_string(), hr.0))
+                Err(_Exception::last().map_or_else(
+                    || ClrError::ApiError(obf!("InvokeMember_3").to_string(), hr.0),
+                    |exception| exception.to_error(hr.0),
+                ))
             }
         }
     }
@@ -392,7 +471,7 @@ pub struct _Type_Vtbl {
     get_ToString: unsafe extern "system" fn(this: *mut c_void, pRetVal: *mut BSTR_PTR) -> HRESULT,
     Equals: *const c_void,
     GetHashCode: *const c_void,
-    GetType: *const c_void,
+    GetType: unsafe extern "system" fn(this: *mut c_void, pRetVal: *mut *mut _Type) -> HRESULT,
     get_MemberType: *const c_void,
     get_name: *const c_void,
     get_DeclaringType: *const c_void,
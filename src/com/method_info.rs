@@ -7,8 +7,10 @@ use windows::core::{BSTR, GUID, HRESULT, IUnknown, Interface};
 
 use const_encrypt::obf;
 
-use super::_Type;
+use super::{_Exception, _Type};
+use crate::Invocation;
 use crate::error::{ClrError, Result};
+use crate::variant::unknown_from_variant;
 use crate::wrappers::SafeArray as SafeArrayWrapper;
 
 /// This struct represents the COM `_MethodInfo` interface.
@@ -29,6 +31,23 @@ impl _MethodInfo {
         self.Invoke_3(variant_obj, params_ptr)
     }
 
+    /// Closes a generic method definition over the given type arguments
+    /// (equivalent to `MethodInfo.MakeGenericMethod` in .NET).
+    #[inline]
+    pub fn make_generic(&self, type_args: &[&_Type]) -> Result<_MethodInfo> {
+        let args = type_args.iter().map(|ty| ty.to_variant()).collect();
+        let result = self.GetType()?.invoke(
+            &obf!("MakeGenericMethod").as_str(),
+            Some(VARIANT::from(IUnknown::clone(self))),
+            Some(args),
+            Invocation::Instance,
+        )?;
+
+        unknown_from_variant(&result)?
+            .cast::<_MethodInfo>()
+            .map_err(|_| ClrError::CastingError(obf!("_MethodInfo").to_string()))
+    }
+
     /// Creates an `_MethodInfo` instance from a raw COM interface pointer.
     #[inline]
     pub fn from_raw(raw: *mut c_void) -> Result<_MethodInfo> {
@@ -69,6 +88,9 @@ impl _MethodInfo {
     }
 
     /// Internal invocation method for the method, used by `invoke`.
+    ///
+    /// When the method throws, the managed exception is returned as
+    /// [`ClrError::ManagedException`].
     #[inline]
     pub fn Invoke_3(&self, obj: VARIANT, parameters: *mut SAFEARRAY) -> Result<VARIANT> {
         unsafe {
@@ -82,7 +104,10 @@ impl _MethodInfo {
             if hr.is_ok() {
                 Ok(result)
             } else {
-                Err(ClrError::ApiError(obf!("Invoke_3").to_string(), hr.0))
+                Err(_Exception::last().map_or_else(
+                    || ClrError::ApiError(obf!("Invoke_3").to_string(), hr.0),
+                    |exception| exception.to_error(hr.0),
+                ))
             }
         }
     }
@@ -20,6 +20,7 @@ mod iclrruntimehost;
 mod iclrruntimeinfo;
 mod icorruntimehost;
 mod ienumunknown;
+mod iexception;
 mod ihostcontrol;
 mod ipropertyinfo;
 mod itype;
@@ -35,6 +36,7 @@ pub use iclrruntimehost::*;
 pub use iclrruntimeinfo::*;
 pub use icorruntimehost::*;
 pub use ienumunknown::*;
+pub use iexception::*;
 pub use ihostcontrol::*;
 pub use ipropertyinfo::*;
 pub use itype::*;
@@ -1,7 +1,14 @@
+use alloc::boxed::Box;
 use alloc::string::String;
+#[cfg(windows)]
+use alloc::vec::Vec;
 use const_encrypt::obf;
 use core::fmt;
 
+#[cfg(windows)]
+use crate::clr::Diagnostic;
+use crate::pe::Architecture;
+
 pub type Result<T> = core::result::Result<T, ClrError>;
 
 pub enum ClrError {
@@ -25,7 +32,32 @@ pub enum ClrError {
     VariantUnsupported,
     Msg(String),
     Message(String),
-    InvalidNtHeader,
+    TruncatedHeader(String),
+    BadMagic(String),
+    RvaOutOfRange(u32),
+    ArchitectureMismatch {
+        required: Architecture,
+        host: Architecture,
+    },
+    MixedModeUnsupported,
+    InvalidTypeName(String),
+    InvalidClixml(String),
+    InvalidMetadata(String),
+    InvalidEncoding(String),
+    TypeNotFound(String),
+    Timeout,
+    Cancelled,
+    WorkerStopped,
+    AbortFailed(Box<ClrError>),
+    #[cfg(windows)]
+    CompilationFailed(Vec<Diagnostic>),
+    ManagedException {
+        type_name: String,
+        message: String,
+        stack_trace: String,
+        hresult: i32,
+        inner: Option<Box<ClrError>>,
+    },
 }
 
 impl fmt::Debug for ClrError {
@@ -77,7 +109,60 @@ impl fmt::Display for ClrError {
             Self::VariantUnsupported => write!(f, "{}", obf!("unsupported VARIANT type")),
             Self::Msg(s) => f.write_str(s),
             Self::Message(s) => f.write_str(s),
-            Self::InvalidNtHeader => write!(f, "{}", obf!("invalid PE file")),
+            Self::TruncatedHeader(s) => write!(f, "{}: {s}", obf!("truncated PE header")),
+            Self::BadMagic(s) => write!(f, "{}: {s}", obf!("bad PE magic")),
+            Self::RvaOutOfRange(rva) => write!(f, "{} {rva:#x}", obf!("RVA out of range:")),
+            Self::ArchitectureMismatch { required, host } => write!(
+                f,
+                "{} {required}, {} {host}",
+                obf!("assembly requires"),
+                obf!("but the host process is")
+            ),
+            Self::MixedModeUnsupported => write!(
+                f,
+                "{}",
+                obf!("mixed-mode assemblies cannot be loaded from memory")
+            ),
+            Self::InvalidTypeName(s) => write!(f, "{}: {s}", obf!("invalid type name")),
+            Self::InvalidClixml(s) => write!(f, "{}: {s}", obf!("invalid CLIXML")),
+            Self::InvalidMetadata(s) => write!(f, "{}: {s}", obf!("invalid metadata")),
+            Self::InvalidEncoding(s) => write!(f, "{}: {s}", obf!("invalid encoding")),
+            Self::TypeNotFound(s) => write!(f, "{}: {s}", obf!("type not found")),
+            Self::Timeout => write!(f, "{}", obf!("execution timed out")),
+            Self::Cancelled => write!(f, "{}", obf!("execution cancelled")),
+            Self::WorkerStopped => write!(f, "{}", obf!("CLR worker has stopped")),
+            Self::AbortFailed(cause) => {
+                write!(f, "{cause}; {}", obf!("the aborted thread did not stop"))
+            }
+            #[cfg(windows)]
+            Self::CompilationFailed(diagnostics) => {
+                write!(f, "{}", obf!("C# compilation failed"))?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n{diagnostic}")?;
+                }
+                Ok(())
+            }
+            Self::ManagedException {
+                type_name,
+                message,
+                stack_trace,
+                inner,
+                ..
+            } => {
+                // Mirrors the layout of .NET's `Exception.ToString()`
+                write!(f, "{type_name}: {message}")?;
+                if let Some(inner) = inner {
+                    write!(
+                        f,
+                        " ---> {inner}\n   {}",
+                        obf!("--- End of inner exception stack trace ---")
+                    )?;
+                }
+                if !stack_trace.is_empty() {
+                    write!(f, "\n{stack_trace}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
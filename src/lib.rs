@@ -12,14 +12,58 @@ extern crate alloc;
 #[cfg(test)]
 extern crate std;
 
+pub mod cmdline;
+pub mod codec;
+#[cfg(windows)]
 pub mod com;
 pub mod error;
+pub mod metadata;
+pub mod pe;
+pub mod type_name;
+#[cfg(windows)]
 pub mod variant;
+#[cfg(windows)]
 pub mod wrappers;
 
+// Thread protocols behind the CLR worker and runspace pools, free of Win32 so they are
+// tested everywhere
+#[cfg_attr(not(windows), allow(dead_code))]
+mod sync;
+
+#[cfg(windows)]
 mod clr;
+#[cfg(windows)]
 mod pwsh;
 
+// Compiler diagnostics and snippet wrapping need no CLR, so they are tested off Windows
+#[cfg(all(test, not(windows)))]
+mod clr {
+    mod diagnostic;
+    mod wrapper;
+    pub use diagnostic::{Diagnostic, Severity};
+}
+
+// Parsing and value types build everywhere, so they can be tested off Windows
+#[cfg(not(windows))]
+mod pwsh {
+    pub mod clixml;
+    #[cfg(test)]
+    mod image;
+    mod value;
+    pub use value::PsValue;
+}
+
+#[cfg(windows)]
 pub use clr::*;
-pub use pwsh::PowerShell;
+pub use metadata::AssemblyReport;
+pub use pe::{validate_file, validate_library};
+#[cfg(windows)]
+pub use pwsh::{
+    ChoiceDescription, ErrorRecord, ExecutionPolicy, ExecutionResult, FieldDescription,
+    LanguageMode, PooledPowerShell, PowerShell, PowerShellConfig, PowerShellPool, ProgressRecord,
+    PsCommand, PsHostHandler,
+};
+pub use pwsh::{PsValue, clixml};
+pub use type_name::TypeName;
+#[cfg(windows)]
 pub use wrappers::SafeArray;
@@ -0,0 +1,376 @@
+//! Decoding of custom attribute value blobs (ECMA-335 II.23.3).
+
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use core::fmt;
+
+use const_encrypt::obf;
+
+use super::reader::{Reader, malformed};
+use super::signature::{MAX_DEPTH, MethodSig, Names, SigType, Token};
+use crate::error::Result;
+
+/// A constructor argument or named argument of a custom attribute.
+pub(crate) enum Value {
+    Bool(bool),
+    Char(char),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(Option<String>),
+    Type(Option<String>),
+    Enum(String, i64),
+    Array(Option<Vec<Value>>),
+}
+
+/// Resolves what the value blob leaves implicit.
+pub(crate) trait AttributeNames: Names {
+    /// Namespace-qualified name of a TypeDef or TypeRef, as written in the blob for enums.
+    fn full_name(&self, token: Token) -> Result<String>;
+
+    /// Element type of the underlying type of the enum `name`, if this module defines it.
+    fn enum_underlying(&self, name: &str) -> Option<u8>;
+}
+
+/// Decoded arguments of one custom attribute.
+pub(crate) struct Arguments {
+    pub fixed: Vec<Value>,
+    pub named: Vec<(String, Value)>,
+}
+
+/// How a value is laid out in the blob.
+enum Elem {
+    Primitive(u8),
+    String,
+    Type,
+    Boxed,
+    Enum(String, u8),
+    Array(Box<Elem>),
+}
+
+const ELEMENT_TYPE_BOOLEAN: u8 = 0x02;
+const ELEMENT_TYPE_CHAR: u8 = 0x03;
+const ELEMENT_TYPE_I4: u8 = 0x08;
+const ELEMENT_TYPE_R4: u8 = 0x0C;
+const ELEMENT_TYPE_R8: u8 = 0x0D;
+const ELEMENT_TYPE_STRING: u8 = 0x0E;
+const ELEMENT_TYPE_SZARRAY: u8 = 0x1D;
+const SERIALIZATION_TYPE_TYPE: u8 = 0x50;
+const SERIALIZATION_TYPE_TAGGED_OBJECT: u8 = 0x51;
+const SERIALIZATION_TYPE_ENUM: u8 = 0x55;
+const NAMED_FIELD: u8 = 0x53;
+const NAMED_PROPERTY: u8 = 0x54;
+
+/// Decodes the value blob of an attribute whose constructor has the signature `ctor`.
+pub(crate) fn decode(
+    blob: &[u8],
+    ctor: &MethodSig,
+    names: &impl AttributeNames,
+) -> Result<Arguments> {
+    let mut reader = Reader::new(blob);
+    if reader.u16()? != 0x0001 {
+        return Err(malformed(obf!("bad custom attribute prolog").as_str()));
+    }
+
+    let fixed = ctor
+        .params
+        .iter()
+        .map(|param| {
+            let elem = elem_of(param, names)?;
+            value(&mut reader, &elem, names, 0)
+        })
+        .collect::<Result<_>>()?;
+
+    let count = reader.u16()?;
+    let mut named = Vec::new();
+    for _ in 0..count {
+        if !matches!(reader.u8()?, NAMED_FIELD | NAMED_PROPERTY) {
+            return Err(malformed(obf!("bad named argument").as_str()));
+        }
+
+        let elem = field_or_prop_type(&mut reader, names, 0)?;
+        let name = ser_string(&mut reader)?.unwrap_or_default();
+        named.push((name, value(&mut reader, &elem, names, 0)?));
+    }
+
+    Ok(Arguments { fixed, named })
+}
+
+/// Maps a constructor parameter type to its blob encoding.
+fn elem_of(ty: &SigType, names: &impl AttributeNames) -> Result<Elem> {
+    let elem = match ty {
+        SigType::Bool => Elem::Primitive(ELEMENT_TYPE_BOOLEAN),
+        SigType::Char => Elem::Primitive(ELEMENT_TYPE_CHAR),
+        SigType::I1 => Elem::Primitive(0x04),
+        SigType::U1 => Elem::Primitive(0x05),
+        SigType::I2 => Elem::Primitive(0x06),
+        SigType::U2 => Elem::Primitive(0x07),
+        SigType::I4 => Elem::Primitive(0x08),
+        SigType::U4 => Elem::Primitive(0x09),
+        SigType::I8 => Elem::Primitive(0x0A),
+        SigType::U8 => Elem::Primitive(0x0B),
+        SigType::R4 => Elem::Primitive(ELEMENT_TYPE_R4),
+        SigType::R8 => Elem::Primitive(ELEMENT_TYPE_R8),
+        SigType::String => Elem::String,
+        SigType::Object => Elem::Boxed,
+        SigType::SzArray(inner) => Elem::Array(Box::new(elem_of(inner, names)?)),
+        SigType::Class(token) if names.full_name(*token)? == "System.Type" => Elem::Type,
+        SigType::ValueType(token) => {
+            // Enums defined elsewhere cannot be looked up; almost all of them are `int`
+            let name = names.full_name(*token)?;
+            let underlying = names.enum_underlying(&name).unwrap_or(ELEMENT_TYPE_I4);
+            Elem::Enum(name, underlying)
+        }
+        _ => return Err(malformed(obf!("unsupported attribute argument").as_str())),
+    };
+
+    Ok(elem)
+}
+
+/// Reads a `FieldOrPropType`, the self-describing type of named and boxed arguments.
+fn field_or_prop_type(
+    reader: &mut Reader,
+    names: &impl AttributeNames,
+    depth: usize,
+) -> Result<Elem> {
+    if depth > MAX_DEPTH {
+        return Err(malformed(obf!("attribute nested too deeply").as_str()));
+    }
+
+    let elem = match reader.u8()? {
+        tag @ ELEMENT_TYPE_BOOLEAN..=ELEMENT_TYPE_R8 => Elem::Primitive(tag),
+        ELEMENT_TYPE_STRING => Elem::String,
+        SERIALIZATION_TYPE_TYPE => Elem::Type,
+        SERIALIZATION_TYPE_TAGGED_OBJECT => Elem::Boxed,
+        ELEMENT_TYPE_SZARRAY => {
+            Elem::Array(Box::new(field_or_prop_type(reader, names, depth + 1)?))
+        }
+        SERIALIZATION_TYPE_ENUM => {
+            // Assembly-qualified; only the type part can match a local enum
+            let name = ser_string(reader)?.unwrap_or_default();
+            let local = name.split(',').next().unwrap_or_default().trim();
+            let underlying = names.enum_underlying(local).unwrap_or(ELEMENT_TYPE_I4);
+            Elem::Enum(name, underlying)
+        }
+        _ => return Err(malformed(obf!("unknown attribute argument type").as_str())),
+    };
+
+    Ok(elem)
+}
+
+fn value(
+    reader: &mut Reader,
+    elem: &Elem,
+    names: &impl AttributeNames,
+    depth: usize,
+) -> Result<Value> {
+    if depth > MAX_DEPTH {
+        return Err(malformed(obf!("attribute nested too deeply").as_str()));
+    }
+
+    let value = match elem {
+        Elem::Primitive(tag) => primitive(reader, *tag)?,
+        Elem::String => Value::String(ser_string(reader)?),
+        Elem::Type => Value::Type(ser_string(reader)?),
+        Elem::Boxed => {
+            let elem = field_or_prop_type(reader, names, depth + 1)?;
+            value(reader, &elem, names, depth + 1)?
+        }
+        Elem::Enum(name, underlying) => {
+            let number = match primitive(reader, *underlying)? {
+                Value::Int(number) => number,
+                Value::UInt(number) => number as i64,
+                _ => return Err(malformed(obf!("bad enum underlying type").as_str())),
+            };
+            Value::Enum(name.clone(), number)
+        }
+        Elem::Array(inner) => match reader.u32()? {
+            u32::MAX => Value::Array(None),
+            count => {
+                // Every element takes at least one byte, so a bogus count fails quickly
+                let items = (0..count)
+                    .map(|_| value(reader, inner, names, depth + 1))
+                    .collect::<Result<_>>()?;
+                Value::Array(Some(items))
+            }
+        },
+    };
+
+    Ok(value)
+}
+
+fn primitive(reader: &mut Reader, tag: u8) -> Result<Value> {
+    let value = match tag {
+        ELEMENT_TYPE_BOOLEAN => Value::Bool(reader.u8()? != 0),
+        ELEMENT_TYPE_CHAR => {
+            Value::Char(char::from_u32(reader.u16()?.into()).unwrap_or('\u{FFFD}'))
+        }
+        0x04 => Value::Int(reader.u8()? as i8 as i64),
+        0x05 => Value::UInt(reader.u8()?.into()),
+        0x06 => Value::Int(reader.u16()? as i16 as i64),
+        0x07 => Value::UInt(reader.u16()?.into()),
+        0x08 => Value::Int(reader.u32()? as i32 as i64),
+        0x09 => Value::UInt(reader.u32()?.into()),
+        0x0A => Value::Int(reader.u64()? as i64),
+        0x0B => Value::UInt(reader.u64()?),
+        ELEMENT_TYPE_R4 => Value::Float(f32::from_bits(reader.u32()?).into()),
+        ELEMENT_TYPE_R8 => Value::Float(f64::from_bits(reader.u64()?)),
+        _ => return Err(malformed(obf!("unknown attribute argument type").as_str())),
+    };
+
+    Ok(value)
+}
+
+/// Reads a `SerString`: a compressed length and UTF-8 bytes, or `0xFF` for null.
+fn ser_string(reader: &mut Reader) -> Result<Option<String>> {
+    if reader.clone().u8()? == 0xFF {
+        reader.skip(1)?;
+        return Ok(None);
+    }
+
+    let length = reader.compressed_u32()?;
+    let bytes = reader.bytes(length as usize)?;
+    Ok(Some(String::from_utf8_lossy(bytes).into_owned()))
+}
+
+impl fmt::Display for Value {
+    /// Formats the value as a C# literal.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Char(value) => write!(f, "{value:?}"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::UInt(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value}"),
+            Self::String(Some(value)) => write!(f, "{value:?}"),
+            Self::Type(Some(name)) => write!(f, "typeof({name})"),
+            Self::Enum(name, value) => {
+                let name = name.split(',').next().unwrap_or_default();
+                write!(f, "({name}){value}")
+            }
+            Self::Array(Some(items)) => {
+                let items = items
+                    .iter()
+                    .map(|item| format!("{item}"))
+                    .collect::<Vec<_>>();
+                write!(f, "new[] {{ {} }}", items.join(", "))
+            }
+            Self::String(None) | Self::Type(None) | Self::Array(None) => f.write_str("null"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::signature::method;
+    use crate::metadata::tables::TYPE_REF;
+    use alloc::string::ToString;
+
+    struct Fake;
+
+    impl Names for Fake {
+        fn type_name(&self, _: Token, _: usize) -> Result<String> {
+            Ok("Fake".into())
+        }
+
+        fn generic_name(&self, _: bool, index: u32) -> String {
+            format!("T{index}")
+        }
+    }
+
+    impl AttributeNames for Fake {
+        fn full_name(&self, (table, rid): Token) -> Result<String> {
+            Ok(match (table, rid) {
+                (TYPE_REF, 1) => "System.Type".into(),
+                _ => "System.AttributeTargets".into(),
+            })
+        }
+
+        fn enum_underlying(&self, name: &str) -> Option<u8> {
+            (name == "Local.Small").then_some(0x05)
+        }
+    }
+
+    fn render(arguments: &Arguments) -> Vec<String> {
+        let fixed = arguments.fixed.iter().map(|value| value.to_string());
+        let named = arguments
+            .named
+            .iter()
+            .map(|(name, value)| format!("{name} = {value}"));
+        fixed.chain(named).collect()
+    }
+
+    #[test]
+    fn test_decode_fixed_arguments() {
+        // .ctor(string, System.Type, System.AttributeTargets, int32[], object)
+        let ctor = method(&[
+            0x20, 0x05, 0x01, 0x0E, 0x12, 0x05, 0x11, 0x09, 0x1D, 0x08, 0x1C,
+        ])
+        .unwrap();
+
+        let mut blob = alloc::vec![0x01, 0x00];
+        blob.extend([0x04, b'v', b'4', b'.', b'8']);
+        blob.extend([0x06, b'S', b'y', b's', b't', b'e', b'm']);
+        blob.extend(4u32.to_le_bytes());
+        blob.extend([
+            0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+        ]);
+        blob.extend([0x0E, 0xFF]);
+        blob.extend([0x00, 0x00]);
+
+        let arguments = decode(&blob, &ctor, &Fake).unwrap();
+        assert_eq!(
+            render(&arguments),
+            [
+                "\"v4.8\"",
+                "typeof(System)",
+                "(System.AttributeTargets)4",
+                "new[] { 1, -1 }",
+                "null"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_named_arguments() {
+        let ctor = method(&[0x20, 0x00, 0x01]).unwrap();
+
+        let mut blob = alloc::vec![0x01, 0x00, 0x03, 0x00];
+        blob.extend([NAMED_PROPERTY, ELEMENT_TYPE_BOOLEAN, 0x09]);
+        blob.extend(b"Inherited");
+        blob.push(0x00);
+        blob.extend([NAMED_FIELD, SERIALIZATION_TYPE_ENUM, 0x0B]);
+        blob.extend(b"Local.Small");
+        blob.extend([0x01, b'x', 0x07]);
+        blob.extend([
+            NAMED_PROPERTY,
+            ELEMENT_TYPE_SZARRAY,
+            ELEMENT_TYPE_STRING,
+            0x01,
+            b'y',
+        ]);
+        blob.extend(1u32.to_le_bytes());
+        blob.extend([0x01, b'a']);
+
+        let arguments = decode(&blob, &ctor, &Fake).unwrap();
+        assert_eq!(
+            render(&arguments),
+            [
+                "Inherited = false",
+                "x = (Local.Small)7",
+                "y = new[] { \"a\" }"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_blobs() {
+        let ctor = method(&[0x20, 0x01, 0x01, 0x08]).unwrap();
+        assert!(decode(&[0x02, 0x00, 0, 0, 0, 0, 0, 0], &ctor, &Fake).is_err());
+        assert!(decode(&[0x01, 0x00, 0x01], &ctor, &Fake).is_err());
+
+        // An array claiming four billion elements ends at the end of the blob
+        let ctor = method(&[0x20, 0x01, 0x01, 0x1D, 0x08]).unwrap();
+        assert!(decode(&[0x01, 0x00, 0xFE, 0xFF, 0xFF, 0xFF, 0x00], &ctor, &Fake).is_err());
+    }
+}
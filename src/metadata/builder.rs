@@ -0,0 +1,931 @@
+//! Assembles an [`AssemblyReport`] from the image and its metadata tables.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{format, string::String, string::ToString, vec, vec::Vec};
+use core::fmt::Write;
+
+use const_encrypt::obf;
+use uuid::Uuid;
+
+use super::attribute::{self, AttributeNames, Value};
+use super::image::{CLI_FLAGS, Image};
+use super::reader::{Reader, malformed};
+use super::report::{
+    AssemblyIdentity, AssemblyReport, AttributeReport, PInvokeImport, ResourceLocation,
+    ResourceReport, TypeReport,
+};
+use super::sha1::public_key_token;
+use super::signature::{self, MAX_DEPTH, Names, SigType, Token};
+use super::tables::*;
+use crate::error::Result;
+use crate::pe::CorFlags;
+
+const TYPE_VISIBILITY_MASK: u32 = 0x07;
+const TYPE_INTERFACE: u32 = 0x20;
+const TYPE_ABSTRACT: u32 = 0x80;
+const TYPE_SEALED: u32 = 0x100;
+
+const MEMBER_ACCESS_MASK: u16 = 0x07;
+const MEMBER_STATIC: u16 = 0x10;
+const FIELD_INIT_ONLY: u16 = 0x20;
+const FIELD_LITERAL: u16 = 0x40;
+const FIELD_RT_SPECIAL_NAME: u16 = 0x400;
+const METHOD_FINAL: u16 = 0x20;
+const METHOD_VIRTUAL: u16 = 0x40;
+const METHOD_NEW_SLOT: u16 = 0x100;
+const METHOD_ABSTRACT: u16 = 0x400;
+const METHOD_PINVOKE: u16 = 0x2000;
+const PARAM_OUT: u16 = 0x02;
+
+const SEMANTICS_SETTER: u16 = 0x01;
+const SEMANTICS_GETTER: u16 = 0x02;
+const SEMANTICS_ADD_ON: u16 = 0x08;
+
+const ASSEMBLY_REF_PUBLIC_KEY: u32 = 0x01;
+const RESOURCE_PUBLIC: u32 = 0x01;
+
+const TARGET_FRAMEWORK: &str = "System.Runtime.Versioning.TargetFrameworkAttribute";
+
+/// Decoded custom attributes, keyed by the token they are attached to.
+type Attributes = BTreeMap<Token, Vec<AttributeReport>>;
+
+/// Walks the tables once to index the relations the report needs.
+pub(crate) struct Builder<'a> {
+    image: &'a Image<'a>,
+    md: &'a Metadata<'a>,
+
+    /// TypeDef owning each MethodDef and Field, indexed by row id - 1.
+    method_owner: Vec<u32>,
+    field_owner: Vec<u32>,
+
+    /// Enclosing TypeDef of each nested type.
+    enclosing: BTreeMap<u32, u32>,
+
+    /// Generic parameter names of each type and method, in order.
+    generics: BTreeMap<Token, Vec<&'a str>>,
+
+    /// Interfaces implemented by each type.
+    interfaces: BTreeMap<u32, Vec<Token>>,
+
+    /// PropertyMap and EventMap row of each type, keyed by map table and type.
+    maps: BTreeMap<Token, u32>,
+
+    /// Accessor methods of each property and event, with their semantics.
+    semantics: BTreeMap<Token, Vec<(u16, u32)>>,
+
+    /// Methods that are accessors, which are shown as part of their property or event.
+    accessors: BTreeSet<u32>,
+
+    /// Element type of the underlying type of each enum this module defines.
+    enums: BTreeMap<String, u8>,
+}
+
+/// Generic context for naming `Var` and `MVar` types.
+#[derive(Clone, Copy)]
+struct Scope<'s, 'a> {
+    builder: &'s Builder<'a>,
+    ty: u32,
+    method: u32,
+}
+
+impl<'a> Builder<'a> {
+    pub fn new(image: &'a Image<'a>, md: &'a Metadata<'a>) -> Result<Self> {
+        let mut builder = Self {
+            image,
+            md,
+            method_owner: vec![0; md.rows(METHOD_DEF) as usize],
+            field_owner: vec![0; md.rows(FIELD) as usize],
+            enclosing: BTreeMap::new(),
+            generics: BTreeMap::new(),
+            interfaces: BTreeMap::new(),
+            maps: BTreeMap::new(),
+            semantics: BTreeMap::new(),
+            accessors: BTreeSet::new(),
+            enums: BTreeMap::new(),
+        };
+
+        for ty in md.rids(TYPE_DEF) {
+            for (column, table) in [(4, FIELD), (5, METHOD_DEF)] {
+                let owners = match table {
+                    FIELD => &mut builder.field_owner,
+                    _ => &mut builder.method_owner,
+                };
+                for rid in md.list(TYPE_DEF, ty, column, table)? {
+                    if let Some(owner) = owners.get_mut((rid as usize).wrapping_sub(1)) {
+                        *owner = ty;
+                    }
+                }
+            }
+        }
+
+        for rid in md.rids(NESTED_CLASS) {
+            let row = md.row(NESTED_CLASS, rid)?;
+            builder.enclosing.insert(row.get(0), row.get(1));
+        }
+
+        for rid in md.rids(INTERFACE_IMPL) {
+            let row = md.row(INTERFACE_IMPL, rid)?;
+            if let Some(interface) = md.decode(Coded::TypeDefOrRef, row.get(1))? {
+                builder
+                    .interfaces
+                    .entry(row.get(0))
+                    .or_default()
+                    .push(interface);
+            }
+        }
+
+        for map in [PROPERTY_MAP, EVENT_MAP] {
+            for rid in md.rids(map) {
+                builder.maps.insert((map, md.row(map, rid)?.get(0)), rid);
+            }
+        }
+
+        let mut generics = BTreeMap::<Token, Vec<(u16, &str)>>::new();
+        for rid in md.rids(GENERIC_PARAM) {
+            let row = md.row(GENERIC_PARAM, rid)?;
+            if let Some(owner) = md.decode(Coded::TypeOrMethodDef, row.get(2))? {
+                let name = md.string(row.get(3))?;
+                generics
+                    .entry(owner)
+                    .or_default()
+                    .push((row.get(0) as u16, name));
+            }
+        }
+        for (owner, mut params) in generics {
+            params.sort_by_key(|(number, _)| *number);
+            let names = params.into_iter().map(|(_, name)| name).collect();
+            builder.generics.insert(owner, names);
+        }
+
+        for rid in md.rids(METHOD_SEMANTICS) {
+            let row = md.row(METHOD_SEMANTICS, rid)?;
+            if let Some(association) = md.decode(Coded::HasSemantics, row.get(2))? {
+                let method = row.get(1);
+                builder
+                    .semantics
+                    .entry(association)
+                    .or_default()
+                    .push((row.get(0) as u16, method));
+                builder.accessors.insert(method);
+            }
+        }
+
+        for ty in md.rids(TYPE_DEF) {
+            if builder.base_name(ty)?.as_deref() != Some("System.Enum") {
+                continue;
+            }
+
+            // The instance field `value__` holds the value of an enum
+            for field in md.list(TYPE_DEF, ty, 4, FIELD)? {
+                let row = md.row(FIELD, field)?;
+                if row.get(0) as u16 & MEMBER_STATIC == 0 {
+                    if let Some(element) = element_type(&signature::field(md.blob(row.get(2))?)?) {
+                        let name = builder.full_name((TYPE_DEF, ty), 0)?;
+                        builder.enums.insert(name, element);
+                    }
+                    break;
+                }
+            }
+        }
+
+        Ok(builder)
+    }
+
+    pub fn build(&self) -> Result<AssemblyReport> {
+        let md = self.md;
+        let cli = &self.image.cli;
+
+        let module = md.row(MODULE, 1)?;
+        let mvid = md
+            .guid(module.get(2))?
+            .map(|guid| Uuid::from_bytes_le(guid).hyphenated().to_string());
+
+        let (mut attributes, target_framework) = self.attributes()?;
+        let mut assembly_attributes = attributes.remove(&(ASSEMBLY, 1)).unwrap_or_default();
+        assembly_attributes.extend(attributes.remove(&(MODULE, 1)).unwrap_or_default());
+
+        let types = md
+            .rids(TYPE_DEF)
+            .map(|ty| self.type_report(ty, attributes.remove(&(TYPE_DEF, ty)).unwrap_or_default()))
+            .filter(|report| !matches!(report, Ok(report) if report.name == "<Module>" && report.members.is_empty()))
+            .collect::<Result<_>>()?;
+
+        Ok(AssemblyReport {
+            identity: self.identity()?,
+            module: md.string(module.get(1))?.into(),
+            mvid,
+            runtime_version: md.version.into(),
+            target_framework,
+            cli_version: format!(
+                "{}.{}",
+                cli.major_runtime_version, cli.minor_runtime_version
+            ),
+            platform: self.image.platform().into(),
+            flags: CLI_FLAGS
+                .iter()
+                .filter(|(flag, _)| cli.flags.contains(*flag))
+                .map(|(_, name)| (*name).into())
+                .collect(),
+            entry_point: self.entry_point()?,
+            assembly_refs: md
+                .rids(ASSEMBLY_REF)
+                .map(|rid| self.assembly_ref(rid))
+                .collect::<Result<_>>()?,
+            module_refs: md
+                .rids(MODULE_REF)
+                .map(|rid| Ok(md.string(md.row(MODULE_REF, rid)?.get(0))?.into()))
+                .collect::<Result<_>>()?,
+            pinvoke_imports: self.pinvoke_imports()?,
+            types,
+            resources: self.resources()?,
+            attributes: assembly_attributes,
+        })
+    }
+
+    fn identity(&self) -> Result<Option<AssemblyIdentity>> {
+        if self.md.rows(ASSEMBLY) == 0 {
+            return Ok(None);
+        }
+
+        let row = self.md.row(ASSEMBLY, 1)?;
+        let public_key = self.md.blob(row.get(6))?;
+        Ok(Some(AssemblyIdentity {
+            name: self.md.string(row.get(7))?.into(),
+            version: format!(
+                "{}.{}.{}.{}",
+                row.get(1),
+                row.get(2),
+                row.get(3),
+                row.get(4)
+            ),
+            culture: culture(self.md.string(row.get(8))?),
+            public_key_token: (!public_key.is_empty()).then(|| hex(&public_key_token(public_key))),
+        }))
+    }
+
+    fn assembly_ref(&self, rid: u32) -> Result<AssemblyIdentity> {
+        let row = self.md.row(ASSEMBLY_REF, rid)?;
+        let key = self.md.blob(row.get(5))?;
+        let token = match row.get(4) & ASSEMBLY_REF_PUBLIC_KEY {
+            0 => hex(key),
+            _ => hex(&public_key_token(key)),
+        };
+
+        Ok(AssemblyIdentity {
+            name: self.md.string(row.get(6))?.into(),
+            version: format!(
+                "{}.{}.{}.{}",
+                row.get(0),
+                row.get(1),
+                row.get(2),
+                row.get(3)
+            ),
+            culture: culture(self.md.string(row.get(7))?),
+            public_key_token: (!key.is_empty()).then_some(token),
+        })
+    }
+
+    fn entry_point(&self) -> Result<Option<String>> {
+        let token = self.image.cli.entry_point;
+        if self.image.cli.flags.contains(CorFlags::NATIVE_ENTRYPOINT) {
+            return Ok(Some(format!("native code at RVA 0x{token:08X}")));
+        }
+
+        let rid = token & 0x00FF_FFFF;
+        match token >> 24 {
+            _ if token == 0 => Ok(None),
+            0x06 => {
+                let method = self.method_parts(rid)?;
+                Ok(Some(format!(
+                    "{}.{}({})",
+                    self.method_owner_name(rid)?,
+                    method.name,
+                    method.params
+                )))
+            }
+            0x26 => {
+                let file = self.md.string(self.md.row(FILE, rid)?.get(1))?;
+                Ok(Some(format!("file {file}")))
+            }
+            _ => Err(malformed(obf!("invalid entry point token").as_str())),
+        }
+    }
+
+    fn pinvoke_imports(&self) -> Result<Vec<PInvokeImport>> {
+        self.md
+            .rids(IMPL_MAP)
+            .map(|rid| {
+                let row = self.md.row(IMPL_MAP, rid)?;
+                let member = self.md.decode(Coded::MemberForwarded, row.get(1))?;
+                let method = match member {
+                    Some((METHOD_DEF, rid)) => {
+                        let name = self.md.string(self.md.row(METHOD_DEF, rid)?.get(3))?;
+                        format!("{}.{name}", self.method_owner_name(rid)?)
+                    }
+                    Some((_, rid)) => {
+                        let owner = owner(&self.field_owner, rid);
+                        let name = self.md.string(self.md.row(FIELD, rid)?.get(1))?;
+                        format!("{}.{name}", self.full_name((TYPE_DEF, owner), 0)?)
+                    }
+                    None => String::new(),
+                };
+
+                let import = self.md.string(row.get(2))?;
+                let module = self.md.row(MODULE_REF, row.get(3))?;
+                Ok(PInvokeImport {
+                    module: self.md.string(module.get(0))?.into(),
+                    entry_point: match import {
+                        "" => method.rsplit('.').next().unwrap_or_default().into(),
+                        import => import.into(),
+                    },
+                    method,
+                })
+            })
+            .collect()
+    }
+
+    fn resources(&self) -> Result<Vec<ResourceReport>> {
+        self.md
+            .rids(MANIFEST_RESOURCE)
+            .map(|rid| {
+                let row = self.md.row(MANIFEST_RESOURCE, rid)?;
+                let location = match self.md.decode(Coded::Implementation, row.get(3))? {
+                    None => {
+                        // Embedded resources are length-prefixed within the resources directory
+                        let resources = self.image.directory(self.image.cli.resources)?;
+                        let size = Reader::at(resources, row.get(0) as usize)?.u32()?;
+                        ResourceLocation::Embedded { size }
+                    }
+                    Some((FILE, rid)) => ResourceLocation::File {
+                        name: self.md.string(self.md.row(FILE, rid)?.get(1))?.into(),
+                    },
+                    Some((_, rid)) => ResourceLocation::Assembly {
+                        name: self.assembly_ref(rid)?.to_string(),
+                    },
+                };
+
+                Ok(ResourceReport {
+                    name: self.md.string(row.get(2))?.into(),
+                    public: row.get(1) & 0x07 == RESOURCE_PUBLIC,
+                    location,
+                })
+            })
+            .collect()
+    }
+
+    /// Collects the attributes of the assembly, its module and its types, by owner,
+    /// and the framework named by `TargetFrameworkAttribute`.
+    fn attributes(&self) -> Result<(Attributes, Option<String>)> {
+        let mut attributes = BTreeMap::<Token, Vec<AttributeReport>>::new();
+        let mut target_framework = None;
+
+        for rid in self.md.rids(CUSTOM_ATTRIBUTE) {
+            let row = self.md.row(CUSTOM_ATTRIBUTE, rid)?;
+            let Some(parent) = self.md.decode(Coded::HasCustomAttribute, row.get(0))? else {
+                continue;
+            };
+            if !matches!(parent.0, ASSEMBLY | MODULE | TYPE_DEF) {
+                continue;
+            }
+
+            let scope = Scope {
+                builder: self,
+                ty: if parent.0 == TYPE_DEF { parent.1 } else { 0 },
+                method: 0,
+            };
+
+            let (type_name, ctor) = match self.md.decode(Coded::CustomAttributeType, row.get(1))? {
+                Some((METHOD_DEF, rid)) => {
+                    let owner = owner(&self.method_owner, rid);
+                    let ctor = self.md.row(METHOD_DEF, rid)?.get(4);
+                    (self.full_name((TYPE_DEF, owner), 0)?, ctor)
+                }
+                Some((_, rid)) => {
+                    let member = self.md.row(MEMBER_REF, rid)?;
+                    let class = match self.md.decode(Coded::MemberRefParent, member.get(0))? {
+                        Some(token @ (TYPE_DEF | TYPE_REF, _)) => self.full_name(token, 0)?,
+                        Some(token @ (TYPE_SPEC, _)) => scope.type_name(token, 0)?,
+                        _ => return Err(malformed(obf!("invalid attribute constructor").as_str())),
+                    };
+                    (class, member.get(2))
+                }
+                None => return Err(malformed(obf!("invalid attribute constructor").as_str())),
+            };
+
+            // Arguments of enums defined in other assemblies may not decode
+            let arguments = signature::method(self.md.blob(ctor)?)
+                .and_then(|ctor| attribute::decode(self.md.blob(row.get(2))?, &ctor, &scope))
+                .ok();
+
+            if parent.0 == ASSEMBLY
+                && type_name == TARGET_FRAMEWORK
+                && let Some(Value::String(Some(framework))) =
+                    arguments.as_ref().and_then(|a| a.fixed.first())
+            {
+                target_framework = Some(framework.clone());
+            }
+
+            let arguments = arguments
+                .map(|arguments| {
+                    let fixed = arguments.fixed.iter().map(|value| value.to_string());
+                    let named = arguments
+                        .named
+                        .iter()
+                        .map(|(name, value)| format!("{name} = {value}"));
+                    fixed.chain(named).collect()
+                })
+                .unwrap_or_default();
+
+            attributes.entry(parent).or_default().push(AttributeReport {
+                type_name,
+                arguments,
+            });
+        }
+
+        Ok((attributes, target_framework))
+    }
+
+    fn type_report(&self, ty: u32, attributes: Vec<AttributeReport>) -> Result<TypeReport> {
+        let md = self.md;
+        let scope = Scope {
+            builder: self,
+            ty,
+            method: 0,
+        };
+
+        let mut members = Vec::new();
+        for field in md.list(TYPE_DEF, ty, 4, FIELD)? {
+            if let Some(field) = self.field(scope, field)? {
+                members.push(field);
+            }
+        }
+
+        for (map, list) in [(PROPERTY_MAP, PROPERTY), (EVENT_MAP, EVENT)] {
+            let Some(&map_rid) = self.maps.get(&(map, ty)) else {
+                continue;
+            };
+            for rid in md.list(map, map_rid, 1, list)? {
+                members.push(match list {
+                    PROPERTY => self.property(scope, rid)?,
+                    _ => self.event(scope, rid)?,
+                });
+            }
+        }
+
+        for method in md.list(TYPE_DEF, ty, 5, METHOD_DEF)? {
+            if !self.accessors.contains(&method) {
+                members.push(self.method(method)?);
+            }
+        }
+
+        Ok(TypeReport {
+            name: self.full_name((TYPE_DEF, ty), 0)?,
+            declaration: self.declaration(ty)?,
+            members,
+            attributes,
+        })
+    }
+
+    fn declaration(&self, ty: u32) -> Result<String> {
+        let row = self.md.row(TYPE_DEF, ty)?;
+        let flags = row.get(0);
+        let scope = Scope {
+            builder: self,
+            ty,
+            method: 0,
+        };
+
+        let mut declaration = String::from(match flags & TYPE_VISIBILITY_MASK {
+            1 | 2 => "public",
+            3 => "private",
+            4 => "protected",
+            6 => "private protected",
+            7 => "protected internal",
+            _ => "internal",
+        });
+
+        let base = self.base_name(ty)?;
+        let kind = match base.as_deref() {
+            _ if flags & TYPE_INTERFACE != 0 => "interface",
+            Some("System.Enum") => "enum",
+            Some("System.ValueType") => "struct",
+            Some("System.MulticastDelegate") => "delegate",
+            _ => "class",
+        };
+
+        if kind == "class" {
+            match (flags & TYPE_ABSTRACT != 0, flags & TYPE_SEALED != 0) {
+                (true, true) => declaration.push_str(" static"),
+                (true, false) => declaration.push_str(" abstract"),
+                (false, true) => declaration.push_str(" sealed"),
+                (false, false) => {}
+            }
+        }
+
+        let _ = write!(
+            declaration,
+            " {kind} {}",
+            self.short_name((TYPE_DEF, ty), 0)?
+        );
+        if let Some(params) = self.generics.get(&(TYPE_DEF, ty)) {
+            let _ = write!(declaration, "<{}>", params.join(", "));
+        }
+
+        // Base types implied by the kind are left out, as C# does
+        let mut bases = Vec::new();
+        if kind == "class"
+            && base.as_deref() != Some("System.Object")
+            && let Some(token) = self.md.decode(Coded::TypeDefOrRef, row.get(3))?
+        {
+            bases.push(scope.type_name(token, 0)?);
+        }
+        for interface in self.interfaces.get(&ty).into_iter().flatten() {
+            bases.push(scope.type_name(*interface, 0)?);
+        }
+        if !bases.is_empty() {
+            let _ = write!(declaration, " : {}", bases.join(", "));
+        }
+
+        Ok(declaration)
+    }
+
+    fn field(&self, scope: Scope, rid: u32) -> Result<Option<String>> {
+        let row = self.md.row(FIELD, rid)?;
+        let flags = row.get(0) as u16;
+        if flags & FIELD_RT_SPECIAL_NAME != 0 {
+            return Ok(None);
+        }
+
+        let mut modifiers = vec![access(flags)];
+        if flags & FIELD_LITERAL != 0 {
+            modifiers.push("const");
+        } else {
+            if flags & MEMBER_STATIC != 0 {
+                modifiers.push("static");
+            }
+            if flags & FIELD_INIT_ONLY != 0 {
+                modifiers.push("readonly");
+            }
+        }
+
+        let ty = signature::field(self.md.blob(row.get(2))?)?.render(&scope, 0)?;
+        let name = self.md.string(row.get(1))?;
+        Ok(Some(format!("{} {ty} {name}", modifiers.join(" "))))
+    }
+
+    fn method(&self, rid: u32) -> Result<String> {
+        let method = self.method_parts(rid)?;
+        let mut modifiers = vec![access(method.flags)];
+        modifiers.extend(method_modifiers(method.flags));
+        if method.flags & METHOD_PINVOKE != 0 {
+            modifiers.push("extern");
+        }
+
+        Ok(match method.ret {
+            Some(ret) => format!(
+                "{} {ret} {}({})",
+                modifiers.join(" "),
+                method.name,
+                method.params
+            ),
+            None => format!("{} {}({})", modifiers.join(" "), method.name, method.params),
+        })
+    }
+
+    /// Renders the name, return type and parameter list of a method.
+    fn method_parts(&self, rid: u32) -> Result<MethodParts> {
+        let md = self.md;
+        let row = md.row(METHOD_DEF, rid)?;
+        let flags = row.get(2) as u16;
+        let owner = owner(&self.method_owner, rid);
+        let scope = Scope {
+            builder: self,
+            ty: owner,
+            method: rid,
+        };
+        let sig = signature::method(md.blob(row.get(4))?)?;
+
+        // Constructors are named after their type and have no return type
+        let mut ret = Some(sig.ret.render(&scope, 0)?);
+        let mut name = match md.string(row.get(3))? {
+            ".ctor" | ".cctor" if owner != 0 => {
+                ret = None;
+                self.short_name((TYPE_DEF, owner), 0)?
+                    .rsplit('.')
+                    .next()
+                    .unwrap_or_default()
+                    .into()
+            }
+            name => String::from(name),
+        };
+        if let Some(params) = self.generics.get(&(METHOD_DEF, rid)) {
+            let _ = write!(name, "<{}>", params.join(", "));
+        }
+
+        let mut names = BTreeMap::new();
+        for param in md.list(METHOD_DEF, rid, 5, PARAM)? {
+            let row = md.row(PARAM, param)?;
+            names.insert(row.get(1), (row.get(0) as u16, md.string(row.get(2))?));
+        }
+
+        let params = sig
+            .params
+            .iter()
+            .enumerate()
+            .map(|(index, param)| {
+                let ty = param.render(&scope, 0)?;
+                Ok(match names.get(&(index as u32 + 1)) {
+                    Some((flags, name)) if flags & PARAM_OUT != 0 && ty.starts_with("ref ") => {
+                        format!("out {} {name}", &ty[4..])
+                    }
+                    Some((_, name)) if !name.is_empty() => format!("{ty} {name}"),
+                    _ => ty,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(MethodParts {
+            flags,
+            ret,
+            name,
+            params: params.join(", "),
+        })
+    }
+
+    fn property(&self, scope: Scope, rid: u32) -> Result<String> {
+        let row = self.md.row(PROPERTY, rid)?;
+        let sig = signature::property(self.md.blob(row.get(2))?)?;
+        let accessors = self
+            .semantics
+            .get(&(PROPERTY, rid))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let mut flags = Vec::new();
+        for (semantics, keyword) in [(SEMANTICS_GETTER, "get"), (SEMANTICS_SETTER, "set")] {
+            if let Some((_, method)) = accessors.iter().find(|(s, _)| s & semantics != 0) {
+                flags.push((self.md.row(METHOD_DEF, *method)?.get(2) as u16, keyword));
+            }
+        }
+
+        // The property is as visible as its most visible accessor
+        let primary = flags
+            .iter()
+            .map(|(flags, _)| *flags)
+            .max_by_key(|flags| flags & MEMBER_ACCESS_MASK);
+        let primary = primary.unwrap_or(0);
+        let mut modifiers = vec![access(primary)];
+        modifiers.extend(method_modifiers(primary));
+
+        let ty = sig.ret.render(&scope, 0)?;
+        let name = match sig.params.is_empty() {
+            true => String::from(self.md.string(row.get(1))?),
+            false => {
+                let params = sig
+                    .params
+                    .iter()
+                    .map(|param| param.render(&scope, 0))
+                    .collect::<Result<Vec<_>>>()?;
+                format!("this[{}]", params.join(", "))
+            }
+        };
+
+        let accessors = flags
+            .iter()
+            .map(|(flags, keyword)| {
+                match flags & MEMBER_ACCESS_MASK == primary & MEMBER_ACCESS_MASK {
+                    true => format!("{keyword};"),
+                    false => format!("{} {keyword};", access(*flags)),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(format!(
+            "{} {ty} {name} {{ {} }}",
+            modifiers.join(" "),
+            accessors.join(" ")
+        ))
+    }
+
+    fn event(&self, scope: Scope, rid: u32) -> Result<String> {
+        let row = self.md.row(EVENT, rid)?;
+        let accessors = self
+            .semantics
+            .get(&(EVENT, rid))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let flags = match accessors
+            .iter()
+            .find(|(semantics, _)| semantics & SEMANTICS_ADD_ON != 0)
+        {
+            Some((_, method)) => self.md.row(METHOD_DEF, *method)?.get(2) as u16,
+            None => 0,
+        };
+
+        let mut modifiers = vec![access(flags)];
+        modifiers.extend(method_modifiers(flags));
+
+        let ty = match self.md.decode(Coded::TypeDefOrRef, row.get(2))? {
+            Some(token) => scope.type_name(token, 0)?,
+            None => String::from("object"),
+        };
+        let name = self.md.string(row.get(1))?;
+        Ok(format!("{} event {ty} {name}", modifiers.join(" ")))
+    }
+
+    /// Full name of the type declaring a method.
+    fn method_owner_name(&self, rid: u32) -> Result<String> {
+        self.full_name((TYPE_DEF, owner(&self.method_owner, rid)), 0)
+    }
+
+    /// Full name of the type a TypeDef extends, if any.
+    fn base_name(&self, ty: u32) -> Result<Option<String>> {
+        let extends = self.md.row(TYPE_DEF, ty)?.get(3);
+        match self.md.decode(Coded::TypeDefOrRef, extends)? {
+            Some(token @ (TYPE_DEF | TYPE_REF, _)) => self.full_name(token, 0).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Reflection-style name of a TypeDef or TypeRef, with `+` before nested types.
+    fn full_name(&self, (table, rid): Token, depth: usize) -> Result<String> {
+        let (outer, namespace, name) = self.name_parts((table, rid), depth)?;
+        Ok(match outer {
+            Some(outer) => format!("{}+{name}", self.full_name(outer, depth + 1)?),
+            None if namespace.is_empty() => name.into(),
+            None => format!("{namespace}.{name}"),
+        })
+    }
+
+    /// C#-style name of a TypeDef or TypeRef: no namespace or generic arity.
+    fn short_name(&self, (table, rid): Token, depth: usize) -> Result<String> {
+        let (outer, _, name) = self.name_parts((table, rid), depth)?;
+        let name = name.split('`').next().unwrap_or_default();
+        Ok(match outer {
+            Some(outer) => format!("{}.{name}", self.short_name(outer, depth + 1)?),
+            None => name.into(),
+        })
+    }
+
+    /// Enclosing type, namespace and name of a TypeDef or TypeRef.
+    fn name_parts(
+        &self,
+        (table, rid): Token,
+        depth: usize,
+    ) -> Result<(Option<Token>, &'a str, &'a str)> {
+        if depth > MAX_DEPTH {
+            return Err(malformed(obf!("types nested too deeply").as_str()));
+        }
+
+        match table {
+            TYPE_DEF => {
+                let row = self.md.row(TYPE_DEF, rid)?;
+                let outer = self.enclosing.get(&rid).map(|&outer| (TYPE_DEF, outer));
+                Ok((
+                    outer,
+                    self.md.string(row.get(2))?,
+                    self.md.string(row.get(1))?,
+                ))
+            }
+            TYPE_REF => {
+                let row = self.md.row(TYPE_REF, rid)?;
+                let outer = self
+                    .md
+                    .decode(Coded::ResolutionScope, row.get(0))?
+                    .filter(|(table, _)| *table == TYPE_REF);
+                Ok((
+                    outer,
+                    self.md.string(row.get(2))?,
+                    self.md.string(row.get(1))?,
+                ))
+            }
+            _ => Err(malformed(obf!("expected a TypeDef or TypeRef").as_str())),
+        }
+    }
+}
+
+/// Rendered pieces of a method declaration.
+struct MethodParts {
+    flags: u16,
+    ret: Option<String>,
+    name: String,
+    params: String,
+}
+
+impl Names for Scope<'_, '_> {
+    fn type_name(&self, token: Token, depth: usize) -> Result<String> {
+        let builder = self.builder;
+        match token.0 {
+            TYPE_SPEC => {
+                let blob = builder
+                    .md
+                    .blob(builder.md.row(TYPE_SPEC, token.1)?.get(0))?;
+                signature::type_spec(blob, depth + 1)?.render(self, depth + 1)
+            }
+            _ => builder.short_name(token, depth),
+        }
+    }
+
+    fn generic_name(&self, method: bool, index: u32) -> String {
+        let (owner, prefix) = match method {
+            true => ((METHOD_DEF, self.method), "!!"),
+            false => ((TYPE_DEF, self.ty), "!"),
+        };
+
+        match self
+            .builder
+            .generics
+            .get(&owner)
+            .and_then(|names| names.get(index as usize))
+        {
+            Some(name) => String::from(*name),
+            None => format!("{prefix}{index}"),
+        }
+    }
+}
+
+impl AttributeNames for Scope<'_, '_> {
+    fn full_name(&self, token: Token) -> Result<String> {
+        match token.0 {
+            TYPE_SPEC => self.type_name(token, 0),
+            _ => self.builder.full_name(token, 0),
+        }
+    }
+
+    fn enum_underlying(&self, name: &str) -> Option<u8> {
+        self.builder.enums.get(name).copied()
+    }
+}
+
+/// C# keyword for the access bits shared by fields and methods.
+fn access(flags: u16) -> &'static str {
+    match flags & MEMBER_ACCESS_MASK {
+        2 => "private protected",
+        3 => "internal",
+        4 => "protected",
+        5 => "protected internal",
+        6 => "public",
+        _ => "private",
+    }
+}
+
+/// C# keywords for the static and virtual bits of a method.
+fn method_modifiers(flags: u16) -> Vec<&'static str> {
+    let mut modifiers = Vec::new();
+    if flags & MEMBER_STATIC != 0 {
+        modifiers.push("static");
+    }
+
+    let new_slot = flags & METHOD_NEW_SLOT != 0;
+    if flags & METHOD_ABSTRACT != 0 {
+        modifiers.push("abstract");
+    } else if flags & METHOD_VIRTUAL != 0 {
+        match (new_slot, flags & METHOD_FINAL != 0) {
+            (false, true) => modifiers.push("sealed override"),
+            (false, false) => modifiers.push("override"),
+            (true, false) => modifiers.push("virtual"),
+
+            // A sealed new slot is how C# implements interface methods
+            (true, true) => {}
+        }
+    }
+
+    modifiers
+}
+
+/// Element type of a primitive, as an enum's underlying type.
+fn element_type(ty: &SigType) -> Option<u8> {
+    Some(match ty {
+        SigType::Bool => 0x02,
+        SigType::Char => 0x03,
+        SigType::I1 => 0x04,
+        SigType::U1 => 0x05,
+        SigType::I2 => 0x06,
+        SigType::U2 => 0x07,
+        SigType::I4 => 0x08,
+        SigType::U4 => 0x09,
+        SigType::I8 => 0x0A,
+        SigType::U8 => 0x0B,
+        _ => return None,
+    })
+}
+
+/// Looks up the TypeDef owning a member, 0 if none does.
+fn owner(owners: &[u32], rid: u32) -> u32 {
+    (rid as usize)
+        .checked_sub(1)
+        .and_then(|index| owners.get(index))
+        .copied()
+        .unwrap_or(0)
+}
+
+fn culture(culture: &str) -> Option<String> {
+    (!culture.is_empty()).then(|| culture.into())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
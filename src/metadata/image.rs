@@ -0,0 +1,62 @@
+//! Locating the CLI header and metadata inside a PE image on disk.
+
+use crate::error::Result;
+use crate::pe::{Architecture, ClrImageInfo, CorFlags, DataDirectory, PeImage};
+
+/// A PE file read from disk, as opposed to one mapped by the loader.
+pub(crate) struct Image<'a> {
+    pe: PeImage<'a>,
+
+    /// The CLI header (`IMAGE_COR20_HEADER`).
+    pub cli: ClrImageInfo,
+}
+
+impl<'a> Image<'a> {
+    /// Parses the PE headers of `data` and reads its CLI header.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`PeImage::parse`] if `data` is not a PE file and
+    /// [`crate::error::ClrError::NotDotNet`] if it has no CLI header.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let pe = PeImage::parse(data)?;
+        let cli = ClrImageInfo::read(&pe)?;
+        Ok(Self { pe, cli })
+    }
+
+    /// Returns `size` bytes of the file starting at `rva`.
+    pub fn slice(&self, rva: u32, size: u32) -> Result<&'a [u8]> {
+        self.pe.slice(rva, size)
+    }
+
+    /// Returns the bytes of a data directory.
+    pub fn directory(&self, directory: DataDirectory) -> Result<&'a [u8]> {
+        self.slice(directory.virtual_address, directory.size)
+    }
+
+    /// Describes the processor architecture the image runs on.
+    pub fn platform(&self) -> &'static str {
+        let required = self.cli.flags.contains(CorFlags::REQUIRED_32BIT);
+        let preferred = self.cli.flags.contains(CorFlags::PREFERRED_32BIT);
+
+        // Same interpretation as `corflags.exe`: PREFERRED is only meaningful with REQUIRED
+        match self.cli.required_architecture() {
+            None if preferred && required => "AnyCPU (32-bit preferred)",
+            None => "AnyCPU",
+            Some(Architecture::X86) => "x86",
+            Some(Architecture::X64) => "x64",
+            Some(Architecture::Arm64) => "ARM64",
+            Some(Architecture::Unknown(_)) => "unknown",
+        }
+    }
+}
+
+/// Names of the CLI header flags, in the spelling of `corflags.exe`.
+pub(crate) const CLI_FLAGS: [(CorFlags, &str); 6] = [
+    (CorFlags::ILONLY, "ILONLY"),
+    (CorFlags::REQUIRED_32BIT, "32BITREQUIRED"),
+    (CorFlags::STRONGNAMESIGNED, "STRONGNAMESIGNED"),
+    (CorFlags::NATIVE_ENTRYPOINT, "NATIVE_ENTRYPOINT"),
+    (CorFlags::TRACKDEBUGDATA, "TRACKDEBUGDATA"),
+    (CorFlags::PREFERRED_32BIT, "32BITPREFERRED"),
+];
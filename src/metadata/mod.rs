@@ -0,0 +1,19 @@
+//! Offline inspection of .NET assemblies.
+//!
+//! [`AssemblyReport`] reads the ECMA-335 metadata of a PE image straight from its
+//! bytes, so an assembly can be examined without loading it into a CLR: its identity,
+//! references, P/Invoke imports, types and members, resources and attributes.
+
+mod attribute;
+mod builder;
+mod image;
+mod reader;
+mod report;
+mod sha1;
+mod signature;
+mod tables;
+
+pub use report::{
+    AssemblyIdentity, AssemblyReport, AttributeReport, PInvokeImport, ResourceLocation,
+    ResourceReport, TypeReport,
+};
@@ -0,0 +1,187 @@
+//! Bounds-checked little-endian cursor over metadata bytes.
+
+use alloc::string::ToString;
+
+use const_encrypt::obf;
+
+use crate::error::{ClrError, Result};
+
+/// Sequential reader over a byte slice; every read fails instead of panicking.
+#[derive(Clone)]
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Creates a reader positioned at the start of `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Creates a reader positioned at `offset`, which may be at most `data.len()`.
+    pub fn at(data: &'a [u8], offset: usize) -> Result<Self> {
+        if offset > data.len() {
+            return Err(truncated());
+        }
+
+        Ok(Self { data, pos: offset })
+    }
+
+    /// Current offset from the start of the data.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Number of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    /// Reads the next `len` bytes.
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).ok_or_else(truncated)?;
+        let bytes = self.data.get(self.pos..end).ok_or_else(truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Skips `len` bytes.
+    pub fn skip(&mut self, len: usize) -> Result<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    /// Advances to the next multiple of `align` from the start of the data.
+    pub fn align(&mut self, align: usize) -> Result<()> {
+        let padding = self.pos.next_multiple_of(align) - self.pos;
+        self.skip(padding)
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    /// Reads an ECMA-335 compressed unsigned integer (II.23.2).
+    pub fn compressed_u32(&mut self) -> Result<u32> {
+        let first = self.u8()?;
+        match first {
+            _ if first & 0x80 == 0 => Ok(first.into()),
+            _ if first & 0xC0 == 0x80 => Ok(u32::from(first & 0x3F) << 8 | u32::from(self.u8()?)),
+            _ if first & 0xE0 == 0xC0 => {
+                let rest = self.bytes(3)?;
+                Ok(u32::from(first & 0x1F) << 24
+                    | u32::from(rest[0]) << 16
+                    | u32::from(rest[1]) << 8
+                    | u32::from(rest[2]))
+            }
+            _ => Err(malformed(obf!("invalid compressed integer").as_str())),
+        }
+    }
+
+    /// Reads an ECMA-335 compressed signed integer (II.23.2).
+    pub fn compressed_i32(&mut self) -> Result<i32> {
+        let start = self.pos;
+        let value = self.compressed_u32()?;
+
+        // The sign bit was rotated into bit 0 of a 7, 14 or 29 bit value
+        let bits = match self.pos - start {
+            1 => 7,
+            2 => 14,
+            _ => 29,
+        };
+        let magnitude = (value >> 1) as i32;
+        Ok(if value & 1 == 0 {
+            magnitude
+        } else {
+            magnitude - (1 << (bits - 1))
+        })
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+}
+
+/// Reads a NUL-terminated UTF-8 string starting at `offset`.
+pub(crate) fn c_str(data: &[u8], offset: usize) -> Result<&str> {
+    let bytes = data.get(offset..).ok_or_else(truncated)?;
+    let len = bytes.iter().position(|&b| b == 0).ok_or_else(truncated)?;
+    core::str::from_utf8(&bytes[..len])
+        .map_err(|_| malformed(obf!("invalid UTF-8 string").as_str()))
+}
+
+/// Error for data that ends before a structure does.
+pub(crate) fn truncated() -> ClrError {
+    malformed(obf!("unexpected end of data").as_str())
+}
+
+/// Error for data that does not follow the expected layout.
+pub(crate) fn malformed(reason: &str) -> ClrError {
+    ClrError::InvalidMetadata(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compressed_integers() {
+        // Examples from ECMA-335 II.23.2
+        let cases: &[(&[u8], u32)] = &[
+            (&[0x03], 0x03),
+            (&[0x7F], 0x7F),
+            (&[0x80, 0x80], 0x80),
+            (&[0xAE, 0x57], 0x2E57),
+            (&[0xBF, 0xFF], 0x3FFF),
+            (&[0xC0, 0x00, 0x40, 0x00], 0x4000),
+            (&[0xDF, 0xFF, 0xFF, 0xFF], 0x1FFF_FFFF),
+        ];
+        for (bytes, value) in cases {
+            assert_eq!(Reader::new(bytes).compressed_u32().unwrap(), *value);
+        }
+
+        let signed: &[(&[u8], i32)] = &[
+            (&[0x06], 3),
+            (&[0x7B], -3),
+            (&[0x80, 0x80], 64),
+            (&[0x01], -64),
+            (&[0xC0, 0x00, 0x40, 0x00], 8192),
+            (&[0x80, 0x01], -8192),
+            (&[0xDF, 0xFF, 0xFF, 0xFE], 268_435_455),
+            (&[0xC0, 0x00, 0x00, 0x01], -268_435_456),
+        ];
+        for (bytes, value) in signed {
+            assert_eq!(Reader::new(bytes).compressed_i32().unwrap(), *value);
+        }
+
+        assert!(Reader::new(&[0xFF]).compressed_u32().is_err());
+        assert!(Reader::new(&[0xC0, 0x00]).compressed_u32().is_err());
+    }
+
+    #[test]
+    fn test_reads_are_bounds_checked() {
+        let mut reader = Reader::new(&[1, 2, 3]);
+        assert_eq!(reader.u16().unwrap(), 0x0201);
+        assert!(reader.u16().is_err());
+        assert_eq!(reader.u8().unwrap(), 3);
+        assert!(reader.bytes(usize::MAX).is_err());
+        assert!(Reader::at(&[0; 2], 3).is_err());
+
+        assert_eq!(c_str(b"#Strings\0x", 1).unwrap(), "Strings");
+        assert!(c_str(b"abc", 0).is_err());
+    }
+}
@@ -0,0 +1,734 @@
+//! Public report types and their text layout.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use super::builder::Builder;
+use super::image::Image;
+use super::tables::Metadata;
+use crate::error::Result;
+
+/// Everything the metadata of an assembly says about it, read without a CLR.
+///
+/// # Example
+///
+/// ```no_run
+/// use rustclr::AssemblyReport;
+///
+/// let buffer = std::fs::read("Seatbelt.exe").unwrap();
+/// let report = AssemblyReport::parse(&buffer).unwrap();
+/// println!("{report}");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AssemblyReport {
+    /// Identity from the Assembly table; `None` for a module that is not an assembly.
+    pub identity: Option<AssemblyIdentity>,
+
+    /// Name of the module, usually the file name it was compiled to.
+    pub module: String,
+
+    /// Module version id, unique to each compilation.
+    pub mvid: Option<String>,
+
+    /// Version of the runtime the assembly was built against (e.g. `v4.0.30319`).
+    pub runtime_version: String,
+
+    /// Framework from `TargetFrameworkAttribute` (e.g. `.NETFramework,Version=v4.8`).
+    pub target_framework: Option<String>,
+
+    /// Version of the CLI header (e.g. `2.5`).
+    pub cli_version: String,
+
+    /// Processor architecture, as `corflags.exe` reports it.
+    pub platform: String,
+
+    /// Flags set in the CLI header (e.g. `ILONLY`).
+    pub flags: Vec<String>,
+
+    /// Method the runtime calls first, rendered as `Namespace.Type.Method(params)`.
+    pub entry_point: Option<String>,
+
+    /// Assemblies this one references.
+    pub assembly_refs: Vec<AssemblyIdentity>,
+
+    /// Native modules referenced by P/Invoke declarations.
+    pub module_refs: Vec<String>,
+
+    /// Native functions imported through P/Invoke.
+    pub pinvoke_imports: Vec<PInvokeImport>,
+
+    /// Types defined by the assembly, nested types included.
+    pub types: Vec<TypeReport>,
+
+    /// Manifest resources.
+    pub resources: Vec<ResourceReport>,
+
+    /// Custom attributes applied to the assembly and its module.
+    pub attributes: Vec<AttributeReport>,
+}
+
+/// Name, version, culture and public key token of an assembly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AssemblyIdentity {
+    /// Simple name (e.g. `mscorlib`).
+    pub name: String,
+
+    /// Four part version (e.g. `4.0.0.0`).
+    pub version: String,
+
+    /// Culture of a satellite assembly; `None` when neutral.
+    pub culture: Option<String>,
+
+    /// Lowercase hex public key token; `None` when not strong named.
+    pub public_key_token: Option<String>,
+}
+
+/// A native function imported through P/Invoke.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PInvokeImport {
+    /// Native module, as written in `DllImport` (e.g. `kernel32.dll`).
+    pub module: String,
+
+    /// Exported function name.
+    pub entry_point: String,
+
+    /// Managed declaration, as `Namespace.Type.Method`.
+    pub method: String,
+}
+
+/// A type defined by the assembly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TypeReport {
+    /// Full name in reflection syntax (e.g. ``Namespace.Outer+Inner`1``).
+    pub name: String,
+
+    /// C# declaration (e.g. `public sealed class Program : Base, IDisposable`).
+    pub declaration: String,
+
+    /// Fields, properties, events and methods as C# declarations.
+    pub members: Vec<String>,
+
+    /// Custom attributes applied to the type.
+    pub attributes: Vec<AttributeReport>,
+}
+
+/// A manifest resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ResourceReport {
+    /// Resource name (e.g. `Program.Properties.Resources.resources`).
+    pub name: String,
+
+    /// Whether other assemblies may read the resource.
+    pub public: bool,
+
+    /// Where the resource data lives.
+    pub location: ResourceLocation,
+}
+
+/// Where the data of a manifest resource lives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "lowercase"))]
+pub enum ResourceLocation {
+    /// Embedded in this file.
+    Embedded {
+        /// Size of the resource data in bytes.
+        size: u32,
+    },
+
+    /// In another file of a multi-file assembly.
+    File {
+        /// Name of the file.
+        name: String,
+    },
+
+    /// In another assembly.
+    Assembly {
+        /// Display name of the assembly.
+        name: String,
+    },
+}
+
+/// A custom attribute and its arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AttributeReport {
+    /// Full name of the attribute type.
+    pub type_name: String,
+
+    /// Constructor arguments followed by `Name = value` pairs, as C# literals.
+    /// Empty if the arguments could not be decoded.
+    pub arguments: Vec<String>,
+}
+
+impl AssemblyReport {
+    /// Reads the metadata of a .NET PE image.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`crate::pe::PeImage::parse`] for buffers that are not PE
+    /// images, [`crate::error::ClrError::NotDotNet`] for native images and
+    /// [`crate::error::ClrError::InvalidMetadata`] if the metadata is malformed.
+    pub fn parse(buffer: &[u8]) -> Result<Self> {
+        let image = Image::parse(buffer)?;
+        let metadata = Metadata::parse(image.directory(image.cli.metadata)?)?;
+        Builder::new(&image, &metadata)?.build()
+    }
+}
+
+impl fmt::Display for AssemblyIdentity {
+    /// Formats the identity as an assembly display name.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, Version={}, Culture={}, PublicKeyToken={}",
+            self.name,
+            self.version,
+            self.culture.as_deref().unwrap_or("neutral"),
+            self.public_key_token.as_deref().unwrap_or("null")
+        )
+    }
+}
+
+impl fmt::Display for AttributeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}({})]", self.type_name, self.arguments.join(", "))
+    }
+}
+
+impl fmt::Display for ResourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Embedded { size } => write!(f, "embedded, {size} bytes"),
+            Self::File { name } => write!(f, "file {name}"),
+            Self::Assembly { name } => write!(f, "in {name}"),
+        }
+    }
+}
+
+impl fmt::Display for AssemblyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.identity {
+            Some(identity) => writeln!(f, "Assembly:     {identity}")?,
+            None => writeln!(f, "Assembly:     (module only)")?,
+        }
+        writeln!(f, "Module:       {}", self.module)?;
+        if let Some(mvid) = &self.mvid {
+            writeln!(f, "MVID:         {mvid}")?;
+        }
+        match &self.target_framework {
+            Some(framework) => writeln!(f, "Runtime:      {} ({framework})", self.runtime_version)?,
+            None => writeln!(f, "Runtime:      {}", self.runtime_version)?,
+        }
+        writeln!(f, "CLI header:   {}", self.cli_version)?;
+        writeln!(f, "Platform:     {}", self.platform)?;
+        writeln!(f, "Flags:        {}", self.flags.join(", "))?;
+        if let Some(entry_point) = &self.entry_point {
+            writeln!(f, "Entry point:  {entry_point}")?;
+        }
+
+        section(f, "References", &self.assembly_refs, |f, identity| {
+            write!(f, "{identity}")
+        })?;
+        section(f, "Module references", &self.module_refs, |f, name| {
+            write!(f, "{name}")
+        })?;
+        section(f, "P/Invoke imports", &self.pinvoke_imports, |f, import| {
+            write!(
+                f,
+                "{}!{}  ({})",
+                import.module, import.entry_point, import.method
+            )
+        })?;
+        section(f, "Resources", &self.resources, |f, resource| {
+            let visibility = if resource.public { "public" } else { "private" };
+            write!(
+                f,
+                "{}  ({visibility}, {})",
+                resource.name, resource.location
+            )
+        })?;
+        section(f, "Attributes", &self.attributes, |f, attribute| {
+            write!(f, "{attribute}")
+        })?;
+        section(f, "Types", &self.types, |f, ty| {
+            for attribute in &ty.attributes {
+                write!(f, "{attribute}\n  ")?;
+            }
+            write!(f, "{}", ty.declaration)?;
+            for member in &ty.members {
+                write!(f, "\n      {member}")?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Writes a titled, indented list, or nothing when `items` is empty.
+fn section<T>(
+    f: &mut fmt::Formatter<'_>,
+    title: &str,
+    items: &[T],
+    mut item: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+) -> fmt::Result {
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    write!(f, "\n{title} ({}):", items.len())?;
+    for value in items {
+        f.write_str("\n  ")?;
+        item(f, value)?;
+    }
+    writeln!(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ClrError;
+    use alloc::{string::ToString, vec};
+
+    /// A table cell of two or four bytes.
+    enum Cell {
+        W(u16),
+        D(u32),
+    }
+
+    use Cell::{D, W};
+
+    #[derive(Default)]
+    struct Heaps {
+        strings: Vec<u8>,
+        blobs: Vec<u8>,
+    }
+
+    impl Heaps {
+        fn string(&mut self, value: &str) -> Cell {
+            if value.is_empty() {
+                return W(0);
+            }
+            let offset = self.strings.len();
+            self.strings.extend(value.as_bytes());
+            self.strings.push(0);
+            W(offset as u16)
+        }
+
+        fn blob(&mut self, value: &[u8]) -> Cell {
+            let offset = self.blobs.len();
+            self.blobs.push(value.len() as u8);
+            self.blobs.extend(value);
+            W(offset as u16)
+        }
+    }
+
+    fn stream_header(out: &mut Vec<u8>, offset: usize, size: usize, name: &str) {
+        out.extend((offset as u32).to_le_bytes());
+        out.extend((size as u32).to_le_bytes());
+        out.extend(name.as_bytes());
+        out.push(0);
+        while !out.len().is_multiple_of(4) {
+            out.push(0);
+        }
+    }
+
+    /// Builds the metadata of a small assembly, as `csc` would for:
+    ///
+    /// ```csharp
+    /// [assembly: TargetFramework(".NETFramework,Version=v4.8")]
+    /// namespace Demo {
+    ///     public sealed class Program : IDisposable {
+    ///         private static int count;
+    ///         public string Name { get; }
+    ///         public static void Main(string[] args) { }
+    ///         [DllImport("user32.dll", EntryPoint = "MessageBoxW")]
+    ///         public static extern int MessageBox(IntPtr hWnd, string text, string, uint);
+    ///         public void Dispose() { }
+    ///     }
+    ///     public enum Color { Red }
+    /// }
+    /// ```
+    fn metadata() -> Vec<u8> {
+        let mut h = Heaps {
+            strings: vec![0],
+            blobs: vec![0],
+        };
+
+        let mut tables: Vec<(usize, Vec<Vec<Cell>>)> = vec![
+            (
+                0x00,
+                vec![vec![W(0), h.string("Demo.exe"), W(1), W(0), W(0)]],
+            ),
+            (
+                0x01,
+                vec![
+                    vec![W(0x06), h.string("Object"), h.string("System")],
+                    vec![
+                        W(0x06),
+                        h.string("TargetFrameworkAttribute"),
+                        h.string("System.Runtime.Versioning"),
+                    ],
+                    vec![W(0x06), h.string("Enum"), h.string("System")],
+                    vec![W(0x06), h.string("IDisposable"), h.string("System")],
+                ],
+            ),
+            (
+                0x02,
+                vec![
+                    vec![D(0), h.string("<Module>"), W(0), W(0), W(1), W(1)],
+                    vec![
+                        D(0x101),
+                        h.string("Program"),
+                        h.string("Demo"),
+                        W(0x05),
+                        W(1),
+                        W(1),
+                    ],
+                    vec![
+                        D(0x101),
+                        h.string("Color"),
+                        h.string("Demo"),
+                        W(0x0D),
+                        W(2),
+                        W(6),
+                    ],
+                ],
+            ),
+            (
+                0x04,
+                vec![
+                    vec![W(0x11), h.string("count"), h.blob(&[0x06, 0x08])],
+                    vec![W(0x606), h.string("value__"), h.blob(&[0x06, 0x08])],
+                    vec![W(0x56), h.string("Red"), h.blob(&[0x06, 0x11, 0x0C])],
+                ],
+            ),
+            (
+                0x06,
+                vec![
+                    vec![
+                        D(0x2050),
+                        W(0),
+                        W(0x16),
+                        h.string("Main"),
+                        h.blob(&[0x00, 0x01, 0x01, 0x1D, 0x0E]),
+                        W(1),
+                    ],
+                    vec![
+                        D(0),
+                        W(0x80),
+                        W(0x2016),
+                        h.string("MessageBox"),
+                        h.blob(&[0x00, 0x04, 0x08, 0x18, 0x0E, 0x0E, 0x09]),
+                        W(2),
+                    ],
+                    vec![
+                        D(0x2054),
+                        W(0),
+                        W(0x806),
+                        h.string("get_Name"),
+                        h.blob(&[0x20, 0x00, 0x0E]),
+                        W(4),
+                    ],
+                    vec![
+                        D(0x2058),
+                        W(0),
+                        W(0x1806),
+                        h.string(".ctor"),
+                        h.blob(&[0x20, 0x00, 0x01]),
+                        W(4),
+                    ],
+                    vec![
+                        D(0x205C),
+                        W(0),
+                        W(0x1E6),
+                        h.string("Dispose"),
+                        h.blob(&[0x20, 0x00, 0x01]),
+                        W(4),
+                    ],
+                ],
+            ),
+            (
+                0x08,
+                vec![
+                    vec![W(0), W(1), h.string("args")],
+                    vec![W(0), W(1), h.string("hWnd")],
+                    vec![W(0), W(2), h.string("text")],
+                ],
+            ),
+            (0x09, vec![vec![W(2), W(0x11)]]),
+            (
+                0x0A,
+                vec![vec![
+                    W(0x11),
+                    h.string(".ctor"),
+                    h.blob(&[0x20, 0x01, 0x01, 0x0E]),
+                ]],
+            ),
+        ];
+
+        let mut value = vec![0x01, 0x00, 26];
+        value.extend(b".NETFramework,Version=v4.8");
+        value.extend([0x00, 0x00]);
+        tables.push((0x0C, vec![vec![W(0x2E), W(0x0B), h.blob(&value)]]));
+
+        tables.push((0x15, vec![vec![W(2), W(1)]]));
+        tables.push((
+            0x17,
+            vec![vec![W(0), h.string("Name"), h.blob(&[0x28, 0x00, 0x0E])]],
+        ));
+        tables.push((0x18, vec![vec![W(0x02), W(3), W(0x03)]]));
+        tables.push((0x1A, vec![vec![h.string("user32.dll")]]));
+        tables.push((
+            0x1C,
+            vec![vec![W(0x0100), W(0x05), h.string("MessageBoxW"), W(1)]],
+        ));
+        tables.push((
+            0x20,
+            vec![vec![
+                D(0x8004),
+                W(1),
+                W(2),
+                W(3),
+                W(4),
+                D(0),
+                W(0),
+                h.string("Demo"),
+                W(0),
+            ]],
+        ));
+        tables.push((
+            0x23,
+            vec![vec![
+                W(4),
+                W(0),
+                W(0),
+                W(0),
+                D(0),
+                h.blob(&[0xB7, 0x7A, 0x5C, 0x56, 0x19, 0x34, 0xE0, 0x89]),
+                h.string("mscorlib"),
+                W(0),
+                W(0),
+            ]],
+        ));
+        tables.push((0x28, vec![vec![D(0), D(0x01), h.string("Demo.data"), W(0)]]));
+
+        let mut stream = vec![0, 0, 0, 0, 2, 0, 0, 1];
+        let valid = tables
+            .iter()
+            .fold(0u64, |valid, (table, _)| valid | 1 << table);
+        stream.extend(valid.to_le_bytes());
+        stream.extend(0u64.to_le_bytes());
+        for (_, rows) in &tables {
+            stream.extend((rows.len() as u32).to_le_bytes());
+        }
+        for cell in tables.iter().flat_map(|(_, rows)| rows).flatten() {
+            match cell {
+                W(value) => stream.extend(value.to_le_bytes()),
+                D(value) => stream.extend(value.to_le_bytes()),
+            }
+        }
+        for heap in [&mut stream, &mut h.strings, &mut h.blobs] {
+            while heap.len() % 4 != 0 {
+                heap.push(0);
+            }
+        }
+        let guid = [
+            0x78, 0x56, 0x34, 0x12, 0x34, 0x12, 0x78, 0x56, 0x9A, 0xBC, 0xDE, 0xF0, 0x12, 0x34,
+            0x56, 0x78,
+        ];
+
+        let mut root = vec![];
+        root.extend(0x424A_5342u32.to_le_bytes());
+        root.extend([1, 0, 1, 0, 0, 0, 0, 0]);
+        root.extend(12u32.to_le_bytes());
+        root.extend(b"v4.0.30319\0\0");
+        root.extend([0, 0, 4, 0]);
+
+        // Stream headers take 12 + 20 + 16 + 16 bytes after the 32 bytes of the root
+        let mut offset = 32 + 64;
+        let mut data: Vec<u8> = Vec::new();
+        for (name, heap) in [
+            ("#~", stream.as_slice()),
+            ("#Strings", h.strings.as_slice()),
+            ("#GUID", guid.as_slice()),
+            ("#Blob", h.blobs.as_slice()),
+        ] {
+            stream_header(&mut root, offset, heap.len(), name);
+            data.extend(heap);
+            offset += heap.len();
+        }
+        assert_eq!(root.len(), 32 + 64);
+        root.extend(data);
+        root
+    }
+
+    /// Wraps metadata into a PE32 image with one section, as an ILONLY x86/AnyCPU exe.
+    fn image() -> Vec<u8> {
+        let metadata = metadata();
+        let metadata_rva = 0x2048u32;
+        let resources_rva = metadata_rva + metadata.len() as u32;
+
+        let mut section = Vec::new();
+        section.extend(72u32.to_le_bytes());
+        section.extend([2, 0, 5, 0]);
+        section.extend(metadata_rva.to_le_bytes());
+        section.extend((metadata.len() as u32).to_le_bytes());
+        section.extend(1u32.to_le_bytes());
+        section.extend(0x0600_0001u32.to_le_bytes());
+        section.extend(resources_rva.to_le_bytes());
+        section.extend(9u32.to_le_bytes());
+        section.resize(72, 0);
+        section.extend(&metadata);
+        section.extend(5u32.to_le_bytes());
+        section.extend(b"hello");
+
+        let mut pe = vec![0u8; 0x200];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3C] = 0x80;
+        pe[0x80..0x84].copy_from_slice(b"PE\0\0");
+        pe[0x84..0x86].copy_from_slice(&0x014Cu16.to_le_bytes());
+        pe[0x86] = 1;
+        pe[0x94..0x96].copy_from_slice(&0xE0u16.to_le_bytes());
+        pe[0x96..0x98].copy_from_slice(&0x0102u16.to_le_bytes());
+
+        let optional = 0x98;
+        pe[optional..optional + 2].copy_from_slice(&0x010Bu16.to_le_bytes());
+        pe[optional + 92] = 16;
+        pe[optional + 96 + 14 * 8..][..4].copy_from_slice(&0x2000u32.to_le_bytes());
+        pe[optional + 96 + 14 * 8 + 4] = 72;
+
+        let header = optional + 0xE0;
+        pe[header..header + 5].copy_from_slice(b".text");
+        pe[header + 8..][..4].copy_from_slice(&(section.len() as u32).to_le_bytes());
+        pe[header + 12..][..4].copy_from_slice(&0x2000u32.to_le_bytes());
+        pe[header + 16..][..4].copy_from_slice(&(section.len() as u32).to_le_bytes());
+        pe[header + 20..][..4].copy_from_slice(&0x200u32.to_le_bytes());
+
+        pe.extend(section);
+        pe
+    }
+
+    #[test]
+    fn test_parse_assembly() {
+        let report = AssemblyReport::parse(&image()).unwrap();
+
+        assert_eq!(
+            report.identity.as_ref().unwrap().to_string(),
+            "Demo, Version=1.2.3.4, Culture=neutral, PublicKeyToken=null"
+        );
+        assert_eq!(report.module, "Demo.exe");
+        assert_eq!(
+            report.mvid.as_deref(),
+            Some("12345678-1234-5678-9abc-def012345678")
+        );
+        assert_eq!(report.runtime_version, "v4.0.30319");
+        assert_eq!(
+            report.target_framework.as_deref(),
+            Some(".NETFramework,Version=v4.8")
+        );
+        assert_eq!(report.cli_version, "2.5");
+        assert_eq!(report.platform, "AnyCPU");
+        assert_eq!(report.flags, ["ILONLY"]);
+        assert_eq!(
+            report.entry_point.as_deref(),
+            Some("Demo.Program.Main(string[] args)")
+        );
+
+        assert_eq!(
+            report.assembly_refs[0].to_string(),
+            "mscorlib, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089"
+        );
+        assert_eq!(report.module_refs, ["user32.dll"]);
+        assert_eq!(
+            report.pinvoke_imports,
+            [PInvokeImport {
+                module: "user32.dll".into(),
+                entry_point: "MessageBoxW".into(),
+                method: "Demo.Program.MessageBox".into(),
+            }]
+        );
+        assert_eq!(
+            report.resources,
+            [ResourceReport {
+                name: "Demo.data".into(),
+                public: true,
+                location: ResourceLocation::Embedded { size: 5 },
+            }]
+        );
+        assert_eq!(
+            report.attributes[0].to_string(),
+            "[System.Runtime.Versioning.TargetFrameworkAttribute(\".NETFramework,Version=v4.8\")]"
+        );
+
+        let [program, color] = report.types.as_slice() else {
+            panic!("expected two types, got {:?}", report.types);
+        };
+        assert_eq!(program.name, "Demo.Program");
+        assert_eq!(
+            program.declaration,
+            "public sealed class Program : IDisposable"
+        );
+        assert_eq!(
+            program.members,
+            [
+                "private static int count",
+                "public string Name { get; }",
+                "public static void Main(string[] args)",
+                "public static extern int MessageBox(IntPtr hWnd, string text, string, uint)",
+                "public Program()",
+                "public void Dispose()",
+            ]
+        );
+        assert_eq!(color.declaration, "public enum Color");
+        assert_eq!(color.members, ["public const Color Red"]);
+    }
+
+    #[test]
+    fn test_display() {
+        let text = AssemblyReport::parse(&image()).unwrap().to_string();
+
+        assert!(text.starts_with("Assembly:     Demo, Version=1.2.3.4"));
+        assert!(text.contains("Runtime:      v4.0.30319 (.NETFramework,Version=v4.8)\n"));
+        assert!(text.contains(
+            "\nP/Invoke imports (1):\n  user32.dll!MessageBoxW  (Demo.Program.MessageBox)\n"
+        ));
+        assert!(text.contains("\n  public enum Color\n      public const Color Red\n"));
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        assert!(matches!(
+            AssemblyReport::parse(&[b'x'; 64]),
+            Err(ClrError::BadMagic(_))
+        ));
+
+        // Without a CLI header the image is native code
+        let mut native = image();
+        native[0x98 + 96 + 14 * 8..][..8].fill(0);
+        assert!(matches!(
+            AssemblyReport::parse(&native),
+            Err(ClrError::NotDotNet)
+        ));
+    }
+
+    #[test]
+    fn test_damaged_images_fail_without_panicking() {
+        let image = image();
+        for len in 0..image.len() {
+            let _ = AssemblyReport::parse(&image[..len]);
+        }
+
+        for index in 0..image.len() {
+            for value in [0x00, 0x7F, 0xFF] {
+                let mut damaged = image.clone();
+                damaged[index] = value;
+                let _ = AssemblyReport::parse(&damaged);
+            }
+        }
+    }
+}
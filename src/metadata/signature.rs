@@ -0,0 +1,358 @@
+//! Decoding of signature blobs (ECMA-335 II.23.2) and rendering them C#-style.
+
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+
+use const_encrypt::obf;
+
+use super::reader::{Reader, malformed};
+use super::tables::{TYPE_DEF, TYPE_REF, TYPE_SPEC};
+use crate::error::Result;
+
+/// Nesting limit for types and signatures, so hostile blobs cannot exhaust the stack.
+pub(crate) const MAX_DEPTH: usize = 64;
+
+/// A row of the TypeDef, TypeRef or TypeSpec table.
+pub(crate) type Token = (usize, u32);
+
+/// A type as written in a signature.
+pub(crate) enum SigType {
+    Void,
+    Bool,
+    Char,
+    I1,
+    U1,
+    I2,
+    U2,
+    I4,
+    U4,
+    I8,
+    U8,
+    R4,
+    R8,
+    String,
+    Object,
+    IntPtr,
+    UIntPtr,
+    TypedReference,
+    Class(Token),
+    ValueType(Token),
+    Ptr(Box<SigType>),
+    ByRef(Box<SigType>),
+    SzArray(Box<SigType>),
+    Array(Box<SigType>, u32),
+    GenericInst(Box<SigType>, Vec<SigType>),
+    Var(u32),
+    MVar(u32),
+    FnPtr(Box<MethodSig>),
+}
+
+/// A method, property or function pointer signature.
+pub(crate) struct MethodSig {
+    /// Return type, or the type of a property.
+    pub ret: SigType,
+
+    /// Parameter types, without the trailing varargs.
+    pub params: Vec<SigType>,
+}
+
+/// Names the types a signature refers to.
+pub(crate) trait Names {
+    /// Name of a TypeDef, TypeRef or TypeSpec row.
+    fn type_name(&self, token: Token, depth: usize) -> Result<String>;
+
+    /// Name of a generic parameter of the enclosing type (`Var`) or method (`MVar`).
+    fn generic_name(&self, method: bool, index: u32) -> String;
+}
+
+const CALLCONV_MASK: u8 = 0x0F;
+const CALLCONV_FIELD: u8 = 0x06;
+const CALLCONV_PROPERTY: u8 = 0x08;
+const CALLCONV_GENERIC: u8 = 0x10;
+
+/// Decodes a MethodDefSig, MethodRefSig or StandAloneMethodSig.
+pub(crate) fn method(blob: &[u8]) -> Result<MethodSig> {
+    method_sig(&mut Reader::new(blob), 0)
+}
+
+/// Decodes a FieldSig.
+pub(crate) fn field(blob: &[u8]) -> Result<SigType> {
+    let mut reader = Reader::new(blob);
+    if reader.u8()? & CALLCONV_MASK != CALLCONV_FIELD {
+        return Err(malformed(obf!("not a field signature").as_str()));
+    }
+
+    sig_type(&mut reader, 0)
+}
+
+/// Decodes a PropertySig; `ret` holds the property type and `params` any indexer parameters.
+pub(crate) fn property(blob: &[u8]) -> Result<MethodSig> {
+    let mut reader = Reader::new(blob);
+    if reader.u8()? & CALLCONV_MASK != CALLCONV_PROPERTY {
+        return Err(malformed(obf!("not a property signature").as_str()));
+    }
+
+    let count = reader.compressed_u32()?;
+    let ret = sig_type(&mut reader, 0)?;
+    let params = (0..count)
+        .map(|_| sig_type(&mut reader, 0))
+        .collect::<Result<_>>()?;
+
+    Ok(MethodSig { ret, params })
+}
+
+/// Decodes a TypeSpec blob.
+pub(crate) fn type_spec(blob: &[u8], depth: usize) -> Result<SigType> {
+    sig_type(&mut Reader::new(blob), depth)
+}
+
+/// Reads a `TypeDefOrRefOrSpecEncoded` token.
+pub(crate) fn type_token(reader: &mut Reader) -> Result<Token> {
+    let value = reader.compressed_u32()?;
+    let table = match value & 0x03 {
+        0 => TYPE_DEF,
+        1 => TYPE_REF,
+        2 => TYPE_SPEC,
+        _ => return Err(malformed(obf!("invalid type token").as_str())),
+    };
+
+    Ok((table, value >> 2))
+}
+
+fn method_sig(reader: &mut Reader, depth: usize) -> Result<MethodSig> {
+    // Generic methods name their parameters in the GenericParam table instead
+    if reader.u8()? & CALLCONV_GENERIC != 0 {
+        reader.compressed_u32()?;
+    }
+
+    let count = reader.compressed_u32()?;
+    let ret = sig_type(reader, depth)?;
+    let mut params = Vec::new();
+    for _ in 0..count {
+        // Arguments after the sentinel are the varargs of a call site
+        if reader.remaining() > 0 && peek(reader)? == ELEMENT_TYPE_SENTINEL {
+            break;
+        }
+        params.push(sig_type(reader, depth)?);
+    }
+
+    Ok(MethodSig { ret, params })
+}
+
+const ELEMENT_TYPE_CMOD_REQD: u8 = 0x1F;
+const ELEMENT_TYPE_CMOD_OPT: u8 = 0x20;
+const ELEMENT_TYPE_SENTINEL: u8 = 0x41;
+const ELEMENT_TYPE_PINNED: u8 = 0x45;
+
+fn peek(reader: &Reader) -> Result<u8> {
+    reader.clone().u8()
+}
+
+fn sig_type(reader: &mut Reader, depth: usize) -> Result<SigType> {
+    if depth > MAX_DEPTH {
+        return Err(malformed(obf!("signature nested too deeply").as_str()));
+    }
+
+    let inner = |reader: &mut Reader| sig_type(reader, depth + 1).map(Box::new);
+    let ty = match reader.u8()? {
+        // Custom modifiers such as `modreq(IsVolatile)` do not change the type
+        ELEMENT_TYPE_CMOD_REQD | ELEMENT_TYPE_CMOD_OPT => {
+            type_token(reader)?;
+            return sig_type(reader, depth + 1);
+        }
+        ELEMENT_TYPE_PINNED => return sig_type(reader, depth + 1),
+        0x01 => SigType::Void,
+        0x02 => SigType::Bool,
+        0x03 => SigType::Char,
+        0x04 => SigType::I1,
+        0x05 => SigType::U1,
+        0x06 => SigType::I2,
+        0x07 => SigType::U2,
+        0x08 => SigType::I4,
+        0x09 => SigType::U4,
+        0x0A => SigType::I8,
+        0x0B => SigType::U8,
+        0x0C => SigType::R4,
+        0x0D => SigType::R8,
+        0x0E => SigType::String,
+        0x0F => SigType::Ptr(inner(reader)?),
+        0x10 => SigType::ByRef(inner(reader)?),
+        0x11 => SigType::ValueType(type_token(reader)?),
+        0x12 => SigType::Class(type_token(reader)?),
+        0x13 => SigType::Var(reader.compressed_u32()?),
+        0x14 => {
+            let element = inner(reader)?;
+            let rank = reader.compressed_u32()?;
+            let sizes = reader.compressed_u32()?;
+            for _ in 0..sizes {
+                reader.compressed_u32()?;
+            }
+            let bounds = reader.compressed_u32()?;
+            for _ in 0..bounds {
+                reader.compressed_i32()?;
+            }
+            SigType::Array(element, rank)
+        }
+        0x15 => {
+            let generic = inner(reader)?;
+            let count = reader.compressed_u32()?;
+            let args = (0..count)
+                .map(|_| sig_type(reader, depth + 1))
+                .collect::<Result<_>>()?;
+            SigType::GenericInst(generic, args)
+        }
+        0x16 => SigType::TypedReference,
+        0x18 => SigType::IntPtr,
+        0x19 => SigType::UIntPtr,
+        0x1B => SigType::FnPtr(Box::new(method_sig(reader, depth + 1)?)),
+        0x1C => SigType::Object,
+        0x1D => SigType::SzArray(inner(reader)?),
+        0x1E => SigType::MVar(reader.compressed_u32()?),
+        _ => return Err(malformed(obf!("unknown element type").as_str())),
+    };
+
+    Ok(ty)
+}
+
+impl SigType {
+    /// Renders the type as C# source would spell it.
+    pub fn render(&self, names: &impl Names, depth: usize) -> Result<String> {
+        if depth > MAX_DEPTH {
+            return Err(malformed(obf!("signature nested too deeply").as_str()));
+        }
+
+        let keyword = match self {
+            Self::Void => "void",
+            Self::Bool => "bool",
+            Self::Char => "char",
+            Self::I1 => "sbyte",
+            Self::U1 => "byte",
+            Self::I2 => "short",
+            Self::U2 => "ushort",
+            Self::I4 => "int",
+            Self::U4 => "uint",
+            Self::I8 => "long",
+            Self::U8 => "ulong",
+            Self::R4 => "float",
+            Self::R8 => "double",
+            Self::String => "string",
+            Self::Object => "object",
+            Self::IntPtr => "IntPtr",
+            Self::UIntPtr => "UIntPtr",
+            Self::TypedReference => "TypedReference",
+            Self::Class(token) | Self::ValueType(token) => {
+                return names.type_name(*token, depth + 1);
+            }
+            Self::Ptr(inner) => return Ok(format!("{}*", inner.render(names, depth + 1)?)),
+            Self::ByRef(inner) => return Ok(format!("ref {}", inner.render(names, depth + 1)?)),
+            Self::SzArray(inner) => return Ok(format!("{}[]", inner.render(names, depth + 1)?)),
+            Self::Array(inner, rank) => {
+                let commas = ",".repeat(rank.saturating_sub(1).min(32) as usize);
+                return Ok(format!("{}[{commas}]", inner.render(names, depth + 1)?));
+            }
+            Self::GenericInst(generic, args) => {
+                let name = generic.render(names, depth + 1)?;
+                let args = args
+                    .iter()
+                    .map(|arg| arg.render(names, depth + 1))
+                    .collect::<Result<Vec<_>>>()?;
+
+                // `Nullable<T>` has a spelling of its own
+                if name == "Nullable" && args.len() == 1 {
+                    return Ok(format!("{}?", args[0]));
+                }
+                return Ok(format!("{name}<{}>", args.join(", ")));
+            }
+            Self::Var(index) => return Ok(names.generic_name(false, *index)),
+            Self::MVar(index) => return Ok(names.generic_name(true, *index)),
+            Self::FnPtr(sig) => {
+                let mut types = sig
+                    .params
+                    .iter()
+                    .map(|param| param.render(names, depth + 1))
+                    .collect::<Result<Vec<_>>>()?;
+                types.push(sig.ret.render(names, depth + 1)?);
+                return Ok(format!("delegate*<{}>", types.join(", ")));
+            }
+        };
+
+        Ok(keyword.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    struct Fake;
+
+    impl Names for Fake {
+        fn type_name(&self, (table, rid): Token, _: usize) -> Result<String> {
+            Ok(match (table, rid) {
+                (TYPE_REF, 1) => "Nullable".into(),
+                (TYPE_REF, 2) => "Dictionary".into(),
+                _ => format!("T{table}_{rid}"),
+            })
+        }
+
+        fn generic_name(&self, method: bool, index: u32) -> String {
+            match method {
+                true => format!("M{index}"),
+                false => format!("T{index}"),
+            }
+        }
+    }
+
+    fn render(ty: &SigType) -> String {
+        ty.render(&Fake, 0).unwrap()
+    }
+
+    #[test]
+    fn test_method_signature() {
+        // static void Main(string[] args)
+        let sig = method(&[0x00, 0x01, 0x01, 0x1D, 0x0E]).unwrap();
+        assert_eq!(render(&sig.ret), "void");
+        assert_eq!(render(&sig.params[0]), "string[]");
+
+        // instance !!0 Get<T>(int32&, valuetype Nullable<int32>, int32[0...,0...])
+        let sig = method(&[
+            0x30, 0x01, 0x03, 0x1E, 0x00, 0x10, 0x08, 0x15, 0x11, 0x05, 0x01, 0x08, 0x14, 0x08,
+            0x02, 0x00, 0x02, 0x00, 0x00,
+        ])
+        .unwrap();
+        let params = sig.params.iter().map(render).collect::<Vec<_>>();
+        assert_eq!(render(&sig.ret), "M0");
+        assert_eq!(params, ["ref int", "int?", "int[,]"]);
+    }
+
+    #[test]
+    fn test_field_and_property_signatures() {
+        // Dictionary<string, List<!0>> with a modopt in front
+        let ty = field(&[
+            0x06, 0x20, 0x0D, 0x15, 0x12, 0x09, 0x02, 0x0E, 0x15, 0x12, 0x08, 0x01, 0x13, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(render(&ty), "Dictionary<string, T2_2<T0>>");
+        assert!(field(&[0x08, 0x00, 0x08]).is_err());
+
+        // instance int32 Item(string)
+        let sig = property(&[0x28, 0x01, 0x08, 0x0E]).unwrap();
+        assert_eq!(render(&sig.ret), "int");
+        assert_eq!(render(&sig.params[0]), "string");
+    }
+
+    #[test]
+    fn test_malformed_signatures() {
+        assert!(method(&[0x00, 0x02, 0x01, 0x08]).is_err());
+        assert!(field(&[0x06, 0x42]).is_err());
+
+        // A pointer chain deeper than the limit is rejected instead of recursing
+        let mut blob = alloc::vec![0x06];
+        blob.extend(core::iter::repeat_n(0x0F, MAX_DEPTH + 8));
+        blob.push(0x08);
+        assert!(field(&blob).is_err());
+
+        let err = type_spec(&[0x12, 0x03], 0).err().unwrap();
+        assert!(err.to_string().contains("invalid type token"));
+    }
+}
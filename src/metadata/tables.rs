@@ -0,0 +1,572 @@
+//! Metadata root, heaps and the `#~` table stream (ECMA-335 II.24).
+
+use alloc::vec::Vec;
+
+use const_encrypt::obf;
+
+use super::reader::{Reader, c_str, malformed, truncated};
+use crate::error::Result;
+
+pub(crate) const MODULE: usize = 0x00;
+pub(crate) const TYPE_REF: usize = 0x01;
+pub(crate) const TYPE_DEF: usize = 0x02;
+pub(crate) const FIELD_PTR: usize = 0x03;
+pub(crate) const FIELD: usize = 0x04;
+pub(crate) const METHOD_PTR: usize = 0x05;
+pub(crate) const METHOD_DEF: usize = 0x06;
+pub(crate) const PARAM_PTR: usize = 0x07;
+pub(crate) const PARAM: usize = 0x08;
+pub(crate) const INTERFACE_IMPL: usize = 0x09;
+pub(crate) const MEMBER_REF: usize = 0x0A;
+pub(crate) const CUSTOM_ATTRIBUTE: usize = 0x0C;
+const DECL_SECURITY: usize = 0x0E;
+const STAND_ALONE_SIG: usize = 0x11;
+pub(crate) const EVENT_MAP: usize = 0x12;
+pub(crate) const EVENT_PTR: usize = 0x13;
+pub(crate) const EVENT: usize = 0x14;
+pub(crate) const PROPERTY_MAP: usize = 0x15;
+pub(crate) const PROPERTY_PTR: usize = 0x16;
+pub(crate) const PROPERTY: usize = 0x17;
+pub(crate) const METHOD_SEMANTICS: usize = 0x18;
+pub(crate) const MODULE_REF: usize = 0x1A;
+pub(crate) const TYPE_SPEC: usize = 0x1B;
+pub(crate) const IMPL_MAP: usize = 0x1C;
+pub(crate) const ASSEMBLY: usize = 0x20;
+pub(crate) const ASSEMBLY_REF: usize = 0x23;
+pub(crate) const FILE: usize = 0x26;
+pub(crate) const EXPORTED_TYPE: usize = 0x27;
+pub(crate) const MANIFEST_RESOURCE: usize = 0x28;
+pub(crate) const NESTED_CLASS: usize = 0x29;
+pub(crate) const GENERIC_PARAM: usize = 0x2A;
+const METHOD_SPEC: usize = 0x2B;
+const GENERIC_PARAM_CONSTRAINT: usize = 0x2C;
+
+/// Number of tables defined for type system metadata.
+const TABLE_COUNT: usize = 0x2D;
+
+/// Marks a coded index tag that no table uses.
+const UNUSED: usize = usize::MAX;
+
+/// Coded index kinds (ECMA-335 II.24.2.6).
+#[derive(Clone, Copy)]
+pub(crate) enum Coded {
+    TypeDefOrRef,
+    HasConstant,
+    HasCustomAttribute,
+    HasFieldMarshal,
+    HasDeclSecurity,
+    MemberRefParent,
+    HasSemantics,
+    MethodDefOrRef,
+    MemberForwarded,
+    Implementation,
+    CustomAttributeType,
+    ResolutionScope,
+    TypeOrMethodDef,
+}
+
+impl Coded {
+    /// Tables selected by each tag value.
+    fn tables(self) -> &'static [usize] {
+        match self {
+            Self::TypeDefOrRef => &[TYPE_DEF, TYPE_REF, TYPE_SPEC],
+            Self::HasConstant => &[FIELD, PARAM, PROPERTY],
+            Self::HasCustomAttribute => &[
+                METHOD_DEF,
+                FIELD,
+                TYPE_REF,
+                TYPE_DEF,
+                PARAM,
+                INTERFACE_IMPL,
+                MEMBER_REF,
+                MODULE,
+                DECL_SECURITY,
+                PROPERTY,
+                EVENT,
+                STAND_ALONE_SIG,
+                MODULE_REF,
+                TYPE_SPEC,
+                ASSEMBLY,
+                ASSEMBLY_REF,
+                FILE,
+                EXPORTED_TYPE,
+                MANIFEST_RESOURCE,
+                GENERIC_PARAM,
+                GENERIC_PARAM_CONSTRAINT,
+                METHOD_SPEC,
+            ],
+            Self::HasFieldMarshal => &[FIELD, PARAM],
+            Self::HasDeclSecurity => &[TYPE_DEF, METHOD_DEF, ASSEMBLY],
+            Self::MemberRefParent => &[TYPE_DEF, TYPE_REF, MODULE_REF, METHOD_DEF, TYPE_SPEC],
+            Self::HasSemantics => &[EVENT, PROPERTY],
+            Self::MethodDefOrRef => &[METHOD_DEF, MEMBER_REF],
+            Self::MemberForwarded => &[FIELD, METHOD_DEF],
+            Self::Implementation => &[FILE, ASSEMBLY_REF, EXPORTED_TYPE],
+            Self::CustomAttributeType => &[UNUSED, UNUSED, METHOD_DEF, MEMBER_REF, UNUSED],
+            Self::ResolutionScope => &[MODULE, MODULE_REF, ASSEMBLY_REF, TYPE_REF],
+            Self::TypeOrMethodDef => &[TYPE_DEF, METHOD_DEF],
+        }
+    }
+
+    /// Number of low bits holding the tag.
+    fn bits(self) -> u32 {
+        usize::BITS - (self.tables().len() - 1).leading_zeros()
+    }
+}
+
+/// Kind of a table column.
+#[derive(Clone, Copy)]
+enum Column {
+    U16,
+    U32,
+    Str,
+    Guid,
+    Blob,
+    Index(usize),
+    Coded(Coded),
+}
+
+use Coded as C;
+use Column::{Blob, Guid, Index, Str, U16, U32};
+
+/// Columns of every table, indexed by table number (ECMA-335 II.22).
+const SCHEMA: [&[Column]; TABLE_COUNT] = [
+    // Module
+    &[U16, Str, Guid, Guid, Guid],
+    // TypeRef
+    &[Column::Coded(C::ResolutionScope), Str, Str],
+    // TypeDef
+    &[
+        U32,
+        Str,
+        Str,
+        Column::Coded(C::TypeDefOrRef),
+        Index(FIELD),
+        Index(METHOD_DEF),
+    ],
+    // FieldPtr
+    &[Index(FIELD)],
+    // Field
+    &[U16, Str, Blob],
+    // MethodPtr
+    &[Index(METHOD_DEF)],
+    // MethodDef
+    &[U32, U16, U16, Str, Blob, Index(PARAM)],
+    // ParamPtr
+    &[Index(PARAM)],
+    // Param
+    &[U16, U16, Str],
+    // InterfaceImpl
+    &[Index(TYPE_DEF), Column::Coded(C::TypeDefOrRef)],
+    // MemberRef
+    &[Column::Coded(C::MemberRefParent), Str, Blob],
+    // Constant
+    &[U16, Column::Coded(C::HasConstant), Blob],
+    // CustomAttribute
+    &[
+        Column::Coded(C::HasCustomAttribute),
+        Column::Coded(C::CustomAttributeType),
+        Blob,
+    ],
+    // FieldMarshal
+    &[Column::Coded(C::HasFieldMarshal), Blob],
+    // DeclSecurity
+    &[U16, Column::Coded(C::HasDeclSecurity), Blob],
+    // ClassLayout
+    &[U16, U32, Index(TYPE_DEF)],
+    // FieldLayout
+    &[U32, Index(FIELD)],
+    // StandAloneSig
+    &[Blob],
+    // EventMap
+    &[Index(TYPE_DEF), Index(EVENT)],
+    // EventPtr
+    &[Index(EVENT)],
+    // Event
+    &[U16, Str, Column::Coded(C::TypeDefOrRef)],
+    // PropertyMap
+    &[Index(TYPE_DEF), Index(PROPERTY)],
+    // PropertyPtr
+    &[Index(PROPERTY)],
+    // Property
+    &[U16, Str, Blob],
+    // MethodSemantics
+    &[U16, Index(METHOD_DEF), Column::Coded(C::HasSemantics)],
+    // MethodImpl
+    &[
+        Index(TYPE_DEF),
+        Column::Coded(C::MethodDefOrRef),
+        Column::Coded(C::MethodDefOrRef),
+    ],
+    // ModuleRef
+    &[Str],
+    // TypeSpec
+    &[Blob],
+    // ImplMap
+    &[
+        U16,
+        Column::Coded(C::MemberForwarded),
+        Str,
+        Index(MODULE_REF),
+    ],
+    // FieldRVA
+    &[U32, Index(FIELD)],
+    // EncLog
+    &[U32, U32],
+    // EncMap
+    &[U32],
+    // Assembly
+    &[U32, U16, U16, U16, U16, U32, Blob, Str, Str],
+    // AssemblyProcessor
+    &[U32],
+    // AssemblyOS
+    &[U32, U32, U32],
+    // AssemblyRef
+    &[U16, U16, U16, U16, U32, Blob, Str, Str, Blob],
+    // AssemblyRefProcessor
+    &[U32, Index(ASSEMBLY_REF)],
+    // AssemblyRefOS
+    &[U32, U32, U32, Index(ASSEMBLY_REF)],
+    // File
+    &[U32, Str, Blob],
+    // ExportedType
+    &[U32, U32, Str, Str, Column::Coded(C::Implementation)],
+    // ManifestResource
+    &[U32, U32, Str, Column::Coded(C::Implementation)],
+    // NestedClass
+    &[Index(TYPE_DEF), Index(TYPE_DEF)],
+    // GenericParam
+    &[U16, U16, Column::Coded(C::TypeOrMethodDef), Str],
+    // MethodSpec
+    &[Column::Coded(C::MethodDefOrRef), Blob],
+    // GenericParamConstraint
+    &[Index(GENERIC_PARAM), Column::Coded(C::TypeDefOrRef)],
+];
+
+/// Layout of one table inside the table stream.
+#[derive(Clone, Copy, Default)]
+struct Table {
+    rows: u32,
+    offset: usize,
+    row_size: usize,
+
+    /// Offset and size of each column within a row.
+    columns: [(u8, u8); 9],
+}
+
+/// The metadata of a module: its version string, heaps and tables.
+pub(crate) struct Metadata<'a> {
+    /// Version string of the metadata root, the runtime the module was built for.
+    pub version: &'a str,
+
+    strings: &'a [u8],
+    blobs: &'a [u8],
+    guids: &'a [u8],
+    data: &'a [u8],
+    tables: [Table; TABLE_COUNT],
+}
+
+/// A row of a metadata table.
+pub(crate) struct Row<'a> {
+    bytes: &'a [u8],
+    table: Table,
+}
+
+impl Row<'_> {
+    /// Reads a column as an integer, heap offset or raw index.
+    pub fn get(&self, column: usize) -> u32 {
+        let (offset, size) = self.table.columns[column];
+        let bytes = &self.bytes[offset as usize..][..size as usize];
+        match size {
+            2 => u16::from_le_bytes([bytes[0], bytes[1]]).into(),
+            _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+impl<'a> Metadata<'a> {
+    /// Parses the metadata root (`BSJB`) and the table stream it points to.
+    pub fn parse(root: &'a [u8]) -> Result<Self> {
+        let mut reader = Reader::new(root);
+        if reader.u32()? != 0x424A_5342 {
+            return Err(malformed(obf!("bad metadata signature").as_str()));
+        }
+
+        reader.skip(8)?;
+        let length = reader.u32()? as usize;
+        let version = reader.bytes(length)?;
+        let version = c_str(version, 0).or_else(|_| {
+            core::str::from_utf8(version)
+                .map_err(|_| malformed(obf!("invalid metadata version").as_str()))
+        })?;
+
+        reader.skip(2)?;
+        let streams = reader.u16()?;
+
+        let mut metadata = Self {
+            version,
+            strings: &[],
+            blobs: &[],
+            guids: &[],
+            data: &[],
+            tables: [Table::default(); TABLE_COUNT],
+        };
+
+        let mut tables = None;
+        for _ in 0..streams {
+            let offset = reader.u32()? as usize;
+            let size = reader.u32()? as usize;
+            let name = c_str(root, reader.position())?;
+            reader.skip(name.len() + 1)?;
+            reader.align(4)?;
+
+            let stream = offset
+                .checked_add(size)
+                .and_then(|end| root.get(offset..end))
+                .ok_or_else(truncated)?;
+            match name {
+                "#~" | "#-" => tables = Some(stream),
+                "#Strings" => metadata.strings = stream,
+                "#Blob" => metadata.blobs = stream,
+                "#GUID" => metadata.guids = stream,
+                _ => {}
+            }
+        }
+
+        let tables = tables.ok_or_else(|| malformed(obf!("missing table stream").as_str()))?;
+        metadata.parse_tables(tables)?;
+        Ok(metadata)
+    }
+
+    /// Reads the table stream header and lays out every present table.
+    fn parse_tables(&mut self, stream: &'a [u8]) -> Result<()> {
+        let mut reader = Reader::new(stream);
+        reader.skip(6)?;
+        let heap_sizes = reader.u8()?;
+        reader.skip(1)?;
+        let valid = reader.u64()?;
+        reader.skip(8)?;
+
+        if valid >> TABLE_COUNT != 0 {
+            return Err(malformed(obf!("unsupported metadata table").as_str()));
+        }
+
+        for (index, table) in self.tables.iter_mut().enumerate() {
+            if valid & (1 << index) != 0 {
+                table.rows = reader.u32()?;
+            }
+        }
+
+        // Unoptimized `#-` streams written by Edit and Continue carry an extra field
+        if heap_sizes & 0x40 != 0 {
+            reader.skip(4)?;
+        }
+
+        let heap = |bit: u8| if heap_sizes & bit != 0 { 4 } else { 2 };
+        let (string, guid, blob) = (heap(0x01), heap(0x02), heap(0x04));
+
+        let mut offset = reader.position();
+        for (index, schema) in SCHEMA.iter().enumerate() {
+            let mut row_size = 0;
+            let mut columns = [(0, 0); 9];
+            for (column, kind) in schema.iter().enumerate() {
+                let size = match *kind {
+                    U16 => 2,
+                    U32 => 4,
+                    Str => string,
+                    Guid => guid,
+                    Blob => blob,
+                    Index(table) => self.index_size(table),
+                    Column::Coded(coded) => self.coded_size(coded),
+                };
+                columns[column] = (row_size as u8, size as u8);
+                row_size += size;
+            }
+
+            let table = &mut self.tables[index];
+            table.offset = offset;
+            table.row_size = row_size;
+            table.columns = columns;
+            offset = (table.rows as usize)
+                .checked_mul(row_size)
+                .and_then(|size| size.checked_add(offset))
+                .ok_or_else(truncated)?;
+        }
+
+        if offset > stream.len() {
+            return Err(truncated());
+        }
+
+        self.data = stream;
+        Ok(())
+    }
+
+    /// Size of a simple index into `table`.
+    fn index_size(&self, table: usize) -> usize {
+        if self.tables[table].rows > 0xFFFF {
+            4
+        } else {
+            2
+        }
+    }
+
+    /// Size of a coded index, which grows once any of its tables outgrows the tag.
+    fn coded_size(&self, coded: Coded) -> usize {
+        let limit = 1u32 << (16 - coded.bits());
+        let large = coded
+            .tables()
+            .iter()
+            .filter(|&&table| table != UNUSED)
+            .any(|&table| self.tables[table].rows >= limit);
+        if large { 4 } else { 2 }
+    }
+
+    /// Number of rows in `table`.
+    pub fn rows(&self, table: usize) -> u32 {
+        self.tables[table].rows
+    }
+
+    /// Returns row `rid` (1-based) of `table`.
+    pub fn row(&self, table: usize, rid: u32) -> Result<Row<'a>> {
+        let layout = self.tables[table];
+        if rid == 0 || rid > layout.rows {
+            return Err(malformed(obf!("row index out of range").as_str()));
+        }
+
+        let start = layout.offset + (rid as usize - 1) * layout.row_size;
+        let bytes = self
+            .data
+            .get(start..start + layout.row_size)
+            .ok_or_else(truncated)?;
+        Ok(Row {
+            bytes,
+            table: layout,
+        })
+    }
+
+    /// Iterates over the row ids of `table`.
+    pub fn rids(&self, table: usize) -> core::ops::RangeInclusive<u32> {
+        1..=self.rows(table)
+    }
+
+    /// Reads a string from the `#Strings` heap.
+    pub fn string(&self, offset: u32) -> Result<&'a str> {
+        c_str(self.strings, offset as usize)
+    }
+
+    /// Reads a length-prefixed blob from the `#Blob` heap.
+    pub fn blob(&self, offset: u32) -> Result<&'a [u8]> {
+        let mut reader = Reader::at(self.blobs, offset as usize)?;
+        let length = reader.compressed_u32()?;
+        reader.bytes(length as usize)
+    }
+
+    /// Reads a GUID from the `#GUID` heap; index 0 means no GUID.
+    pub fn guid(&self, index: u32) -> Result<Option<[u8; 16]>> {
+        if index == 0 {
+            return Ok(None);
+        }
+
+        let start = (index as usize - 1) * 16;
+        let bytes = self.guids.get(start..start + 16).ok_or_else(truncated)?;
+        let mut guid = [0; 16];
+        guid.copy_from_slice(bytes);
+        Ok(Some(guid))
+    }
+
+    /// Splits a coded index into its table and row id, `None` for a null index.
+    pub fn decode(&self, coded: Coded, value: u32) -> Result<Option<(usize, u32)>> {
+        let bits = coded.bits();
+        let table = coded
+            .tables()
+            .get((value & ((1 << bits) - 1)) as usize)
+            .copied()
+            .filter(|&table| table != UNUSED)
+            .ok_or_else(|| malformed(obf!("invalid coded index").as_str()))?;
+
+        let rid = value >> bits;
+        Ok((rid != 0).then_some((table, rid)))
+    }
+
+    /// Resolves the run of `target` rows owned by row `rid` of `table`, whose
+    /// `column` holds the first one; the run ends where the next row's begins.
+    pub fn list(&self, table: usize, rid: u32, column: usize, target: usize) -> Result<Vec<u32>> {
+        let pointer = match target {
+            FIELD => FIELD_PTR,
+            METHOD_DEF => METHOD_PTR,
+            PARAM => PARAM_PTR,
+            EVENT => EVENT_PTR,
+            _ => PROPERTY_PTR,
+        };
+
+        // With pointer tables present, lists index them instead of the target
+        let indirect = self.rows(pointer) > 0;
+        let count = if indirect {
+            self.rows(pointer)
+        } else {
+            self.rows(target)
+        };
+
+        let start = self.row(table, rid)?.get(column);
+        let end = if rid < self.rows(table) {
+            self.row(table, rid + 1)?.get(column)
+        } else {
+            count + 1
+        };
+
+        let end = end.min(count + 1);
+        (start.max(1)..end)
+            .map(|index| match indirect {
+                true => Ok(self.row(pointer, index)?.get(0)),
+                false => Ok(index),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coded_index_bits() {
+        assert_eq!(Coded::TypeDefOrRef.bits(), 2);
+        assert_eq!(Coded::HasCustomAttribute.bits(), 5);
+        assert_eq!(Coded::HasFieldMarshal.bits(), 1);
+        assert_eq!(Coded::MemberRefParent.bits(), 3);
+        assert_eq!(Coded::CustomAttributeType.bits(), 3);
+        assert_eq!(Coded::ResolutionScope.bits(), 2);
+    }
+
+    #[test]
+    fn test_index_sizes() {
+        let mut metadata = Metadata {
+            version: "v4.0.30319",
+            strings: &[],
+            blobs: &[],
+            guids: &[],
+            data: &[],
+            tables: [Table::default(); TABLE_COUNT],
+        };
+        assert_eq!(metadata.coded_size(Coded::TypeDefOrRef), 2);
+
+        // 2^14 rows no longer fit next to a two bit tag
+        metadata.tables[TYPE_REF].rows = 0x3FFF;
+        assert_eq!(metadata.coded_size(Coded::TypeDefOrRef), 2);
+        metadata.tables[TYPE_REF].rows = 0x4000;
+        assert_eq!(metadata.coded_size(Coded::TypeDefOrRef), 4);
+        assert_eq!(metadata.coded_size(Coded::HasFieldMarshal), 2);
+        assert_eq!(metadata.index_size(TYPE_REF), 2);
+
+        metadata.tables[TYPE_REF].rows = 0x1_0000;
+        assert_eq!(metadata.index_size(TYPE_REF), 4);
+
+        assert_eq!(
+            metadata.decode(Coded::TypeDefOrRef, 0x0D).unwrap(),
+            Some((TYPE_REF, 3))
+        );
+        assert_eq!(metadata.decode(Coded::TypeDefOrRef, 0x01).unwrap(), None);
+        assert!(metadata.decode(Coded::TypeDefOrRef, 0x03).is_err());
+        assert!(metadata.decode(Coded::CustomAttributeType, 0x09).is_err());
+    }
+}
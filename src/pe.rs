@@ -0,0 +1,879 @@
+//! Bounds-checked parsing of PE/COFF images.
+//!
+//! [`PeImage`] reads the headers of an image as laid out on disk, straight from a byte
+//! slice: the DOS header, the NT headers of PE32 and PE32+ images, the data
+//! directories and the section table. Every read is checked against the slice, so a
+//! truncated or crafted buffer fails with [`ClrError::TruncatedHeader`],
+//! [`ClrError::BadMagic`] or [`ClrError::RvaOutOfRange`] instead of reading past it.
+//!
+//! [`ClrImageInfo`] reads the CLI header of a .NET image on top of it: its flags tell
+//! whether the image is mixed-mode and which processes it can load into.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rustclr::pe::{COM_DESCRIPTOR, PeImage};
+//!
+//! let buffer = std::fs::read("examples/sample.exe")?;
+//! let image = PeImage::parse(&buffer)?;
+//! if let Some(directory) = image.directory(COM_DESCRIPTOR) {
+//!     let cli_header = image.slice(directory.virtual_address, directory.size)?;
+//!     println!("CLI header of {} bytes", cli_header.len());
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use alloc::{string::ToString, vec::Vec};
+use core::fmt;
+
+use bitflags::bitflags;
+use const_encrypt::obf;
+
+use crate::error::{ClrError, Result};
+
+/// `IMAGE_DOS_HEADER.e_magic`: `MZ`.
+const DOS_SIGNATURE: u16 = 0x5A4D;
+
+/// Signature at the start of the NT headers: `PE\0\0`.
+const NT_SIGNATURE: u32 = 0x0000_4550;
+
+/// Size of `IMAGE_DOS_HEADER`, whose last field is `e_lfanew`.
+const DOS_HEADER_SIZE: usize = 64;
+
+/// Size of the signature and `IMAGE_FILE_HEADER`.
+const NT_HEADERS_SIZE: usize = 24;
+
+/// Size of an `IMAGE_SECTION_HEADER`.
+const SECTION_HEADER_SIZE: usize = 40;
+
+/// Most data directories an optional header can declare.
+const MAX_DIRECTORIES: usize = 16;
+
+/// Index of the CLI header (`IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR`) in the data directories.
+pub const COM_DESCRIPTOR: usize = 14;
+
+/// `IMAGE_FILE_HEADER.Machine` of x86 images, and of most AnyCPU assemblies.
+pub const MACHINE_I386: u16 = 0x014C;
+
+/// `IMAGE_FILE_HEADER.Machine` of x64 images.
+pub const MACHINE_AMD64: u16 = 0x8664;
+
+/// `IMAGE_FILE_HEADER.Machine` of ARM64 images.
+pub const MACHINE_ARM64: u16 = 0xAA64;
+
+/// `IMAGE_FILE_HEADER.Characteristics` bit of images that can run.
+pub const FILE_EXECUTABLE_IMAGE: u16 = 0x0002;
+
+/// `IMAGE_FILE_HEADER.Characteristics` bit of dynamic-link libraries.
+pub const FILE_DLL: u16 = 0x2000;
+
+/// `IMAGE_OPTIONAL_HEADER.Subsystem` of drivers and native system processes.
+pub const SUBSYSTEM_NATIVE: u16 = 1;
+
+/// A PE image read from disk, as opposed to one mapped by the loader.
+#[derive(Debug, Clone)]
+pub struct PeImage<'a> {
+    data: &'a [u8],
+
+    /// `IMAGE_DOS_HEADER.e_lfanew`: the file offset of the NT headers.
+    pub nt_offset: u32,
+
+    /// The COFF file header.
+    pub file_header: FileHeader,
+
+    /// The optional header, less its data directories.
+    pub optional_header: OptionalHeader,
+
+    /// The data directories, as many as the optional header declares.
+    pub directories: Vec<DataDirectory>,
+
+    /// The section table.
+    pub sections: Vec<SectionHeader>,
+}
+
+/// The fields of `IMAGE_FILE_HEADER`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHeader {
+    pub machine: u16,
+    pub number_of_sections: u16,
+    pub time_date_stamp: u32,
+    pub size_of_optional_header: u16,
+    pub characteristics: u16,
+}
+
+/// Layout of the optional header, given by its magic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeKind {
+    /// `IMAGE_OPTIONAL_HEADER32`, magic `0x10B`.
+    Pe32,
+
+    /// `IMAGE_OPTIONAL_HEADER64`, magic `0x20B`.
+    Pe32Plus,
+}
+
+/// The fields of `IMAGE_OPTIONAL_HEADER32`/`64` that locate and describe the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptionalHeader {
+    pub kind: PeKind,
+    pub address_of_entry_point: u32,
+    pub image_base: u64,
+    pub section_alignment: u32,
+    pub file_alignment: u32,
+    pub size_of_image: u32,
+    pub size_of_headers: u32,
+    pub subsystem: u16,
+    pub dll_characteristics: u16,
+}
+
+/// An `IMAGE_DATA_DIRECTORY` entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DataDirectory {
+    pub virtual_address: u32,
+    pub size: u32,
+}
+
+/// The fields of `IMAGE_SECTION_HEADER`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionHeader {
+    pub name: [u8; 8],
+    pub virtual_size: u32,
+    pub virtual_address: u32,
+    pub size_of_raw_data: u32,
+    pub pointer_to_raw_data: u32,
+    pub characteristics: u32,
+}
+
+/// The CLI header (`IMAGE_COR20_HEADER`) of a .NET image, with the machine type the
+/// runtime checks alongside its flags before loading the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClrImageInfo {
+    /// `IMAGE_FILE_HEADER.Machine`.
+    pub machine: u16,
+
+    /// Version of the CLI header, 2.5 for every runtime since .NET Framework 2.0.
+    pub major_runtime_version: u16,
+    pub minor_runtime_version: u16,
+
+    /// Location of the ECMA-335 metadata.
+    pub metadata: DataDirectory,
+
+    /// `IMAGE_COR20_HEADER.Flags`.
+    pub flags: CorFlags,
+
+    /// Token of the entry point method, or its RVA with [`CorFlags::NATIVE_ENTRYPOINT`].
+    pub entry_point: u32,
+
+    /// Location of the managed resources.
+    pub resources: DataDirectory,
+
+    /// Location of the strong name signature.
+    pub strong_name_signature: DataDirectory,
+}
+
+bitflags! {
+    /// `COMIMAGE_FLAGS_*` values of `IMAGE_COR20_HEADER.Flags`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CorFlags: u32 {
+        /// The image holds IL only, no native code.
+        const ILONLY = 0x0000_0001;
+        /// The image only runs in a 32-bit process.
+        const REQUIRED_32BIT = 0x0000_0002;
+        const IL_LIBRARY = 0x0000_0004;
+        const STRONGNAMESIGNED = 0x0000_0008;
+        /// The entry point is native code, given by RVA rather than by token.
+        const NATIVE_ENTRYPOINT = 0x0000_0010;
+        const TRACKDEBUGDATA = 0x0001_0000;
+        /// With [`CorFlags::REQUIRED_32BIT`], the image prefers a 32-bit process but
+        /// runs in any.
+        const PREFERRED_32BIT = 0x0002_0000;
+    }
+}
+
+/// A processor architecture the CLR runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Architecture {
+    X86,
+    X64,
+    Arm64,
+
+    /// Any other `IMAGE_FILE_HEADER.Machine` value.
+    Unknown(u16),
+}
+
+impl<'a> PeImage<'a> {
+    /// Parses the headers and section table of the PE image in `data`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::BadMagic`] if a signature or the optional header magic is
+    /// wrong, and [`ClrError::TruncatedHeader`] if a header extends past `data`.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let dos = header(data, 0, DOS_HEADER_SIZE, obf!("DOS header").as_str())?;
+        if u16_at(dos, 0) != DOS_SIGNATURE {
+            return Err(ClrError::BadMagic(obf!("DOS signature").to_string()));
+        }
+
+        let nt_offset = u32_at(dos, 0x3C);
+        let nt = header(
+            data,
+            nt_offset as usize,
+            NT_HEADERS_SIZE,
+            obf!("NT headers").as_str(),
+        )?;
+        if u32_at(nt, 0) != NT_SIGNATURE {
+            return Err(ClrError::BadMagic(obf!("PE signature").to_string()));
+        }
+
+        let file_header = FileHeader {
+            machine: u16_at(nt, 4),
+            number_of_sections: u16_at(nt, 6),
+            time_date_stamp: u32_at(nt, 8),
+            size_of_optional_header: u16_at(nt, 20),
+            characteristics: u16_at(nt, 22),
+        };
+
+        let optional_offset = nt_offset as usize + NT_HEADERS_SIZE;
+        let optional = header(
+            data,
+            optional_offset,
+            file_header.size_of_optional_header.into(),
+            obf!("optional header").as_str(),
+        )?;
+        let (optional_header, directories) = parse_optional_header(optional)?;
+
+        let table = header(
+            data,
+            optional_offset + optional.len(),
+            usize::from(file_header.number_of_sections) * SECTION_HEADER_SIZE,
+            obf!("section table").as_str(),
+        )?;
+        let sections = table
+            .as_chunks::<SECTION_HEADER_SIZE>()
+            .0
+            .iter()
+            .map(|entry| SectionHeader {
+                name: entry[..8].try_into().unwrap_or_default(),
+                virtual_size: u32_at(entry, 8),
+                virtual_address: u32_at(entry, 12),
+                size_of_raw_data: u32_at(entry, 16),
+                pointer_to_raw_data: u32_at(entry, 20),
+                characteristics: u32_at(entry, 36),
+            })
+            .collect();
+
+        Ok(Self {
+            data,
+            nt_offset,
+            file_header,
+            optional_header,
+            directories,
+            sections,
+        })
+    }
+
+    /// The bytes the image was parsed from.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the data directory at `index`, or `None` if the image does not declare
+    /// it or leaves it empty.
+    pub fn directory(&self, index: usize) -> Option<DataDirectory> {
+        self.directories
+            .get(index)
+            .copied()
+            .filter(|directory| directory.virtual_address != 0 && directory.size != 0)
+    }
+
+    /// Returns the section holding `rva`, if any.
+    pub fn section(&self, rva: u32) -> Option<&SectionHeader> {
+        self.sections.iter().find(|section| section.contains(rva))
+    }
+
+    /// Maps `rva` to the file offset of the byte the loader would place there.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::RvaOutOfRange`] if no byte of the file is mapped at `rva`.
+    pub fn rva_to_offset(&self, rva: u32) -> Result<usize> {
+        self.offset(rva)
+            .map(|(offset, _)| offset)
+            .filter(|&offset| offset < self.data.len())
+            .ok_or(ClrError::RvaOutOfRange(rva))
+    }
+
+    /// Returns the `size` bytes of the file mapped from `rva` on.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::RvaOutOfRange`] unless all of them are backed by the file:
+    /// part of the headers or of one section's raw data, and inside `data`.
+    pub fn slice(&self, rva: u32, size: u32) -> Result<&'a [u8]> {
+        let (offset, available) = self.offset(rva).ok_or(ClrError::RvaOutOfRange(rva))?;
+
+        // Bytes past the raw data would be zero-filled by the loader, but they are
+        // not in the file to be read
+        if size > available {
+            return Err(ClrError::RvaOutOfRange(rva));
+        }
+
+        offset
+            .checked_add(size as usize)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(ClrError::RvaOutOfRange(rva))
+    }
+
+    /// Returns the bytes of the data directory at `index`, or `None` if the image
+    /// does not declare it or leaves it empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::RvaOutOfRange`] if the directory is not backed by the file.
+    pub fn directory_data(&self, index: usize) -> Result<Option<&'a [u8]>> {
+        self.directory(index)
+            .map(|directory| self.slice(directory.virtual_address, directory.size))
+            .transpose()
+    }
+
+    /// Returns the file offset of `rva` and how many bytes of the file follow it in
+    /// the same region.
+    fn offset(&self, rva: u32) -> Option<(usize, u32)> {
+        if let Some(section) = self.section(rva) {
+            let delta = rva - section.virtual_address;
+            let available = section.size_of_raw_data.checked_sub(delta)?;
+            let offset = section.pointer_to_raw_data.checked_add(delta)?;
+            return (available > 0).then_some((offset as usize, available));
+        }
+
+        // The headers are mapped at RVA 0 as they are in the file
+        (rva < self.optional_header.size_of_headers)
+            .then(|| (rva as usize, self.optional_header.size_of_headers - rva))
+    }
+}
+
+impl SectionHeader {
+    /// The section name, without the NUL padding.
+    pub fn name(&self) -> &str {
+        let len = self.name.iter().position(|&b| b == 0).unwrap_or(8);
+        core::str::from_utf8(&self.name[..len]).unwrap_or_default()
+    }
+
+    /// Whether the section spans `rva` once mapped.
+    pub fn contains(&self, rva: u32) -> bool {
+        let extent = self.virtual_size.max(self.size_of_raw_data);
+        rva >= self.virtual_address && rva - self.virtual_address < extent
+    }
+}
+
+impl ClrImageInfo {
+    /// Parses the PE image in `buffer` and reads its CLI header.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`PeImage::parse`] and [`ClrImageInfo::read`].
+    pub fn parse(buffer: &[u8]) -> Result<Self> {
+        Self::read(&PeImage::parse(buffer)?)
+    }
+
+    /// Reads the CLI header of a parsed image.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::NotDotNet`] if the image has no CLI header and
+    /// [`ClrError::RvaOutOfRange`] if it is not backed by the file.
+    pub fn read(pe: &PeImage) -> Result<Self> {
+        let com = pe.directory(COM_DESCRIPTOR).ok_or(ClrError::NotDotNet)?;
+        let header = pe.slice(com.virtual_address, 72)?;
+        let directory = |offset| DataDirectory {
+            virtual_address: u32_at(header, offset),
+            size: u32_at(header, offset + 4),
+        };
+
+        Ok(Self {
+            machine: pe.file_header.machine,
+            major_runtime_version: u16_at(header, 4),
+            minor_runtime_version: u16_at(header, 6),
+            metadata: directory(8),
+            flags: CorFlags::from_bits_retain(u32_at(header, 16)),
+            entry_point: u32_at(header, 20),
+            resources: directory(24),
+            strong_name_signature: directory(32),
+        })
+    }
+
+    /// Whether the image holds native code besides IL, as C++/CLI images do. The loader
+    /// cannot fix up such images when they are loaded from memory.
+    pub fn is_mixed_mode(&self) -> bool {
+        !self.flags.contains(CorFlags::ILONLY)
+    }
+
+    /// Returns the architecture the process must have to load the image, or `None` if
+    /// it runs on any.
+    pub fn required_architecture(&self) -> Option<Architecture> {
+        let required = self.flags.contains(CorFlags::REQUIRED_32BIT);
+        let preferred = self.flags.contains(CorFlags::PREFERRED_32BIT);
+
+        // AnyCPU images are marked I386 too; 32BITREQUIRED, unless relaxed by
+        // 32BITPREFERRED, or native code pins them to x86
+        match Architecture::from_machine(self.machine) {
+            Architecture::X86 if required && preferred => None,
+            Architecture::X86 if required || self.is_mixed_mode() => Some(Architecture::X86),
+            Architecture::X86 => None,
+            architecture => Some(architecture),
+        }
+    }
+}
+
+impl Architecture {
+    /// The architecture of the current process.
+    pub const fn host() -> Self {
+        if cfg!(target_arch = "x86") {
+            Self::X86
+        } else if cfg!(target_arch = "aarch64") {
+            Self::Arm64
+        } else {
+            Self::X64
+        }
+    }
+
+    /// Maps an `IMAGE_FILE_HEADER.Machine` value.
+    pub const fn from_machine(machine: u16) -> Self {
+        match machine {
+            MACHINE_I386 => Self::X86,
+            MACHINE_AMD64 => Self::X64,
+            MACHINE_ARM64 => Self::Arm64,
+            _ => Self::Unknown(machine),
+        }
+    }
+}
+
+impl fmt::Display for Architecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::X86 => f.write_str("x86"),
+            Self::X64 => f.write_str("x64"),
+            Self::Arm64 => f.write_str("ARM64"),
+            Self::Unknown(machine) => write!(f, "{} {machine:#06x}", obf!("machine")),
+        }
+    }
+}
+
+/// Validates whether the given PE buffer represents a .NET executable this process
+/// can load from memory.
+///
+/// # Errors
+///
+/// Returns a [`ClrError`] variant if the file is not valid or not a .NET assembly,
+/// [`ClrError::MixedModeUnsupported`] if it holds native code, and
+/// [`ClrError::ArchitectureMismatch`] if it requires another architecture than the
+/// current process.
+pub fn validate_file(buffer: &[u8]) -> Result<()> {
+    let pe = PeImage::parse(buffer)?;
+
+    if !is_valid_executable(&pe) {
+        return Err(ClrError::InvalidExecutable);
+    }
+
+    if !is_dotnet(&pe) {
+        return Err(ClrError::NotDotNet);
+    }
+
+    let info = ClrImageInfo::read(&pe)?;
+    if info.is_mixed_mode() {
+        return Err(ClrError::MixedModeUnsupported);
+    }
+
+    let host = Architecture::host();
+    if let Some(required) = info.required_architecture()
+        && required != host
+    {
+        return Err(ClrError::ArchitectureMismatch { required, host });
+    }
+
+    Ok(())
+}
+
+/// Validates whether the given PE buffer represents a .NET class library.
+///
+/// # Errors
+///
+/// Returns a [`ClrError`] variant if the file is not a DLL or not a .NET assembly.
+pub fn validate_library(buffer: &[u8]) -> Result<()> {
+    let pe = PeImage::parse(buffer)?;
+
+    if pe.file_header.characteristics & FILE_DLL == 0 {
+        return Err(ClrError::InvalidExecutable);
+    }
+
+    if !is_dotnet(&pe) {
+        return Err(ClrError::NotDotNet);
+    }
+
+    Ok(())
+}
+
+/// Checks whether the PE headers represent a valid Windows executable.
+fn is_valid_executable(pe: &PeImage) -> bool {
+    let characteristics = pe.file_header.characteristics;
+    (characteristics & FILE_EXECUTABLE_IMAGE != 0)
+        && (characteristics & FILE_DLL == 0)
+        && (pe.optional_header.subsystem != SUBSYSTEM_NATIVE)
+}
+
+/// Checks if the PE includes a COM Descriptor directory, indicating a .NET assembly.
+///
+/// The COM descriptor is required for the CLR to recognize and load the assembly.
+fn is_dotnet(pe: &PeImage) -> bool {
+    pe.directory(COM_DESCRIPTOR).is_some()
+}
+
+/// Reads the optional header, whose fields and data directories sit at different
+/// offsets in PE32 and PE32+.
+fn parse_optional_header(optional: &[u8]) -> Result<(OptionalHeader, Vec<DataDirectory>)> {
+    let truncated = || ClrError::TruncatedHeader(obf!("optional header").to_string());
+    if optional.len() < 2 {
+        return Err(truncated());
+    }
+
+    let (kind, directories_offset) = match u16_at(optional, 0) {
+        0x010B => (PeKind::Pe32, 96),
+        0x020B => (PeKind::Pe32Plus, 112),
+        _ => {
+            return Err(ClrError::BadMagic(
+                obf!("optional header magic").to_string(),
+            ));
+        }
+    };
+
+    let fixed = optional.get(..directories_offset).ok_or_else(truncated)?;
+    let image_base = match kind {
+        PeKind::Pe32 => u32_at(fixed, 28).into(),
+        PeKind::Pe32Plus => u64::from(u32_at(fixed, 24)) | u64::from(u32_at(fixed, 28)) << 32,
+    };
+    let header = OptionalHeader {
+        kind,
+        address_of_entry_point: u32_at(fixed, 16),
+        image_base,
+        section_alignment: u32_at(fixed, 32),
+        file_alignment: u32_at(fixed, 36),
+        size_of_image: u32_at(fixed, 56),
+        size_of_headers: u32_at(fixed, 60),
+        subsystem: u16_at(fixed, 68),
+        dll_characteristics: u16_at(fixed, 70),
+    };
+
+    // NumberOfRvaAndSizes ends the fixed part; the loader ignores entries past 16
+    let count = (u32_at(fixed, directories_offset - 4) as usize).min(MAX_DIRECTORIES);
+    let directories = optional
+        .get(directories_offset..directories_offset + count * 8)
+        .ok_or_else(truncated)?
+        .as_chunks::<8>()
+        .0
+        .iter()
+        .map(|entry| DataDirectory {
+            virtual_address: u32_at(entry, 0),
+            size: u32_at(entry, 4),
+        })
+        .collect();
+
+    Ok((header, directories))
+}
+
+/// Returns the `len` bytes at `offset`, or [`ClrError::TruncatedHeader`] naming `name`.
+fn header<'a>(data: &'a [u8], offset: usize, len: usize, name: &str) -> Result<&'a [u8]> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| ClrError::TruncatedHeader(name.to_string()))
+}
+
+/// Reads a little-endian `u16` at `offset`, which the caller has bounds-checked.
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// Reads a little-endian `u32` at `offset`, which the caller has bounds-checked.
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Builds an image with a `.text` section of 0x100 raw bytes mapped at 0x2000,
+    /// and a CLI header directory inside it.
+    fn image(kind: PeKind) -> Vec<u8> {
+        let (magic, optional_size, directories) = match kind {
+            PeKind::Pe32 => (0x010Bu16, 0xE0u16, 96),
+            PeKind::Pe32Plus => (0x020B, 0xF0, 112),
+        };
+
+        let mut pe = vec![0u8; 0x300];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3C] = 0x80;
+        pe[0x80..0x84].copy_from_slice(b"PE\0\0");
+        pe[0x84..0x86].copy_from_slice(&MACHINE_AMD64.to_le_bytes());
+        pe[0x86] = 1;
+        pe[0x94..0x96].copy_from_slice(&optional_size.to_le_bytes());
+        pe[0x96..0x98].copy_from_slice(&0x0022u16.to_le_bytes());
+
+        let optional = 0x98;
+        pe[optional..optional + 2].copy_from_slice(&magic.to_le_bytes());
+        pe[optional + 16..][..4].copy_from_slice(&0x2010u32.to_le_bytes());
+        pe[optional + 24..][..8].copy_from_slice(&0x1_4000_0000u64.to_le_bytes());
+        pe[optional + 60..][..4].copy_from_slice(&0x200u32.to_le_bytes());
+        pe[optional + 68] = 3;
+        pe[optional + directories - 4] = 16;
+        let com = optional + directories + COM_DESCRIPTOR * 8;
+        pe[com..com + 4].copy_from_slice(&0x2008u32.to_le_bytes());
+        pe[com + 4] = 72;
+
+        let section = optional + usize::from(optional_size);
+        pe[section..section + 5].copy_from_slice(b".text");
+        pe[section + 8..][..4].copy_from_slice(&0x80u32.to_le_bytes());
+        pe[section + 12..][..4].copy_from_slice(&0x2000u32.to_le_bytes());
+        pe[section + 16..][..4].copy_from_slice(&0x100u32.to_le_bytes());
+        pe[section + 20..][..4].copy_from_slice(&0x200u32.to_le_bytes());
+        pe
+    }
+
+    #[test]
+    fn test_parse_pe32_and_pe32_plus() {
+        for kind in [PeKind::Pe32, PeKind::Pe32Plus] {
+            let data = image(kind);
+            let image = PeImage::parse(&data).unwrap();
+
+            assert_eq!(image.nt_offset, 0x80);
+            assert_eq!(image.file_header.machine, MACHINE_AMD64);
+            assert_eq!(image.file_header.characteristics, 0x0022);
+            assert_eq!(image.optional_header.kind, kind);
+            assert_eq!(image.optional_header.address_of_entry_point, 0x2010);
+            assert_eq!(image.optional_header.subsystem, 3);
+            assert_eq!(image.directories.len(), 16);
+            assert_eq!(image.sections.len(), 1);
+            assert_eq!(image.sections[0].name(), ".text");
+
+            // PE32 has BaseOfData where PE32+ has the high half of ImageBase
+            let image_base = match kind {
+                PeKind::Pe32 => 0x1,
+                PeKind::Pe32Plus => 0x1_4000_0000,
+            };
+            assert_eq!(image.optional_header.image_base, image_base);
+
+            let com = image.directory(COM_DESCRIPTOR).unwrap();
+            assert_eq!((com.virtual_address, com.size), (0x2008, 72));
+            assert_eq!(
+                image.directory_data(COM_DESCRIPTOR).unwrap().unwrap().len(),
+                72
+            );
+            assert!(image.directory(0).is_none());
+            assert!(image.directory(16).is_none());
+        }
+    }
+
+    #[test]
+    fn test_rva_to_offset() {
+        let data = image(PeKind::Pe32);
+        let image = PeImage::parse(&data).unwrap();
+
+        // Headers map to themselves, sections to their raw data
+        assert_eq!(image.rva_to_offset(0).unwrap(), 0);
+        assert_eq!(image.rva_to_offset(0x1FF).unwrap(), 0x1FF);
+        assert_eq!(image.rva_to_offset(0x2000).unwrap(), 0x200);
+        assert_eq!(image.rva_to_offset(0x20FF).unwrap(), 0x2FF);
+        assert_eq!(image.slice(0x2080, 0x80).unwrap(), &data[0x280..0x300]);
+        assert!(image.slice(0x2080, 0).unwrap().is_empty());
+
+        // Outside any section, or past the raw data of one
+        for rva in [0x200, 0x1FFF, 0x2100, 0xFFFF_FFFF] {
+            assert!(matches!(
+                image.rva_to_offset(rva),
+                Err(ClrError::RvaOutOfRange(r)) if r == rva
+            ));
+        }
+        assert!(matches!(
+            image.slice(0x2080, 0x81),
+            Err(ClrError::RvaOutOfRange(0x2080))
+        ));
+
+        // Raw data the section table promises but the file lacks
+        let image = PeImage::parse(&data[..0x280]).unwrap();
+        assert!(image.slice(0x2000, 0x80).is_ok());
+        assert!(image.slice(0x2000, 0x81).is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_magic() {
+        let cases = [
+            (0x00, *b"ZM\0\0"),
+            (0x80, *b"PE\0\x01"),
+            (0x98, *b"\x07\x01\0\0"),
+        ];
+
+        for (offset, bytes) in cases {
+            let mut data = image(PeKind::Pe32);
+            data[offset..offset + 4].copy_from_slice(&bytes);
+            assert!(matches!(PeImage::parse(&data), Err(ClrError::BadMagic(_))));
+        }
+    }
+
+    #[test]
+    fn test_rejects_truncated_headers() {
+        let data = image(PeKind::Pe32Plus);
+
+        // Everything up to the end of the section table is required
+        let table_end = 0x98 + 0xF0 + SECTION_HEADER_SIZE;
+        for len in 0..table_end {
+            assert!(matches!(
+                PeImage::parse(&data[..len]),
+                Err(ClrError::TruncatedHeader(_))
+            ));
+        }
+        assert!(PeImage::parse(&data[..table_end]).is_ok());
+
+        // e_lfanew past the end, and more directories than the optional header holds
+        let mut far = data.clone();
+        far[0x3C..0x40].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut short = data.clone();
+        short[0x94] = 0x80;
+        for data in [far, short] {
+            assert!(matches!(
+                PeImage::parse(&data),
+                Err(ClrError::TruncatedHeader(_))
+            ));
+        }
+    }
+
+    /// Sets the machine of [`image`] and fills in its CLI header.
+    fn assembly(machine: u16, flags: CorFlags) -> Vec<u8> {
+        let mut data = image(PeKind::Pe32);
+        data[0x84..0x86].copy_from_slice(&machine.to_le_bytes());
+
+        let header = &mut data[0x208..0x208 + 72];
+        header[..8].copy_from_slice(&[72, 0, 0, 0, 2, 0, 5, 0]);
+        header[8..12].copy_from_slice(&0x2050u32.to_le_bytes());
+        header[12..16].copy_from_slice(&0x40u32.to_le_bytes());
+        header[16..20].copy_from_slice(&flags.bits().to_le_bytes());
+        header[20..24].copy_from_slice(&0x0600_0001u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_clr_image_info() {
+        let info = ClrImageInfo::parse(&assembly(MACHINE_I386, CorFlags::ILONLY)).unwrap();
+        assert_eq!(info.machine, MACHINE_I386);
+        assert_eq!(
+            (info.major_runtime_version, info.minor_runtime_version),
+            (2, 5)
+        );
+        assert_eq!(info.metadata.virtual_address, 0x2050);
+        assert_eq!(info.metadata.size, 0x40);
+        assert_eq!(info.flags, CorFlags::ILONLY);
+        assert_eq!(info.entry_point, 0x0600_0001);
+        assert!(!info.is_mixed_mode());
+
+        // Unknown bits are kept rather than dropped
+        let flags = CorFlags::from_bits_retain(0x8000_0001);
+        let info = ClrImageInfo::parse(&assembly(MACHINE_I386, flags)).unwrap();
+        assert_eq!(info.flags.bits(), 0x8000_0001);
+
+        // Without a CLI header the image is native code
+        let mut native = image(PeKind::Pe32);
+        native[0x98 + 96 + COM_DESCRIPTOR * 8..][..8].fill(0);
+        assert!(matches!(
+            ClrImageInfo::parse(&native),
+            Err(ClrError::NotDotNet)
+        ));
+    }
+
+    #[test]
+    fn test_required_architecture() {
+        let il = CorFlags::ILONLY;
+        let cases = [
+            (MACHINE_I386, il, None),
+            (
+                MACHINE_I386,
+                il | CorFlags::REQUIRED_32BIT,
+                Some(Architecture::X86),
+            ),
+            (
+                MACHINE_I386,
+                il | CorFlags::REQUIRED_32BIT | CorFlags::PREFERRED_32BIT,
+                None,
+            ),
+            (MACHINE_I386, CorFlags::empty(), Some(Architecture::X86)),
+            (MACHINE_AMD64, il, Some(Architecture::X64)),
+            (MACHINE_ARM64, il, Some(Architecture::Arm64)),
+            (0x01C4, il, Some(Architecture::Unknown(0x01C4))),
+        ];
+
+        for (machine, flags, required) in cases {
+            let info = ClrImageInfo::parse(&assembly(machine, flags)).unwrap();
+            assert_eq!(
+                info.required_architecture(),
+                required,
+                "{machine:#x} {flags:?}"
+            );
+            assert_eq!(info.is_mixed_mode(), !flags.contains(CorFlags::ILONLY));
+        }
+
+        assert_eq!(Architecture::Unknown(0x01C4).to_string(), "machine 0x01c4");
+        assert_eq!(Architecture::from_machine(MACHINE_AMD64).to_string(), "x64");
+    }
+
+    #[test]
+    fn test_validate() {
+        let executable = assembly(MACHINE_I386, CorFlags::ILONLY);
+        assert!(validate_file(&executable).is_ok());
+        assert!(matches!(
+            validate_library(&executable),
+            Err(ClrError::InvalidExecutable)
+        ));
+
+        let mut library = executable.clone();
+        library[0x96..0x98].copy_from_slice(&(FILE_EXECUTABLE_IMAGE | FILE_DLL).to_le_bytes());
+        assert!(validate_library(&library).is_ok());
+        assert!(matches!(
+            validate_file(&library),
+            Err(ClrError::InvalidExecutable)
+        ));
+
+        let mut driver = executable.clone();
+        driver[0x98 + 68] = SUBSYSTEM_NATIVE as u8;
+        assert!(matches!(
+            validate_file(&driver),
+            Err(ClrError::InvalidExecutable)
+        ));
+
+        let mut native = executable.clone();
+        native[0x98 + 96 + COM_DESCRIPTOR * 8..][..8].fill(0);
+        assert!(matches!(validate_file(&native), Err(ClrError::NotDotNet)));
+        assert!(matches!(
+            validate_file(&assembly(MACHINE_I386, CorFlags::empty())),
+            Err(ClrError::MixedModeUnsupported)
+        ));
+    }
+
+    #[test]
+    fn test_damaged_images_fail_without_panicking() {
+        let data = image(PeKind::Pe32);
+        for index in 0..0x200 {
+            for value in [0x00, 0x7F, 0xFF] {
+                let mut damaged = data.clone();
+                damaged[index] = value;
+                if let Ok(image) = PeImage::parse(&damaged) {
+                    for rva in [0, 0x1FF, 0x2000, 0x2008, 0x20FF, u32::MAX] {
+                        let _ = image.slice(rva, 72);
+                    }
+                }
+            }
+        }
+    }
+}
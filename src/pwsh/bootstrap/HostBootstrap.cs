@@ -0,0 +1,384 @@
+using System;
+using System.Text;
+using System.Reflection;
+using System.Globalization;
+using System.Collections.Generic;
+using System.Runtime.CompilerServices;
+using System.Runtime.InteropServices;
+using System.Management.Automation;
+using System.Management.Automation.Host;
+using System.Management.Automation.Runspaces;
+public static class HostBootstrap {
+    private static bool _initialized = false;
+    private static Assembly _smaAssembly = null;
+    private static Assembly _compiledAsm = null;
+    private static string _lastError = null;
+    private static long _nextId = 1;
+    private class InstanceInfo {
+        public Runspace Runspace;
+        public CaptureHost Host;
+        public IntPtr OriginalStdOut;
+        public IntPtr PipeReadOut;
+        public IntPtr PipeWriteOut;
+    }
+    private static Dictionary<long, InstanceInfo> _instances = new Dictionary<long, InstanceInfo>();
+    [DllImport("kernel32.dll", SetLastError = true)]
+    private static extern IntPtr GetStdHandle(int nStdHandle);
+    [DllImport("kernel32.dll", SetLastError = true)]
+    private static extern bool SetStdHandle(int nStdHandle, IntPtr hHandle);
+    [DllImport("kernel32.dll", SetLastError = true)]
+    private static extern bool CreatePipe(out IntPtr hReadPipe, out IntPtr hWritePipe, ref SECURITY_ATTRIBUTES lpPipeAttributes, uint nSize);
+    [DllImport("kernel32.dll", SetLastError = true)]
+    private static extern bool ReadFile(IntPtr hFile, byte[] lpBuffer, uint nNumberOfBytesToRead, out uint lpNumberOfBytesRead, IntPtr lpOverlapped);
+    [DllImport("kernel32.dll", SetLastError = true)]
+    private static extern bool PeekNamedPipe(IntPtr hNamedPipe, IntPtr lpBuffer, uint nBufferSize, IntPtr lpBytesRead, out uint lpTotalBytesAvail, IntPtr lpBytesLeftThisMessage);
+    [DllImport("kernel32.dll", SetLastError = true)]
+    private static extern bool CloseHandle(IntPtr hObject);
+    [StructLayout(LayoutKind.Sequential)]
+    private struct SECURITY_ATTRIBUTES {
+        public int nLength;
+        public IntPtr lpSecurityDescriptor;
+        public bool bInheritHandle;
+    }
+    private const int STD_OUTPUT_HANDLE = -11;
+    public static string GetLastError() { return _lastError ?? ""; }
+    public static void SetCompiledAssembly(Assembly asm) { _compiledAsm = asm; }
+    public static void Initialize() {
+        if (_initialized) return;
+        _initialized = true;
+        foreach (var asm in AppDomain.CurrentDomain.GetAssemblies()) {
+            if (asm.GetName().Name == "System.Management.Automation") {
+                _smaAssembly = asm;
+                break;
+            }
+        }
+        AppDomain.CurrentDomain.AssemblyResolve += OnAssemblyResolve;
+    }
+    private static Assembly OnAssemblyResolve(object sender, ResolveEventArgs args) {
+        if (args.Name.Contains(".resources")) return null;
+        if (args.Name.StartsWith("System.Management.Automation,") && _smaAssembly != null) return _smaAssembly;
+        return null;
+    }
+    public static object CreateSessionState(int languageMode, bool restrictCommands, string[] commands, string[] modules, int executionPolicy, string[] functionNames, string[] functionBodies) {
+        var iss = InitialSessionState.CreateDefault();
+        if (languageMode >= 0) iss.LanguageMode = (PSLanguageMode)languageMode;
+        if (restrictCommands) {
+            var allowed = new HashSet<string>(commands ?? new string[0], StringComparer.OrdinalIgnoreCase);
+            allowed.Add("Out-String");
+            _allowlists.Add(iss, allowed);
+            iss.Applications.Clear();
+            iss.Scripts.Clear();
+            iss.Variables.Add(new SessionStateVariableEntry("PSModuleAutoLoadingPreference", "None", ""));
+        }
+        if (modules != null) iss.ImportPSModule(modules);
+        if (executionPolicy >= 0) {
+            var policy = typeof(InitialSessionState).GetProperty("ExecutionPolicy");
+            if (policy != null) policy.SetValue(iss, Enum.ToObject(policy.PropertyType, executionPolicy), null);
+        }
+        for (int i = 0; functionNames != null && i < functionNames.Length; i++) iss.Commands.Add(new SessionStateFunctionEntry(functionNames[i], functionBodies[i]));
+        return iss;
+    }
+    private static ConditionalWeakTable<InitialSessionState, HashSet<string>> _allowlists = new ConditionalWeakTable<InitialSessionState, HashSet<string>>();
+    private static void RestrictCommands(Runspace runspace, HashSet<string> allowed) {
+        foreach (var command in runspace.SessionStateProxy.InvokeCommand.GetCommands("*", CommandTypes.All, true)) {
+            if (!allowed.Contains(command.Name)) command.Visibility = SessionStateEntryVisibility.Private;
+        }
+    }
+    public static void AddSessionVariable(object state, string name, object value) {
+        ((InitialSessionState)state).Variables.Add(new SessionStateVariableEntry(name, value, ""));
+    }
+    public static long CreateRunspace(object state) {
+        try {
+            Initialize();
+            if (_smaAssembly == null) { _lastError = "SMA assembly not found"; return -1; }
+            if (_compiledAsm == null) { _lastError = "Compiled assembly not set"; return -1; }
+            var host = new CaptureHost();
+            var iss = state as InitialSessionState ?? InitialSessionState.CreateDefault();
+            var runspace = RunspaceFactory.CreateRunspace(host, iss);
+            runspace.Open();
+            HashSet<string> allowed;
+            if (_allowlists.TryGetValue(iss, out allowed)) RestrictCommands(runspace, allowed);
+            var sa = new SECURITY_ATTRIBUTES();
+            sa.nLength = Marshal.SizeOf(sa);
+            sa.bInheritHandle = true;
+            IntPtr pipeRead, pipeWrite;
+            if (!CreatePipe(out pipeRead, out pipeWrite, ref sa, 0)) {
+                runspace.Close();
+                _lastError = "CreatePipe failed";
+                return -1;
+            }
+            var id = _nextId++;
+            _instances[id] = new InstanceInfo {
+                Runspace = runspace,
+                Host = host,
+                OriginalStdOut = GetStdHandle(STD_OUTPUT_HANDLE),
+                PipeReadOut = pipeRead,
+                PipeWriteOut = pipeWrite
+            };
+            return id;
+        } catch (Exception ex) {
+            var inner = ex;
+            while (inner.InnerException != null) inner = inner.InnerException;
+            _lastError = inner.GetType().FullName + ": " + inner.Message;
+            return -1;
+        }
+    }
+    private static Dictionary<long, RunspacePool> _pools = new Dictionary<long, RunspacePool>();
+    public static long CreatePool(int size) {
+        try {
+            Initialize();
+            if (_smaAssembly == null) { _lastError = "SMA assembly not found"; return -1; }
+            var pool = RunspaceFactory.CreateRunspacePool(1, size);
+            pool.Open();
+            lock (_pools) {
+                var id = _nextId++;
+                _pools[id] = pool;
+                return id;
+            }
+        } catch (Exception ex) {
+            var inner = ex;
+            while (inner.InnerException != null) inner = inner.InnerException;
+            _lastError = inner.GetType().FullName + ": " + inner.Message;
+            return -1;
+        }
+    }
+    public static object InvokePool(long id, string script, bool format) {
+        RunspacePool pool;
+        lock (_pools) {
+            if (!_pools.TryGetValue(id, out pool)) throw new InvalidOperationException("Runspace pool is closed");
+        }
+        using (var ps = PowerShell.Create()) {
+            ps.RunspacePool = pool;
+            ps.AddScript(script);
+            if (!format) return ps.Invoke();
+            ps.AddCommand("Out-String");
+            var sb = new StringBuilder();
+            foreach (var item in ps.Invoke()) sb.Append(item);
+            return sb.ToString();
+        }
+    }
+    public static void ClosePool(long id) {
+        RunspacePool pool;
+        lock (_pools) {
+            if (!_pools.TryGetValue(id, out pool)) return;
+            _pools.Remove(id);
+        }
+        try { pool.Close(); pool.Dispose(); } catch { }
+    }
+    public static void SetHostCallbacks(long id, long token, long readLine, long prompt, long choice, long progress) {
+        InstanceInfo info;
+        if (!_instances.TryGetValue(id, out info)) return;
+        var ui = info.Host.CaptureUI;
+        ui.Token = token;
+        ui.ReadLineHandler = (ReadLineCallback)Marshal.GetDelegateForFunctionPointer(new IntPtr(readLine), typeof(ReadLineCallback));
+        ui.PromptHandler = (PromptCallback)Marshal.GetDelegateForFunctionPointer(new IntPtr(prompt), typeof(PromptCallback));
+        ui.ChoiceHandler = (ChoiceCallback)Marshal.GetDelegateForFunctionPointer(new IntPtr(choice), typeof(ChoiceCallback));
+        ui.ProgressHandler = (ProgressCallback)Marshal.GetDelegateForFunctionPointer(new IntPtr(progress), typeof(ProgressCallback));
+    }
+    public static object GetRunspace(long id) {
+        InstanceInfo info;
+        return _instances.TryGetValue(id, out info) ? info.Runspace : null;
+    }
+    public static void CloseRunspace(long id) {
+        InstanceInfo info;
+        if (_instances.TryGetValue(id, out info)) {
+            try {
+                if (info.Runspace != null) {
+                    if (info.Runspace.RunspaceStateInfo.State == RunspaceState.Opened) info.Runspace.Close();
+                    info.Runspace.Dispose();
+                }
+                if (info.PipeReadOut != IntPtr.Zero) CloseHandle(info.PipeReadOut);
+                if (info.PipeWriteOut != IntPtr.Zero) CloseHandle(info.PipeWriteOut);
+                info.Runspace = null;
+                info.Host = null;
+            } catch { }
+            _instances.Remove(id);
+            GC.Collect();
+            GC.WaitForPendingFinalizers();
+            GC.Collect();
+        }
+    }
+    public static void BeginCapture(long id) {
+        InstanceInfo info;
+        if (_instances.TryGetValue(id, out info) && info.PipeWriteOut != IntPtr.Zero) SetStdHandle(STD_OUTPUT_HANDLE, info.PipeWriteOut);
+    }
+    public static void EndCapture(long id) {
+        InstanceInfo info;
+        if (_instances.TryGetValue(id, out info) && info.OriginalStdOut != IntPtr.Zero) SetStdHandle(STD_OUTPUT_HANDLE, info.OriginalStdOut);
+    }
+    private static string ReadFromPipe(IntPtr pipeRead) {
+        var sb = new StringBuilder();
+        uint available;
+        while (PeekNamedPipe(pipeRead, IntPtr.Zero, 0, IntPtr.Zero, out available, IntPtr.Zero) && available > 0) {
+            var buffer = new byte[Math.Min(available, 4096)];
+            uint read;
+            if (ReadFile(pipeRead, buffer, (uint)buffer.Length, out read, IntPtr.Zero) && read > 0) sb.Append(Encoding.UTF8.GetString(buffer, 0, (int)read));
+            else break;
+        }
+        return sb.ToString();
+    }
+    public static void ClearHostOutput(long id) {
+        InstanceInfo info;
+        if (_instances.TryGetValue(id, out info)) { info.Host.ClearOutput(); ReadFromPipe(info.PipeReadOut); }
+    }
+    public static string GetHostOutput(long id) {
+        InstanceInfo info;
+        if (!_instances.TryGetValue(id, out info)) return "";
+        var sb = new StringBuilder();
+        sb.Append(ReadFromPipe(info.PipeReadOut));
+        sb.Append(info.Host.GetOutput());
+        return sb.ToString();
+    }
+    public static string GetHostInformation(long id) {
+        InstanceInfo info;
+        if (!_instances.TryGetValue(id, out info)) return "";
+        return ReadFromPipe(info.PipeReadOut) + info.Host.CaptureUI.GetInformation();
+    }
+    private static List<string> GetStream(long id, int stream) {
+        InstanceInfo info;
+        if (!_instances.TryGetValue(id, out info)) return null;
+        switch (stream) {
+            case 0: return info.Host.CaptureUI.Warnings;
+            case 1: return info.Host.CaptureUI.Verbose;
+            case 2: return info.Host.CaptureUI.Debug;
+            default: return null;
+        }
+    }
+    public static int GetHostStreamCount(long id, int stream) {
+        var list = GetStream(id, stream);
+        return list == null ? 0 : list.Count;
+    }
+    public static string GetHostStreamItem(long id, int stream, int index) {
+        var list = GetStream(id, stream);
+        return list != null && index < list.Count ? list[index] : "";
+    }
+    public static string GetErrorField(object value, int field) {
+        var pso = value as PSObject;
+        if (pso != null) value = pso.BaseObject;
+        var record = value as ErrorRecord;
+        var container = value as IContainsErrorRecord;
+        if (record == null && container != null) record = container.ErrorRecord;
+        if (record == null) {
+            var ex = value as Exception;
+            switch (field) {
+                case 0: return ex != null ? ex.Message : (value == null ? "" : value.ToString());
+                case 1: return "NotSpecified";
+                case 2: return ex != null ? ex.GetType().Name : "";
+                default: return "";
+            }
+        }
+        switch (field) {
+            case 0: return record.ToString();
+            case 1: return record.CategoryInfo.Category.ToString();
+            case 2: return record.FullyQualifiedErrorId ?? "";
+            default:
+                var stack = typeof(ErrorRecord).GetProperty("ScriptStackTrace");
+                return stack == null ? "" : (stack.GetValue(record, null) as string ?? "");
+        }
+    }
+}
+public class CaptureRawUI : PSHostRawUserInterface {
+    public override ConsoleColor BackgroundColor { get { return ConsoleColor.Black; } set { } }
+    public override ConsoleColor ForegroundColor { get { return ConsoleColor.White; } set { } }
+    public override Size BufferSize { get { return new Size(120, 50); } set { } }
+    public override Coordinates CursorPosition { get { return new Coordinates(0, 0); } set { } }
+    public override int CursorSize { get { return 1; } set { } }
+    public override Size MaxPhysicalWindowSize { get { return new Size(120, 50); } }
+    public override Size MaxWindowSize { get { return new Size(120, 50); } }
+    public override Coordinates WindowPosition { get { return new Coordinates(0, 0); } set { } }
+    public override Size WindowSize { get { return new Size(120, 50); } set { } }
+    public override string WindowTitle { get { return ""; } set { } }
+    public override bool KeyAvailable { get { return false; } }
+    public override void FlushInputBuffer() { }
+    public override BufferCell[,] GetBufferContents(Rectangle r) { return null; }
+    public override KeyInfo ReadKey(ReadKeyOptions o) { return new KeyInfo(); }
+    public override void ScrollBufferContents(Rectangle s, Coordinates d, Rectangle c, BufferCell f) { }
+    public override void SetBufferContents(Rectangle r, BufferCell f) { }
+    public override void SetBufferContents(Coordinates o, BufferCell[,] c) { }
+}
+[UnmanagedFunctionPointer(CallingConvention.Winapi)]
+[return: MarshalAs(UnmanagedType.BStr)]
+public delegate string ReadLineCallback(long token);
+[UnmanagedFunctionPointer(CallingConvention.Winapi)]
+[return: MarshalAs(UnmanagedType.BStr)]
+public delegate string PromptCallback(long token, [MarshalAs(UnmanagedType.LPWStr)] string caption, [MarshalAs(UnmanagedType.LPWStr)] string message, [MarshalAs(UnmanagedType.LPWStr)] string fields);
+[UnmanagedFunctionPointer(CallingConvention.Winapi)]
+public delegate int ChoiceCallback(long token, [MarshalAs(UnmanagedType.LPWStr)] string caption, [MarshalAs(UnmanagedType.LPWStr)] string message, [MarshalAs(UnmanagedType.LPWStr)] string choices, int defaultChoice);
+[UnmanagedFunctionPointer(CallingConvention.Winapi)]
+public delegate void ProgressCallback(long token, int activityId, int parentActivityId, [MarshalAs(UnmanagedType.LPWStr)] string activity, [MarshalAs(UnmanagedType.LPWStr)] string status, [MarshalAs(UnmanagedType.LPWStr)] string currentOperation, int percentComplete, int secondsRemaining, [MarshalAs(UnmanagedType.Bool)] bool completed);
+public class CaptureUI : PSHostUserInterface {
+    private StringBuilder _out;
+    private StringBuilder _info;
+    private CaptureRawUI _rawUI;
+    public List<string> Warnings = new List<string>();
+    public List<string> Verbose = new List<string>();
+    public List<string> Debug = new List<string>();
+    public long Token;
+    public ReadLineCallback ReadLineHandler;
+    public PromptCallback PromptHandler;
+    public ChoiceCallback ChoiceHandler;
+    public ProgressCallback ProgressHandler;
+    public CaptureUI(StringBuilder sb) { _out = sb; _info = new StringBuilder(); _rawUI = new CaptureRawUI(); }
+    public override PSHostRawUserInterface RawUI { get { return _rawUI; } }
+    public override void Write(string value) { _out.Append(value); _info.Append(value); }
+    public override void Write(ConsoleColor f, ConsoleColor b, string value) { _out.Append(value); _info.Append(value); }
+    public override void WriteLine(string value) { _out.AppendLine(value); _info.AppendLine(value); }
+    public override void WriteLine(ConsoleColor f, ConsoleColor b, string value) { _out.AppendLine(value); _info.AppendLine(value); }
+    public override void WriteDebugLine(string m) { _out.AppendLine("DEBUG: " + m); Debug.Add(m); }
+    public override void WriteErrorLine(string m) { _out.AppendLine("ERROR: " + m); }
+    public override void WriteVerboseLine(string m) { _out.AppendLine("VERBOSE: " + m); Verbose.Add(m); }
+    public override void WriteWarningLine(string m) { _out.AppendLine("WARNING: " + m); Warnings.Add(m); }
+    public string GetInformation() { return _info.ToString(); }
+    public void Clear() { _info.Length = 0; Warnings.Clear(); Verbose.Clear(); Debug.Clear(); }
+    public override void WriteProgress(long id, ProgressRecord r) {
+        if (ProgressHandler != null) ProgressHandler(Token, r.ActivityId, r.ParentActivityId, r.Activity ?? "", r.StatusDescription ?? "", r.CurrentOperation ?? "", r.PercentComplete, r.SecondsRemaining, r.RecordType == ProgressRecordType.Completed);
+    }
+    public override string ReadLine() { return ReadLineHandler != null ? ReadLineHandler(Token) ?? "" : ""; }
+    public override System.Security.SecureString ReadLineAsSecureString() { return new System.Security.SecureString(); }
+    public override System.Collections.Generic.Dictionary<string, PSObject> Prompt(string c, string m, System.Collections.ObjectModel.Collection<FieldDescription> d) {
+        var result = new System.Collections.Generic.Dictionary<string, PSObject>();
+        if (PromptHandler == null) return result;
+        var sb = new StringBuilder();
+        foreach (var field in d) {
+            if (sb.Length > 0) sb.Append('\x1e');
+            sb.Append(field.Name).Append('\x1f').Append(field.Label).Append('\x1f').Append(field.HelpMessage);
+        }
+        var answer = PromptHandler(Token, c ?? "", m ?? "", sb.ToString());
+        if (answer == null) return result;
+        var values = answer.Split('\x1e');
+        for (int i = 0; i < d.Count && i < values.Length; i++) result[d[i].Name] = new PSObject(values[i]);
+        return result;
+    }
+    public override int PromptForChoice(string c, string m, System.Collections.ObjectModel.Collection<ChoiceDescription> ch, int df) {
+        if (ChoiceHandler == null) return df;
+        var sb = new StringBuilder();
+        foreach (var choice in ch) {
+            if (sb.Length > 0) sb.Append('\x1e');
+            sb.Append(choice.Label).Append('\x1f').Append(choice.HelpMessage);
+        }
+        var selected = ChoiceHandler(Token, c ?? "", m ?? "", sb.ToString(), df);
+        return selected >= 0 && selected < ch.Count ? selected : df;
+    }
+    public override PSCredential PromptForCredential(string c, string m, string u, string t) { return null; }
+    public override PSCredential PromptForCredential(string c, string m, string u, string t, PSCredentialTypes at, PSCredentialUIOptions o) { return null; }
+}
+public class CaptureHost : PSHost {
+    private Guid _id;
+    private StringBuilder _output;
+    private CaptureUI _ui;
+    public CaptureHost() { _id = Guid.NewGuid(); _output = new StringBuilder(); _ui = new CaptureUI(_output); }
+    public override string Name { get { return "CaptureHost"; } }
+    public override Version Version { get { return new Version(1, 0); } }
+    public override Guid InstanceId { get { return _id; } }
+    public override CultureInfo CurrentCulture { get { return CultureInfo.CurrentCulture; } }
+    public override CultureInfo CurrentUICulture { get { return CultureInfo.CurrentUICulture; } }
+    public override PSHostUserInterface UI { get { return _ui; } }
+    public CaptureUI CaptureUI { get { return _ui; } }
+    public override void SetShouldExit(int code) { }
+    public override void EnterNestedPrompt() { }
+    public override void ExitNestedPrompt() { }
+    public override void NotifyBeginApplication() { }
+    public override void NotifyEndApplication() { }
+    public string GetOutput() { return _output.ToString(); }
+    public void ClearOutput() { _output.Length = 0; _ui.Clear(); }
+}
@@ -0,0 +1,15 @@
+using System;
+using System.Reflection;
+public static class RustClrResolver {
+    public static void Install() {
+        AppDomain.CurrentDomain.AssemblyResolve += delegate(object sender, ResolveEventArgs args) {
+            string name = new AssemblyName(args.Name).Name;
+            Assembly match = null;
+            foreach (Assembly assembly in AppDomain.CurrentDomain.GetAssemblies()) {
+                if (assembly.FullName == args.Name) return assembly;
+                if (match == null && assembly.GetName().Name == name) match = assembly;
+            }
+            return match;
+        };
+    }
+}
@@ -0,0 +1,595 @@
+//! Parser for CLIXML, the XML format produced by `PSSerializer.Serialize` and `Export-Clixml`.
+//!
+//! The parser is self-contained and only needs `alloc`, so serialized output can be
+//! captured once and read anywhere.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use const_encrypt::obf;
+
+use super::PsValue;
+use crate::error::{ClrError, Result};
+
+/// Parses a CLIXML document into the objects it contains.
+///
+/// Every child of the `<Objs>` root becomes one [`PsValue`]. `<Ref>` elements are
+/// resolved to a copy of the object they point to.
+///
+/// # Example
+///
+/// ```
+/// use rustclr::clixml;
+///
+/// let xml = r#"<Objs Version="1.1.0.1" xmlns="http://schemas.microsoft.com/powershell/2004/04"><S>hello</S><I32>42</I32></Objs>"#;
+/// let values = clixml::parse(xml)?;
+/// assert_eq!(values[0].as_str(), Some("hello"));
+/// assert_eq!(values[1].as_i64(), Some(42));
+/// ```
+pub fn parse(xml: &str) -> Result<Vec<PsValue>> {
+    let mut parser = Parser {
+        reader: Reader::new(xml),
+        refs: BTreeMap::new(),
+        pending: BTreeSet::new(),
+        type_names: BTreeMap::new(),
+    };
+
+    let root = parser
+        .next_start()?
+        .ok_or_else(|| error("missing <Objs> root"))?;
+    if root.name != "Objs" {
+        return Err(error(&format!("expected <Objs>, found <{}>", root.name)));
+    }
+
+    parser.children(&root, |parser, child| parser.value(child))
+}
+
+/// Start tag of an element.
+struct Start<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, String)>,
+
+    /// `true` for `<Name />`, which has no children and no end tag.
+    empty: bool,
+}
+
+impl Start<'_> {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Events produced by [`Reader`].
+enum Event<'a> {
+    Start(Start<'a>),
+    End(&'a str),
+    Text(String),
+    Eof,
+}
+
+/// Minimal pull parser covering the XML subset CLIXML uses.
+struct Reader<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a str) -> Self {
+        // Skip a byte order mark left over from reading a file
+        Self {
+            input: input.strip_prefix('\u{feff}').unwrap_or(input),
+            pos: 0,
+        }
+    }
+
+    fn next(&mut self) -> Result<Event<'a>> {
+        loop {
+            let rest = &self.input[self.pos..];
+            if rest.is_empty() {
+                return Ok(Event::Eof);
+            }
+
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.pos += end;
+                return Ok(Event::Text(decode_entities(&rest[..end])?));
+            }
+
+            // Declarations, processing instructions and comments carry no data
+            let skip = if rest.starts_with("<?") {
+                Some("?>")
+            } else if rest.starts_with("<!--") {
+                Some("-->")
+            } else if rest.starts_with("<!") {
+                Some(">")
+            } else {
+                None
+            };
+            if let Some(terminator) = skip {
+                let end = rest
+                    .find(terminator)
+                    .ok_or_else(|| error("unterminated markup"))?;
+                self.pos += end + terminator.len();
+                continue;
+            }
+
+            let end = rest.find('>').ok_or_else(|| error("unterminated tag"))?;
+            let tag = &rest[1..end];
+            self.pos += end + 1;
+
+            if let Some(name) = tag.strip_prefix('/') {
+                return Ok(Event::End(name.trim()));
+            }
+
+            return parse_start(tag).map(Event::Start);
+        }
+    }
+}
+
+/// Parses the inside of a start tag, e.g. `S N="Name"`.
+fn parse_start(tag: &str) -> Result<Start<'_>> {
+    let (tag, empty) = match tag.strip_suffix('/') {
+        Some(tag) => (tag, true),
+        None => (tag, false),
+    };
+
+    let name_end = tag
+        .find(|c: char| c.is_ascii_whitespace())
+        .unwrap_or(tag.len());
+    let name = &tag[..name_end];
+    if name.is_empty() {
+        return Err(error("empty tag name"));
+    }
+
+    let mut attributes = Vec::new();
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let eq = rest
+            .find('=')
+            .ok_or_else(|| error("attribute without value"))?;
+        let key = rest[..eq].trim_end();
+        let value = rest[eq + 1..].trim_start();
+
+        let quote = value
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| error("unquoted attribute value"))?;
+        let close = value[1..]
+            .find(quote)
+            .ok_or_else(|| error("unterminated attribute value"))?;
+
+        attributes.push((key, decode_entities(&value[1..close + 1])?));
+        rest = value[close + 2..].trim_start();
+    }
+
+    Ok(Start {
+        name,
+        attributes,
+        empty,
+    })
+}
+
+/// Builds [`PsValue`]s from reader events.
+struct Parser<'a> {
+    reader: Reader<'a>,
+
+    /// Completed objects by `RefId`, for `<Ref>`.
+    refs: BTreeMap<String, PsValue>,
+
+    /// `RefId`s of objects still being parsed; references to them are cycles.
+    pending: BTreeSet<String>,
+
+    /// Type name lists by `RefId`, for `<TNRef>`.
+    type_names: BTreeMap<String, Vec<String>>,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns the next start tag, skipping whitespace, or `None` at the end of input.
+    fn next_start(&mut self) -> Result<Option<Start<'a>>> {
+        loop {
+            match self.reader.next()? {
+                Event::Start(start) => return Ok(Some(start)),
+                Event::Text(text) if text.trim().is_empty() => continue,
+                Event::Text(_) => return Err(error("unexpected text outside an element")),
+                Event::End(name) => return Err(error(&format!("unexpected </{name}>"))),
+                Event::Eof => return Ok(None),
+            }
+        }
+    }
+
+    /// Runs `f` for every child element of `parent` and collects the results.
+    fn children<T>(
+        &mut self,
+        parent: &Start<'_>,
+        mut f: impl FnMut(&mut Self, Start<'a>) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        if parent.empty {
+            return Ok(items);
+        }
+
+        loop {
+            match self.reader.next()? {
+                Event::Start(child) => items.push(f(self, child)?),
+                Event::End(name) if name == parent.name => return Ok(items),
+                Event::End(name) => return Err(error(&format!("mismatched </{name}>"))),
+                Event::Text(text) if text.trim().is_empty() => {}
+                Event::Text(_) => {
+                    return Err(error(&format!("unexpected text in <{}>", parent.name)));
+                }
+                Event::Eof => return Err(error(&format!("unterminated <{}>", parent.name))),
+            }
+        }
+    }
+
+    /// Reads the text content of `start`, which must not contain child elements.
+    fn text(&mut self, start: &Start<'_>) -> Result<String> {
+        let mut text = String::new();
+        if start.empty {
+            return Ok(text);
+        }
+
+        loop {
+            match self.reader.next()? {
+                Event::Text(chunk) => text.push_str(&chunk),
+                Event::End(name) if name == start.name => return Ok(decode_escapes(&text)),
+                Event::Start(child) => {
+                    return Err(error(&format!(
+                        "unexpected <{}> in <{}>",
+                        child.name, start.name
+                    )));
+                }
+                Event::End(name) => return Err(error(&format!("mismatched </{name}>"))),
+                Event::Eof => return Err(error(&format!("unterminated <{}>", start.name))),
+            }
+        }
+    }
+
+    /// Parses a value element.
+    fn value(&mut self, start: Start<'a>) -> Result<PsValue> {
+        let value = match start.name {
+            "Nil" => {
+                self.text(&start)?;
+                PsValue::Null
+            }
+            "B" => match self.text(&start)?.trim() {
+                "true" => PsValue::Bool(true),
+                "false" => PsValue::Bool(false),
+                other => return Err(error(&format!("invalid boolean '{other}'"))),
+            },
+            "SB" | "I16" | "I32" | "I64" => PsValue::Int(self.number(&start)?),
+            "By" | "U16" | "U32" | "U64" => PsValue::UInt(self.number(&start)?),
+            "Sg" | "Db" | "D" => PsValue::Float(self.number(&start)?),
+            "C" => {
+                let code: u32 = self.number(&start)?;
+                let c = char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER);
+                PsValue::String(c.to_string())
+            }
+            "DT" => PsValue::DateTime(self.text(&start)?),
+            "S" | "G" | "URI" | "Version" | "TS" | "XD" | "SBK" | "BA" | "SS" => {
+                PsValue::String(self.text(&start)?)
+            }
+            "Obj" => self.object(start)?,
+            "Ref" => {
+                let id = start
+                    .attribute("RefId")
+                    .ok_or_else(|| error("<Ref> without RefId"))?
+                    .to_string();
+                self.text(&start)?;
+
+                match self.refs.get(&id) {
+                    Some(value) => value.clone(),
+                    // A reference back into an enclosing object would be infinite
+                    None if self.pending.contains(&id) => PsValue::Null,
+                    None => return Err(error(&format!("unknown RefId '{id}'"))),
+                }
+            }
+            other => return Err(error(&format!("unsupported element <{other}>"))),
+        };
+
+        Ok(value)
+    }
+
+    /// Parses the text content of `start` as a number.
+    fn number<T: core::str::FromStr>(&mut self, start: &Start<'_>) -> Result<T> {
+        let text = self.text(start)?;
+        text.trim()
+            .parse()
+            .map_err(|_| error(&format!("invalid number '{text}' in <{}>", start.name)))
+    }
+
+    /// Parses an `<Obj>` element.
+    fn object(&mut self, start: Start<'a>) -> Result<PsValue> {
+        let ref_id = start.attribute("RefId").map(ToString::to_string);
+        if let Some(id) = &ref_id {
+            self.pending.insert(id.clone());
+        }
+
+        let mut type_names = Vec::new();
+        let mut to_string = None;
+        let mut properties = Vec::new();
+        let mut collection = None;
+        let mut primitive = None;
+
+        self.children(&start, |parser, child| {
+            match child.name {
+                "TN" => {
+                    let names = parser.children(&child, |parser, t| parser.text(&t))?;
+                    if let Some(id) = child.attribute("RefId") {
+                        parser.type_names.insert(id.to_string(), names.clone());
+                    }
+                    type_names = names;
+                }
+                "TNRef" => {
+                    let id = child
+                        .attribute("RefId")
+                        .ok_or_else(|| error("<TNRef> without RefId"))?;
+                    type_names = parser
+                        .type_names
+                        .get(id)
+                        .cloned()
+                        .ok_or_else(|| error(&format!("unknown type RefId '{id}'")))?;
+                    parser.text(&child)?;
+                }
+                "ToString" => to_string = Some(parser.text(&child)?),
+                "Props" | "MS" => {
+                    let members = parser.children(&child, |parser, member| {
+                        let name = member
+                            .attribute("N")
+                            .map(decode_escapes)
+                            .unwrap_or_default();
+                        Ok((name, parser.value(member)?))
+                    })?;
+                    properties.extend(members);
+                }
+                "LST" | "IE" | "STK" | "QUE" => {
+                    let items = parser.children(&child, |parser, item| parser.value(item))?;
+                    collection = Some(PsValue::Array(items));
+                }
+                "DCT" => {
+                    let entries = parser.children(&child, |parser, entry| parser.entry(entry))?;
+                    collection = Some(PsValue::Hashtable(entries));
+                }
+                _ => primitive = Some(parser.value(child)?),
+            }
+
+            Ok(())
+        })?;
+
+        // Collections win over their adapter properties; enums and other adapted
+        // primitives become the primitive unless they carry extra members
+        let value = match (collection, primitive, to_string) {
+            (Some(collection), ..) => collection,
+            (None, _, _) if !properties.is_empty() => PsValue::Object {
+                type_names,
+                properties,
+            },
+            (None, Some(primitive), _) => primitive,
+            (None, None, Some(to_string)) => PsValue::String(to_string),
+            (None, None, None) => PsValue::Object {
+                type_names,
+                properties,
+            },
+        };
+
+        if let Some(id) = ref_id {
+            self.pending.remove(&id);
+            self.refs.insert(id, value.clone());
+        }
+
+        Ok(value)
+    }
+
+    /// Parses a dictionary `<En>` entry holding a `Key` and a `Value` member.
+    fn entry(&mut self, start: Start<'a>) -> Result<(PsValue, PsValue)> {
+        if start.name != "En" {
+            return Err(error(&format!("unexpected <{}> in <DCT>", start.name)));
+        }
+
+        let mut key = None;
+        let mut value = None;
+        self.children(&start, |parser, member| {
+            match member.attribute("N") {
+                Some("Key") => key = Some(parser.value(member)?),
+                Some("Value") => value = Some(parser.value(member)?),
+                _ => return Err(error("dictionary entry member without Key/Value name")),
+            }
+            Ok(())
+        })?;
+
+        Ok((
+            key.ok_or_else(|| error("dictionary entry without key"))?,
+            value.unwrap_or(PsValue::Null),
+        ))
+    }
+}
+
+/// Replaces the predefined XML entities and character references.
+fn decode_entities(text: &str) -> Result<String> {
+    if !text.contains('&') {
+        return Ok(text.to_string());
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        let semi = rest[amp..]
+            .find(';')
+            .ok_or_else(|| error("unterminated entity"))?;
+        let entity = &rest[amp + 1..amp + semi];
+
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32)
+                    .ok_or_else(|| error(&format!("unknown entity '&{entity};'")))?
+            }
+        };
+
+        decoded.push(c);
+        rest = &rest[amp + semi + 1..];
+    }
+    decoded.push_str(rest);
+
+    Ok(decoded)
+}
+
+/// Decodes PowerShell's `_xHHHH_` escapes, used for characters XML cannot carry.
+///
+/// Escapes are UTF-16 code units, so surrogate pairs span two consecutive escapes.
+fn decode_escapes(text: &str) -> String {
+    if !text.contains("_x") {
+        return text.to_string();
+    }
+
+    let mut units = Vec::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        let escape = rest
+            .strip_prefix("_x")
+            .filter(|hex| hex.len() >= 5 && hex.as_bytes()[4] == b'_')
+            .and_then(|hex| u16::from_str_radix(&hex[..4], 16).ok());
+
+        match escape {
+            Some(unit) => {
+                units.push(unit);
+                rest = &rest[7..];
+            }
+            None => {
+                let c = rest.chars().next().unwrap_or_default();
+                let mut buf = [0; 2];
+                units.extend_from_slice(c.encode_utf16(&mut buf));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    String::from_utf16_lossy(&units)
+}
+
+fn error(message: &str) -> ClrError {
+    ClrError::InvalidClixml(format!("{}: {message}", obf!("CLIXML")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const CUSTOM_OBJECT: &str = include_str!("../../files/clixml/custom_object.xml");
+    const SERVICES: &str = include_str!("../../files/clixml/services.xml");
+
+    #[test]
+    fn test_parse_custom_object() -> Result<()> {
+        let values = parse(CUSTOM_OBJECT)?;
+        assert_eq!(values.len(), 1);
+
+        let object = &values[0];
+        let PsValue::Object { type_names, .. } = object else {
+            panic!("expected an object");
+        };
+        assert_eq!(type_names[0], "System.Management.Automation.PSCustomObject");
+
+        assert_eq!(
+            object.get("Name").and_then(PsValue::as_str),
+            Some("rust\nclr <1>")
+        );
+        assert_eq!(object.get("Count").and_then(PsValue::as_i64), Some(3));
+        assert_eq!(object.get("Ratio").and_then(PsValue::as_f64), Some(0.5));
+        assert_eq!(object.get("Missing"), Some(&PsValue::Null));
+        assert_eq!(
+            object.get("When"),
+            Some(&PsValue::DateTime("2024-05-01T12:00:00+02:00".to_string()))
+        );
+        assert_eq!(
+            object.get("Tags"),
+            Some(&PsValue::Array(vec![
+                PsValue::String("a".to_string()),
+                PsValue::String("b".to_string()),
+            ]))
+        );
+        assert_eq!(
+            object.get("Meta").and_then(|meta| meta.get("enabled")),
+            Some(&PsValue::Bool(true))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_type_refs_and_object_refs() -> Result<()> {
+        let values = parse(SERVICES)?;
+        assert_eq!(values.len(), 3);
+
+        let PsValue::Object { type_names, .. } = &values[1] else {
+            panic!("expected an object");
+        };
+        assert_eq!(type_names[0], "System.ServiceProcess.ServiceController");
+
+        // Enums are read as their underlying value, Props and MS are merged
+        assert_eq!(values[0].get("Status").and_then(PsValue::as_i64), Some(4));
+        assert_eq!(values[1].get("Status").and_then(PsValue::as_i64), Some(1));
+        assert_eq!(
+            values[0].get("DisplayName").and_then(PsValue::as_str),
+            Some("DHCP Client")
+        );
+
+        // <Ref> repeats the referenced object
+        assert_eq!(values[2], values[0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_escapes() {
+        assert_eq!(decode_escapes("a_x000D__x000A_b"), "a\r\nb");
+        assert_eq!(decode_escapes("_xD83D__xDE00_"), "\u{1F600}");
+        assert_eq!(decode_escapes("snake_case_x"), "snake_case_x");
+    }
+
+    #[test]
+    fn test_parse_primitives() -> Result<()> {
+        let xml = "<?xml version=\"1.0\"?><Objs><B>false</B><U64>18446744073709551615</U64>\
+                   <C>65</C><Db>-INF</Db><S>&lt;&amp;&#x41;&gt;</S><S /></Objs>";
+        let values = parse(xml)?;
+
+        assert_eq!(values[0], PsValue::Bool(false));
+        assert_eq!(values[1], PsValue::UInt(u64::MAX));
+        assert_eq!(values[2].as_str(), Some("A"));
+        assert_eq!(values[3].as_f64(), Some(f64::NEG_INFINITY));
+        assert_eq!(values[4].as_str(), Some("<&A>"));
+        assert_eq!(values[5].as_str(), Some(""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_documents() {
+        assert!(parse("").is_err());
+        assert!(parse("<Root />").is_err());
+        assert!(parse("<Objs><I32>x</I32></Objs>").is_err());
+        assert!(parse("<Objs><Ref RefId=\"9\" /></Objs>").is_err());
+        assert!(parse("<Objs><Unknown /></Objs>").is_err());
+        assert!(parse("<Objs><S>unterminated</Objs>").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! Builder for PowerShell commands with object-valued parameters.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use const_encrypt::obf;
+use windows::Win32::System::Variant::VARIANT;
+use windows::core::BSTR;
+
+use super::{CompiledEnv, Pipeline, PowerShell, PsValue};
+use crate::Invocation;
+use crate::error::Result;
+use crate::variant::create_safe_args;
+
+/// A single command with parameters, created by [`PowerShell::command`].
+///
+/// The command is added through `Pipeline.Commands.Add(Command)` and every value is
+/// passed as a .NET object via `Command.Parameters.Add`, so nothing is ever
+/// interpolated into script text and untrusted values cannot inject code.
+pub struct PsCommand<'a> {
+    shell: &'a PowerShell,
+
+    /// Name of the cmdlet, function or application to run.
+    name: String,
+
+    /// Parameters in the order they were added.
+    parameters: Vec<Parameter>,
+}
+
+/// A parameter of a [`PsCommand`].
+enum Parameter {
+    /// `-Name value`.
+    Named(String, VARIANT),
+
+    /// A value bound by position.
+    Positional(VARIANT),
+}
+
+impl<'a> PsCommand<'a> {
+    pub(super) fn new(shell: &'a PowerShell, name: &str) -> Self {
+        Self {
+            shell,
+            name: name.to_string(),
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Adds the named parameter `-name value`.
+    pub fn param<V: Into<VARIANT>>(mut self, name: &str, value: V) -> Self {
+        self.parameters
+            .push(Parameter::Named(name.to_string(), value.into()));
+        self
+    }
+
+    /// Adds the switch parameter `-name`.
+    pub fn switch(self, name: &str) -> Self {
+        self.param(name, true)
+    }
+
+    /// Adds a positional argument.
+    pub fn arg<V: Into<VARIANT>>(mut self, value: V) -> Self {
+        self.parameters.push(Parameter::Positional(value.into()));
+        self
+    }
+
+    /// Runs the command and returns its output objects as [`PsValue`] trees.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::ManagedException`] when the command fails with a terminating
+    /// error, e.g. when it does not exist or a parameter cannot be bound.
+    ///
+    /// [`ClrError::ManagedException`]: crate::error::ClrError::ManagedException
+    pub fn invoke(&self) -> Result<Vec<PsValue>> {
+        let guard = self.shell.env.lock();
+        let env = &*guard;
+        let mscorlib = env
+            .clr
+            .app_domain
+            .get_assembly(&obf!("mscorlib").as_str())?;
+
+        let pipeline = Pipeline::empty(env, self.shell.instance_id)?;
+        self.add_to(env, &pipeline)?;
+        pipeline.values(&mscorlib)
+    }
+
+    /// Builds the `Command` object and appends it to `pipeline`.
+    fn add_to(&self, env: &CompiledEnv, pipeline: &Pipeline) -> Result<()> {
+        let command_type = env
+            .automation
+            .resolve_type(&obf!("System.Management.Automation.Runspaces.Command").as_str())?;
+        let command = command_type.construct(vec![BSTR::from(self.name.as_str()).into()])?;
+
+        let parameters = command_type.invoke(
+            &obf!("get_Parameters").as_str(),
+            Some(command.clone()),
+            None,
+            Invocation::Instance,
+        )?;
+        let parameter_collection = env.automation.resolve_type(
+            &obf!("System.Management.Automation.Runspaces.CommandParameterCollection").as_str(),
+        )?;
+        let add_parameter = parameter_collection
+            .method_signature(&obf!("Void Add(System.String, System.Object)").as_str())?;
+
+        for parameter in &self.parameters {
+            // A null name binds the value by position
+            let (name, value) = match parameter {
+                Parameter::Named(name, value) => (BSTR::from(name.as_str()).into(), value),
+                Parameter::Positional(value) => (VARIANT::default(), value),
+            };
+
+            let args = create_safe_args(vec![name, value.clone()])?;
+            add_parameter.invoke(Some(parameters.clone()), Some(&args))?;
+        }
+
+        let command_collection = env.automation.resolve_type(
+            &obf!("System.Management.Automation.Runspaces.CommandCollection").as_str(),
+        )?;
+        let add_command = command_collection.method_signature(
+            &obf!("Void Add(System.Management.Automation.Runspaces.Command)").as_str(),
+        )?;
+        let args = create_safe_args(vec![command])?;
+        add_command.invoke(Some(pipeline.commands()?), Some(&args))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+//! Restrictions applied to a runspace's `InitialSessionState`.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use const_encrypt::obf;
+use windows::Win32::System::Variant::VARIANT;
+
+use super::CompiledEnv;
+use crate::Invocation;
+use crate::error::Result;
+use crate::variant::create_string_array_variant;
+
+/// `PSLanguageMode` of a runspace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LanguageMode {
+    /// Every language element is allowed.
+    FullLanguage,
+
+    /// Scripts run, but only core .NET types may be used and `Add-Type` is blocked.
+    ConstrainedLanguage,
+
+    /// Only literals, variables and a few operators; no script blocks or `& { }`.
+    RestrictedLanguage,
+
+    /// No script text at all; only commands built with
+    /// [`PowerShell::command`](super::PowerShell::command) can run.
+    NoLanguage,
+}
+
+impl LanguageMode {
+    /// Value of the matching `PSLanguageMode` member.
+    fn value(self) -> i32 {
+        match self {
+            LanguageMode::FullLanguage => 0,
+            LanguageMode::RestrictedLanguage => 1,
+            LanguageMode::NoLanguage => 2,
+            LanguageMode::ConstrainedLanguage => 3,
+        }
+    }
+}
+
+/// `Microsoft.PowerShell.ExecutionPolicy` of a runspace.
+///
+/// Ignored on PowerShell 2.0, whose `InitialSessionState` has no execution policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutionPolicy {
+    /// Every script runs; downloaded scripts prompt first.
+    Unrestricted,
+
+    /// Downloaded scripts must be signed.
+    RemoteSigned,
+
+    /// Every script and configuration file must be signed.
+    AllSigned,
+
+    /// Commands run but script files do not.
+    Restricted,
+
+    /// Nothing is blocked and nothing prompts.
+    Bypass,
+}
+
+impl ExecutionPolicy {
+    /// Value of the matching `ExecutionPolicy` member.
+    fn value(self) -> i32 {
+        match self {
+            ExecutionPolicy::Unrestricted => 0,
+            ExecutionPolicy::RemoteSigned => 1,
+            ExecutionPolicy::AllSigned => 2,
+            ExecutionPolicy::Restricted => 3,
+            ExecutionPolicy::Bypass => 4,
+        }
+    }
+}
+
+/// Describes how the runspace of a [`PowerShell`](super::PowerShell) is set up.
+///
+/// Everything except the command list is applied to the `InitialSessionState` before
+/// the runspace is opened, and the command list right after it opens, so scripts cannot
+/// undo it by changing `$ExecutionContext.SessionState.LanguageMode` or re-importing
+/// modules they were not given.
+///
+/// # Example
+///
+/// ```
+/// let config = PowerShellConfig::new()
+///     .with_language_mode(LanguageMode::ConstrainedLanguage)
+///     .with_commands(["Get-Date", "Get-ChildItem", "Get-Inventory"])
+///     .with_function("Get-Inventory", "Get-ChildItem -Path $InventoryRoot")
+///     .with_variable("InventoryRoot", "C:\\Inventory");
+/// let pwsh = PowerShell::with_config(&config)?;
+/// ```
+#[derive(Clone, Default)]
+pub struct PowerShellConfig {
+    /// Language mode, or the default `FullLanguage` when `None`.
+    language_mode: Option<LanguageMode>,
+
+    /// Commands left visible to scripts, or every command when `None`.
+    commands: Option<Vec<String>>,
+
+    /// Modules imported before the runspace opens.
+    modules: Vec<String>,
+
+    /// Execution policy for the process scope of the runspace.
+    execution_policy: Option<ExecutionPolicy>,
+
+    /// Variables defined in the global scope.
+    variables: Vec<(String, VARIANT)>,
+
+    /// Functions defined in the global scope, as name and body.
+    functions: Vec<(String, String)>,
+}
+
+impl PowerShellConfig {
+    /// Creates a configuration equivalent to `InitialSessionState.CreateDefault()`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the language mode scripts run in.
+    pub fn with_language_mode(mut self, mode: LanguageMode) -> Self {
+        self.language_mode = Some(mode);
+        self
+    }
+
+    /// Hides every command not in `commands` from scripts.
+    ///
+    /// The list is applied once the runspace has opened, so it also covers commands of
+    /// modules from [`PowerShellConfig::with_module`] and functions from
+    /// [`PowerShellConfig::with_function`]; those must be listed to stay callable, and
+    /// functions may still use hidden commands internally. External applications and
+    /// scripts are blocked and module auto-loading is turned off. `Out-String` is always
+    /// kept because [`PowerShell::execute`](super::PowerShell::execute) formats output
+    /// with it.
+    pub fn with_commands<I, S>(mut self, commands: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.commands = Some(commands.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Imports a module by name or path before the runspace opens.
+    pub fn with_module(mut self, module: &str) -> Self {
+        self.modules.push(module.to_string());
+        self
+    }
+
+    /// Sets the execution policy of the runspace.
+    pub fn with_execution_policy(mut self, policy: ExecutionPolicy) -> Self {
+        self.execution_policy = Some(policy);
+        self
+    }
+
+    /// Defines a global variable, passed as a .NET object.
+    pub fn with_variable<V: Into<VARIANT>>(mut self, name: &str, value: V) -> Self {
+        self.variables.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// Defines a global function from its script body.
+    pub fn with_function(mut self, name: &str, body: &str) -> Self {
+        self.functions.push((name.to_string(), body.to_string()));
+        self
+    }
+
+    /// Builds the `InitialSessionState` described by this configuration.
+    pub(super) fn session_state(&self, env: &CompiledEnv) -> Result<VARIANT> {
+        let (function_names, function_bodies): (Vec<_>, Vec<_>) =
+            self.functions.iter().cloned().unzip();
+
+        let state = env.bootstrap_type.invoke(
+            &obf!("CreateSessionState").as_str(),
+            None,
+            Some(vec![
+                self.language_mode.map_or(-1, LanguageMode::value).into(),
+                self.commands.is_some().into(),
+                string_array(self.commands.clone().unwrap_or_default())?,
+                string_array(self.modules.clone())?,
+                self.execution_policy
+                    .map_or(-1, ExecutionPolicy::value)
+                    .into(),
+                string_array(function_names)?,
+                string_array(function_bodies)?,
+            ]),
+            Invocation::Static,
+        )?;
+
+        for (name, value) in &self.variables {
+            env.bootstrap_type.invoke(
+                &obf!("AddSessionVariable").as_str(),
+                None,
+                Some(vec![state.clone(), name.as_str().into(), value.clone()]),
+                Invocation::Static,
+            )?;
+        }
+
+        Ok(state)
+    }
+}
+
+/// Converts `strings` into a `string[]` argument, passing `null` for an empty list.
+fn string_array(strings: Vec<String>) -> Result<VARIANT> {
+    if strings.is_empty() {
+        return Ok(VARIANT::default());
+    }
+
+    create_string_array_variant(strings)
+}
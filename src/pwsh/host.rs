@@ -0,0 +1,380 @@
+//! Callbacks from the managed host into Rust for prompts and progress.
+//!
+//! `CaptureUI` receives native function pointers through `HostBootstrap.SetHostCallbacks`
+//! and turns them into delegates. Every call carries the token the handler was
+//! registered under, so instances in different AppDomains never collide. Lists cross
+//! the boundary as one string, with fields split by `\x1f` and records by `\x1e`.
+
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicI64, Ordering};
+
+use spin::Mutex;
+use windows::core::{BSTR, PCWSTR};
+
+/// Separates the fields of a record.
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// Separates records.
+const RECORD_SEPARATOR: char = '\x1e';
+
+/// Answers the interactive requests a script makes of its host.
+///
+/// Every method has a default that matches the behaviour without a handler, so
+/// implementations only override what they support. Methods are called on the
+/// runspace's pipeline thread while the execution is in progress; calling back into
+/// the same [`PowerShell`](super::PowerShell) from them deadlocks.
+///
+/// # Example
+///
+/// ```
+/// struct Console;
+///
+/// impl PsHostHandler for Console {
+///     fn read_line(&self) -> Option<String> {
+///         let mut line = String::new();
+///         std::io::stdin().read_line(&mut line).ok()?;
+///         Some(line.trim_end().to_string())
+///     }
+///
+///     fn progress(&self, record: &ProgressRecord) {
+///         eprintln!("{} {}%", record.activity, record.percent_complete);
+///     }
+/// }
+///
+/// let pwsh = PowerShell::new()?.with_host_handler(Console)?;
+/// pwsh.execute("$name = Read-Host; \"Hello $name\"")?;
+/// ```
+pub trait PsHostHandler: Send + Sync {
+    /// Answers `Read-Host` without a prompt and `$Host.UI.ReadLine()`; `None` yields an
+    /// empty string.
+    fn read_line(&self) -> Option<String> {
+        None
+    }
+
+    /// Answers `$Host.UI.Prompt`, e.g. for `Read-Host -Prompt` or mandatory parameters
+    /// left unbound.
+    ///
+    /// Returns one value per field in the same order; `None` leaves every field unset.
+    fn prompt(
+        &self,
+        caption: &str,
+        message: &str,
+        fields: &[FieldDescription],
+    ) -> Option<Vec<String>> {
+        let _ = (caption, message, fields);
+        None
+    }
+
+    /// Answers `$Host.UI.PromptForChoice`, e.g. `-Confirm` prompts, with an index into
+    /// `choices`; `None` or an out-of-range index selects `default`.
+    fn prompt_for_choice(
+        &self,
+        caption: &str,
+        message: &str,
+        choices: &[ChoiceDescription],
+        default: usize,
+    ) -> Option<usize> {
+        let _ = (caption, message, choices, default);
+        None
+    }
+
+    /// Receives `Write-Progress` updates.
+    fn progress(&self, record: &ProgressRecord) {
+        let _ = record;
+    }
+}
+
+impl<H: PsHostHandler + ?Sized> PsHostHandler for Arc<H> {
+    fn read_line(&self) -> Option<String> {
+        (**self).read_line()
+    }
+
+    fn prompt(
+        &self,
+        caption: &str,
+        message: &str,
+        fields: &[FieldDescription],
+    ) -> Option<Vec<String>> {
+        (**self).prompt(caption, message, fields)
+    }
+
+    fn prompt_for_choice(
+        &self,
+        caption: &str,
+        message: &str,
+        choices: &[ChoiceDescription],
+        default: usize,
+    ) -> Option<usize> {
+        (**self).prompt_for_choice(caption, message, choices, default)
+    }
+
+    fn progress(&self, record: &ProgressRecord) {
+        (**self).progress(record)
+    }
+}
+
+/// A field requested by [`PsHostHandler::prompt`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldDescription {
+    /// Name of the field, e.g. the parameter name.
+    pub name: String,
+
+    /// Label to display, may contain an `&` hotkey marker.
+    pub label: String,
+
+    /// Help message for the field.
+    pub help: String,
+}
+
+/// An option offered by [`PsHostHandler::prompt_for_choice`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChoiceDescription {
+    /// Label to display, e.g. `&Yes`; the character after `&` is the hotkey.
+    pub label: String,
+
+    /// Help message for the option.
+    pub help: String,
+}
+
+/// A `Write-Progress` update.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProgressRecord {
+    /// Identifies the activity among concurrent ones.
+    pub activity_id: i32,
+
+    /// Activity this one is nested under, or `-1`.
+    pub parent_activity_id: i32,
+
+    /// Description of the activity.
+    pub activity: String,
+
+    /// Current status of the activity.
+    pub status: String,
+
+    /// Operation being performed, may be empty.
+    pub current_operation: String,
+
+    /// Percentage done, or `-1` when unknown.
+    pub percent_complete: i32,
+
+    /// Estimated seconds left, or `-1` when unknown.
+    pub seconds_remaining: i32,
+
+    /// `true` for the final update of the activity.
+    pub completed: bool,
+}
+
+/// Handlers by registration token.
+static HANDLERS: Mutex<Vec<(i64, Arc<dyn PsHostHandler>)>> = Mutex::new(Vec::new());
+
+/// Next registration token.
+static NEXT_TOKEN: AtomicI64 = AtomicI64::new(1);
+
+/// Registers `handler` and returns the token the managed host passes back.
+pub(super) fn register(handler: Arc<dyn PsHostHandler>) -> i64 {
+    let token = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+    HANDLERS.lock().push((token, handler));
+    token
+}
+
+/// Removes the handler registered under `token`.
+pub(super) fn unregister(token: i64) {
+    HANDLERS
+        .lock()
+        .retain(|(registered, _)| *registered != token);
+}
+
+/// Looks up a handler without holding the registry lock while it runs.
+fn handler(token: i64) -> Option<Arc<dyn PsHostHandler>> {
+    HANDLERS
+        .lock()
+        .iter()
+        .find(|(registered, _)| *registered == token)
+        .map(|(_, handler)| handler.clone())
+}
+
+/// Addresses of the callbacks, in the order `SetHostCallbacks` expects them.
+pub(super) fn callbacks() -> [i64; 4] {
+    [
+        read_line as *const () as usize as i64,
+        prompt as *const () as usize as i64,
+        prompt_for_choice as *const () as usize as i64,
+        progress as *const () as usize as i64,
+    ]
+}
+
+/// `ReadLineCallback`; returns a BSTR the marshaller frees, or null.
+extern "system" fn read_line(token: i64) -> *const u16 {
+    handler(token)
+        .and_then(|handler| handler.read_line())
+        .map_or(core::ptr::null(), |line| {
+            BSTR::from(line.as_str()).into_raw()
+        })
+}
+
+/// `PromptCallback`; returns the values joined by [`RECORD_SEPARATOR`] as a BSTR, or null.
+extern "system" fn prompt(
+    token: i64,
+    caption: *const u16,
+    message: *const u16,
+    fields: *const u16,
+) -> *const u16 {
+    let Some(handler) = handler(token) else {
+        return core::ptr::null();
+    };
+
+    let fields = records(&wide(fields))
+        .map(|mut record| FieldDescription {
+            name: take(&mut record, 0),
+            label: take(&mut record, 1),
+            help: take(&mut record, 2),
+        })
+        .collect::<Vec<_>>();
+
+    handler
+        .prompt(&wide(caption), &wide(message), &fields)
+        .map_or(core::ptr::null(), |values| {
+            let joined = values.join(&RECORD_SEPARATOR.to_string());
+            BSTR::from(joined.as_str()).into_raw()
+        })
+}
+
+/// `ChoiceCallback`; returns the selected index or `-1`.
+extern "system" fn prompt_for_choice(
+    token: i64,
+    caption: *const u16,
+    message: *const u16,
+    choices: *const u16,
+    default: i32,
+) -> i32 {
+    let Some(handler) = handler(token) else {
+        return -1;
+    };
+
+    let choices = records(&wide(choices))
+        .map(|mut record| ChoiceDescription {
+            label: take(&mut record, 0),
+            help: take(&mut record, 1),
+        })
+        .collect::<Vec<_>>();
+
+    handler
+        .prompt_for_choice(
+            &wide(caption),
+            &wide(message),
+            &choices,
+            usize::try_from(default).unwrap_or(0),
+        )
+        .filter(|&choice| choice < choices.len())
+        .and_then(|choice| i32::try_from(choice).ok())
+        .unwrap_or(-1)
+}
+
+/// `ProgressCallback`.
+#[allow(clippy::too_many_arguments)]
+extern "system" fn progress(
+    token: i64,
+    activity_id: i32,
+    parent_activity_id: i32,
+    activity: *const u16,
+    status: *const u16,
+    current_operation: *const u16,
+    percent_complete: i32,
+    seconds_remaining: i32,
+    completed: i32,
+) {
+    if let Some(handler) = handler(token) {
+        handler.progress(&ProgressRecord {
+            activity_id,
+            parent_activity_id,
+            activity: wide(activity),
+            status: wide(status),
+            current_operation: wide(current_operation),
+            percent_complete,
+            seconds_remaining,
+            completed: completed != 0,
+        });
+    }
+}
+
+/// Copies a null-terminated UTF-16 string passed by the marshaller.
+fn wide(ptr: *const u16) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+
+    unsafe { PCWSTR(ptr).to_string() }.unwrap_or_default()
+}
+
+/// Splits a payload into records of fields; an empty payload has no records.
+fn records(payload: &str) -> impl Iterator<Item = Vec<String>> + '_ {
+    payload
+        .split(RECORD_SEPARATOR)
+        .filter(move |_| !payload.is_empty())
+        .map(|record| {
+            record
+                .split(FIELD_SEPARATOR)
+                .map(ToString::to_string)
+                .collect()
+        })
+}
+
+/// Moves field `index` out of `record`, or returns an empty string if it is missing.
+fn take(record: &mut [String], index: usize) -> String {
+    record
+        .get_mut(index)
+        .map(core::mem::take)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    struct Answers;
+
+    impl PsHostHandler for Answers {
+        fn read_line(&self) -> Option<String> {
+            Some("typed".into())
+        }
+    }
+
+    #[test]
+    fn test_records() {
+        assert_eq!(records("").count(), 0);
+        assert_eq!(
+            records("Path\x1f&Path\x1fWhere\x1eForce\x1f\x1f").collect::<Vec<_>>(),
+            [vec!["Path", "&Path", "Where"], vec!["Force", "", ""]]
+        );
+
+        let mut record = vec!["&Yes".to_string()];
+        assert_eq!(take(&mut record, 0), "&Yes");
+        assert_eq!(take(&mut record, 1), "");
+    }
+
+    #[test]
+    fn test_registry() {
+        let token = register(Arc::new(Answers));
+        let other = register(Arc::new(Answers));
+        assert_ne!(token, other);
+
+        assert_eq!(
+            handler(token).and_then(|handler| handler.read_line()),
+            Some("typed".into())
+        );
+        assert_eq!(
+            handler(token).and_then(|handler| handler.prompt_for_choice("", "", &[], 0)),
+            None
+        );
+
+        unregister(token);
+        assert!(handler(token).is_none());
+        assert!(handler(other).is_some());
+        unregister(other);
+    }
+}
@@ -0,0 +1,37 @@
+//! The prebuilt `HostBootstrap` assembly produced by the build script.
+
+/// `HostBootstrap.dll` as compiled by `build.rs`, or `None` when no .NET compiler was
+/// available at build time.
+///
+/// Besides the PowerShell host it carries `RustClrResolver`, used by
+/// [`RustClr::with_dependency`](crate::RustClr::with_dependency).
+#[cfg(embedded_bootstrap)]
+pub const HOST_IMAGE: Option<&[u8]> = Some(include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/bootstrap/HostBootstrap.dll"
+)));
+
+#[cfg(not(embedded_bootstrap))]
+pub const HOST_IMAGE: Option<&[u8]> = None;
+
+#[cfg(test)]
+mod tests {
+    use super::HOST_IMAGE;
+    use crate::AssemblyReport;
+
+    #[test]
+    fn test_host_bootstrap_image() {
+        let image =
+            HOST_IMAGE.expect("HostBootstrap.dll was not built, see the build script warning");
+        assert!(crate::pe::validate_library(image).is_ok());
+
+        let report = AssemblyReport::parse(image).unwrap();
+        assert_eq!(report.identity.unwrap().name, "HostBootstrap");
+        for name in ["HostBootstrap", "RustClrResolver", "CaptureHost"] {
+            assert!(
+                report.types.iter().any(|ty| ty.name == name),
+                "{name} missing"
+            );
+        }
+    }
+}
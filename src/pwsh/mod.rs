@@ -0,0 +1,1253 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use const_encrypt::obf;
+use core::time::Duration;
+use spin::{Mutex, MutexGuard};
+use windows::Win32::System::Threading::Sleep;
+use windows::Win32::System::Variant::VARIANT;
+use windows::core::{BSTR, Interface};
+
+use crate::clr::thread::{Deadline, Event};
+use crate::com::{_Assembly, _Exception};
+use crate::error::{ClrError, Result};
+use crate::variant::{create_safe_args, unknown_from_variant};
+use crate::{CSharpCompiler, Invocation, RustClrEnv, com};
+
+pub mod clixml;
+
+mod command;
+pub use command::PsCommand;
+
+mod config;
+pub use config::{ExecutionPolicy, LanguageMode, PowerShellConfig};
+
+mod host;
+pub use host::{ChoiceDescription, FieldDescription, ProgressRecord, PsHostHandler};
+
+mod image;
+pub(crate) use image::HOST_IMAGE;
+
+mod pool;
+pub use pool::{PooledPowerShell, PowerShellPool};
+
+mod result;
+pub use result::{ErrorRecord, ExecutionResult};
+
+mod value;
+pub use value::PsValue;
+use value::ValueReader;
+pub(crate) use value::scalar;
+
+struct CompiledEnv {
+    bootstrap_type: com::_Type,
+    automation: _Assembly,
+    clr: RustClrEnv,
+}
+
+// SAFETY: the CLR's COM interfaces are agile, so any thread may call them, as described
+// on `ClrWorker`. Access is serialized by the Mutex, preventing concurrent use.
+unsafe impl Send for CompiledEnv {}
+
+/// Environment shared by every [`PowerShell::new`] instance, compiled on first use.
+static SHARED_ENV: Mutex<Option<SharedEnv>> = Mutex::new(None);
+
+/// A compiled host environment and the locks serialising access to it.
+type SharedEnv = Arc<EnvLock>;
+
+/// Serialises access to a [`CompiledEnv`] shared by several instances.
+struct EnvLock {
+    /// Held for each call into the environment.
+    env: Mutex<CompiledEnv>,
+
+    /// Held while an instance redirects the process's standard output, which lasts as
+    /// long as its pipeline runs; a kernel event so waiting instances block rather than spin.
+    capture: Event,
+}
+
+impl EnvLock {
+    fn new(env: CompiledEnv) -> Result<SharedEnv> {
+        let capture = Event::new(false)?;
+        capture.set();
+
+        Ok(Arc::new(Self {
+            env: Mutex::new(env),
+            capture,
+        }))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CompiledEnv> {
+        self.env.lock()
+    }
+
+    /// Waits until no other instance captures standard output and keeps it until the
+    /// returned guard is dropped.
+    fn capture(&self) -> CaptureTurn<'_> {
+        self.capture.wait();
+        CaptureTurn(&self.capture)
+    }
+}
+
+/// Lets the next instance capture standard output once dropped.
+struct CaptureTurn<'a>(&'a Event);
+
+impl Drop for CaptureTurn<'_> {
+    fn drop(&mut self) {
+        self.0.set();
+    }
+}
+
+/// Returns the shared environment, compiling it on first use.
+fn shared_env() -> Result<SharedEnv> {
+    let mut guard = SHARED_ENV.lock();
+    if let Some(env) = guard.as_ref() {
+        return Ok(env.clone());
+    }
+
+    let env = EnvLock::new(create_env()?)?;
+    *guard = Some(env.clone());
+    Ok(env)
+}
+
+/// `PipelineState` values of a pipeline that has finished running.
+const PIPELINE_STATE_STOPPED: i32 = 3;
+const PIPELINE_STATE_COMPLETED: i32 = 4;
+const PIPELINE_STATE_FAILED: i32 = 5;
+
+/// Interval between pipeline state checks while a timeout is armed.
+const PIPELINE_POLL_INTERVAL_MS: u32 = 10;
+
+/// Host streams exposed by `HostBootstrap.GetHostStreamCount`/`GetHostStreamItem`.
+const HOST_STREAM_WARNING: i32 = 0;
+const HOST_STREAM_VERBOSE: i32 = 1;
+const HOST_STREAM_DEBUG: i32 = 2;
+
+/// Fields exposed by `HostBootstrap.GetErrorField`.
+const ERROR_FIELD_MESSAGE: i32 = 0;
+const ERROR_FIELD_CATEGORY: i32 = 1;
+const ERROR_FIELD_ID: i32 = 2;
+const ERROR_FIELD_STACK_TRACE: i32 = 3;
+
+/// Serialization depth used by [`PowerShell::execute_clixml`], the `Export-Clixml` default.
+const CLIXML_DEPTH: u32 = 2;
+
+/// Source of the `HostBootstrap` assembly, compiled at runtime when no prebuilt image was
+/// built with the crate.
+const HOST_SOURCE: &str = include_str!("bootstrap/HostBootstrap.cs");
+
+/// Creates a runtime with a fresh AppDomain and loads the PowerShell host into it.
+fn create_env() -> Result<CompiledEnv> {
+    let clr = RustClrEnv::new(None)?;
+    let mscorlib = clr.app_domain.get_assembly(&obf!("mscorlib").as_str())?;
+    let reflection_assembly =
+        mscorlib.resolve_type(&obf!("System.Reflection.Assembly").as_str())?;
+    let load_partial_name = reflection_assembly.method_signature(
+        &obf!("System.Reflection.Assembly LoadWithPartialName(System.String)").as_str(),
+    )?;
+
+    // Load System.Management.Automation via partial name (version-agnostic)
+    let sma_param = create_safe_args(vec![
+        (&*obf!("System.Management.Automation").as_str()).into(),
+    ])?;
+    let sma_asm = load_partial_name.invoke(None, Some(&sma_param))?;
+    let sma_ptr = unsafe { sma_asm.Anonymous.Anonymous.Anonymous.byref };
+    if sma_ptr.is_null() {
+        return Err(ClrError::Msg(
+            obf!("System.Management.Automation assembly not found").to_string(),
+        ));
+    }
+    let automation = _Assembly::from_raw(sma_ptr)?;
+
+    // The prebuilt host is only missing when no .NET compiler was available at build time
+    let host = match HOST_IMAGE {
+        Some(image) => {
+            crate::pe::validate_library(image)?;
+            clr.app_domain.load_bytes(image)?
+        }
+        None => compile_host(&clr, &automation)?,
+    };
+    let bootstrap_type = init_bootstrap(&host)?;
+
+    Ok(CompiledEnv {
+        bootstrap_type,
+        automation,
+        clr,
+    })
+}
+
+/// Resolves `HostBootstrap` in the host assembly and hands the assembly to it.
+fn init_bootstrap(compiled_asm: &_Assembly) -> Result<com::_Type> {
+    let bootstrap_type = compiled_asm.resolve_type(&obf!("HostBootstrap").as_str())?;
+
+    // Store the compiled assembly reference in C# for later use
+    let asm_variant = compiled_asm.to_variant();
+    bootstrap_type.invoke(
+        &obf!("SetCompiledAssembly").as_str(),
+        None,
+        Some(vec![asm_variant]),
+        Invocation::Static,
+    )?;
+
+    Ok(bootstrap_type)
+}
+
+/// Compiles [`HOST_SOURCE`] in memory against the loaded `System.Management.Automation`.
+fn compile_host(clr: &RustClrEnv, automation: &_Assembly) -> Result<_Assembly> {
+    CSharpCompiler::new(&clr.app_domain)
+        .add_reference(&obf!("System.Core.dll").as_str())
+        .add_reference(&automation.get_Location()?)
+        .compile(HOST_SOURCE)
+}
+
+/// Provides a persistent interface for executing PowerShell commands.
+///
+/// # Example
+///
+/// ```
+/// let pwsh = PowerShell::new()?;
+/// let out = pwsh.execute("whoami")?;
+/// print!("Output: {}", out);
+/// ```
+pub struct PowerShell {
+    env: SharedEnv,
+    instance_id: i64,
+
+    /// Registration token of the [`PsHostHandler`], if one was set.
+    host_token: Option<i64>,
+}
+
+impl PowerShell {
+    /// Creates a new `PowerShell` with a custom host for output capture.
+    ///
+    /// Instances created this way share one AppDomain and take turns executing; use
+    /// [`PowerShell::isolated`] or a [`PowerShellPool`] when that is not acceptable.
+    pub fn new() -> Result<Self> {
+        Self::with_env(shared_env()?, None)
+    }
+
+    /// Creates a `PowerShell` whose runspace is restricted as described by `config`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let config = PowerShellConfig::new()
+    ///     .with_language_mode(LanguageMode::ConstrainedLanguage)
+    ///     .with_commands(["Get-Date", "Get-ChildItem"]);
+    /// let pwsh = PowerShell::with_config(&config)?;
+    /// println!("{}", pwsh.execute("Get-Date")?);
+    /// ```
+    pub fn with_config(config: &PowerShellConfig) -> Result<Self> {
+        Self::with_env(shared_env()?, Some(config))
+    }
+
+    /// Creates a `PowerShell` in an AppDomain of its own.
+    ///
+    /// Modules it loads and failures it runs into cannot affect other instances, and the
+    /// domain is unloaded when the instance is dropped. Compiling the host for each
+    /// domain makes this noticeably slower than [`PowerShell::new`].
+    pub fn isolated() -> Result<Self> {
+        Self::with_env(EnvLock::new(create_env()?)?, None)
+    }
+
+    /// Opens a runspace in `env`, using the default session state unless `config` is set.
+    fn with_env(env: SharedEnv, config: Option<&PowerShellConfig>) -> Result<Self> {
+        let instance_id = {
+            let guard = env.lock();
+            let state = match config {
+                Some(config) => config.session_state(&guard)?,
+                None => VARIANT::default(),
+            };
+
+            // Create a new runspace instance (returns ID)
+            let create_result = guard.bootstrap_type.invoke(
+                &obf!("CreateRunspace").as_str(),
+                None,
+                Some(vec![state]),
+                Invocation::Static,
+            )?;
+            unsafe { create_result.Anonymous.Anonymous.Anonymous.llVal }
+        };
+
+        if instance_id < 0 {
+            return Err(ClrError::Msg(obf!("CreateRunspace failed").to_string()));
+        }
+
+        Ok(Self {
+            env,
+            instance_id,
+            host_token: None,
+        })
+    }
+
+    /// Routes `Read-Host`, prompts and `Write-Progress` to `handler` instead of answering
+    /// them with empty defaults.
+    ///
+    /// Replaces any handler set before.
+    pub fn with_host_handler<H: PsHostHandler + 'static>(mut self, handler: H) -> Result<Self> {
+        let token = host::register(Arc::new(handler));
+        let [read_line, prompt, choice, progress] = host::callbacks();
+
+        let result = self.env.lock().bootstrap_type.invoke(
+            &obf!("SetHostCallbacks").as_str(),
+            None,
+            Some(vec![
+                self.instance_id.into(),
+                token.into(),
+                read_line.into(),
+                prompt.into(),
+                choice.into(),
+                progress.into(),
+            ]),
+            Invocation::Static,
+        );
+        if let Err(err) = result {
+            host::unregister(token);
+            return Err(err);
+        }
+
+        if let Some(previous) = self.host_token.replace(token) {
+            host::unregister(previous);
+        }
+
+        Ok(self)
+    }
+
+    /// Executes a PowerShell command and returns its output as a string.
+    pub fn execute(&self, command: &str) -> Result<String> {
+        self.execute_inner(command, None)
+    }
+
+    /// Executes a PowerShell command, stopping the pipeline if it is still running
+    /// once `timeout` has elapsed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::Timeout`] when the pipeline had to be stopped.
+    pub fn execute_with_timeout(&self, command: &str, timeout: Duration) -> Result<String> {
+        self.execute_inner(command, Some(timeout))
+    }
+
+    /// Executes a PowerShell command and returns its output with errors, warnings,
+    /// verbose, debug and host output collected separately.
+    ///
+    /// Non-terminating errors do not fail the call; inspect
+    /// [`ExecutionResult::had_errors`] and [`ExecutionResult::errors`] instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pwsh = PowerShell::new()?;
+    /// let result = pwsh.execute_detailed("Get-Item C:\\missing; Write-Warning 'careful'")?;
+    /// for error in &result.errors {
+    ///     println!("{}: {}", error.category, error.message);
+    /// }
+    /// ```
+    pub fn execute_detailed(&self, command: &str) -> Result<ExecutionResult> {
+        self.execute_detailed_inner(command, None)
+    }
+
+    /// Like [`PowerShell::execute_detailed`], stopping the pipeline if it is still
+    /// running once `timeout` has elapsed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::Timeout`] when the pipeline had to be stopped.
+    pub fn execute_detailed_with_timeout(
+        &self,
+        command: &str,
+        timeout: Duration,
+    ) -> Result<ExecutionResult> {
+        self.execute_detailed_inner(command, Some(timeout))
+    }
+
+    fn execute_detailed_inner(
+        &self,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> Result<ExecutionResult> {
+        let mscorlib = self.mscorlib()?;
+        let (pipeline, output) = self.run_formatted(&mscorlib, command, timeout)?;
+
+        let guard = self.env.lock();
+        let env = &*guard;
+        let mut result = ExecutionResult {
+            output: output.concat(),
+            ..ExecutionResult::default()
+        };
+
+        // Non-terminating errors, then the reason the pipeline failed, if it did
+        let mut errors = collection_items(&mscorlib, &pipeline.read_errors()?)?;
+        errors.extend(pipeline.failure_reason()?);
+        for error in errors {
+            result.errors.push(self.error_record(env, error)?);
+        }
+        result.had_errors = !result.errors.is_empty();
+
+        result.warnings = self.host_stream(env, HOST_STREAM_WARNING)?;
+        result.verbose = self.host_stream(env, HOST_STREAM_VERBOSE)?;
+        result.debug = self.host_stream(env, HOST_STREAM_DEBUG)?;
+
+        let information = env.bootstrap_type.invoke(
+            &obf!("GetHostInformation").as_str(),
+            None,
+            Some(vec![self.instance_id.into()]),
+            Invocation::Static,
+        )?;
+        result.information = information
+            .to_string()
+            .lines()
+            .map(ToString::to_string)
+            .collect();
+
+        Ok(result)
+    }
+
+    /// Executes a PowerShell script and returns its output objects as [`PsValue`] trees
+    /// instead of formatted text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pwsh = PowerShell::new()?;
+    /// for process in pwsh.invoke("Get-Process | Select-Object -First 3")? {
+    ///     println!("{:?} {:?}", process.get("ProcessName"), process.get("Id"));
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::ManagedException`] when the script fails with a terminating error.
+    pub fn invoke(&self, script: &str) -> Result<Vec<PsValue>> {
+        let guard = self.env.lock();
+        let env = &*guard;
+        let mscorlib = env
+            .clr
+            .app_domain
+            .get_assembly(&obf!("mscorlib").as_str())?;
+
+        let pipeline = Pipeline::new(env, self.instance_id, script)?;
+        pipeline.values(&mscorlib)
+    }
+
+    /// Starts building a command whose parameters are passed as objects rather than
+    /// spliced into script text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pwsh = PowerShell::new()?;
+    /// let user_supplied_path = "C:\\Temp'; Stop-Computer #";
+    /// let files = pwsh
+    ///     .command("Get-ChildItem")
+    ///     .param("Path", user_supplied_path)
+    ///     .switch("Recurse")
+    ///     .invoke()?;
+    /// ```
+    pub fn command(&self, name: &str) -> PsCommand<'_> {
+        PsCommand::new(self, name)
+    }
+
+    /// Executes a PowerShell command, serializes its output to CLIXML inside the
+    /// runspace and parses it back into [`PsValue`]s.
+    ///
+    /// Unlike [`PowerShell::invoke`], which walks live objects, this goes through
+    /// `PSSerializer` and therefore yields exactly what `Export-Clixml` would store.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClrError::ManagedException`] when the command fails with a terminating
+    /// error and [`ClrError::InvalidClixml`] when the serialized output cannot be parsed.
+    pub fn execute_clixml(&self, command: &str) -> Result<Vec<PsValue>> {
+        let guard = self.env.lock();
+        let env = &*guard;
+        let mscorlib = env
+            .clr
+            .app_domain
+            .get_assembly(&obf!("mscorlib").as_str())?;
+
+        let script = format!(
+            "{serializer}::Serialize(@(& {{ {command} }}), {CLIXML_DEPTH})",
+            serializer = obf!("[System.Management.Automation.PSSerializer]")
+        );
+        let pipeline = Pipeline::new(env, self.instance_id, &script)?;
+        pipeline.start()?;
+        let output_collection = pipeline.read_output()?;
+
+        if let Some(reason) = pipeline.failure_reason()? {
+            return Err(exception_error(&reason));
+        }
+
+        let object_type = mscorlib.resolve_type(&obf!("System.Object").as_str())?;
+        let to_string = object_type.method_signature(&obf!("System.String ToString()").as_str())?;
+        let mut xml = String::new();
+        for item in collection_items(&mscorlib, &output_collection)? {
+            xml.push_str(&to_string.invoke(Some(item), None)?.to_string());
+        }
+
+        // The output was wrapped in a single array so it serializes as one document
+        let mut values = clixml::parse(&xml)?;
+        match values.pop() {
+            Some(PsValue::Array(items)) if values.is_empty() => Ok(items),
+            Some(value) => {
+                values.push(value);
+                Ok(values)
+            }
+            None => Ok(values),
+        }
+    }
+
+    /// Sets a variable in the runspace so later executions can read it as `$name`.
+    ///
+    /// The value is passed to `SessionStateProxy.SetVariable` as a .NET object, so strings,
+    /// numbers, booleans and COM objects keep their type instead of going through script text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pwsh = PowerShell::new()?;
+    /// pwsh.set_variable("Target", "dc01.corp.local")?;
+    /// pwsh.execute("Test-Connection $Target -Count 1")?;
+    /// ```
+    pub fn set_variable<V: Into<VARIANT>>(&self, name: &str, value: V) -> Result<()> {
+        let guard = self.env.lock();
+        let env = &*guard;
+
+        let (proxy_type, proxy) = self.session_state(env)?;
+        let set_variable = proxy_type
+            .method_signature(&obf!("Void SetVariable(System.String, System.Object)").as_str())?;
+        let args = create_safe_args(vec![BSTR::from(name).into(), value.into()])?;
+        set_variable.invoke(Some(proxy), Some(&args))?;
+
+        Ok(())
+    }
+
+    /// Reads a variable from the runspace, e.g. one assigned by an earlier execution.
+    ///
+    /// Returns [`PsValue::Null`] when the variable does not exist.
+    pub fn get_variable(&self, name: &str) -> Result<PsValue> {
+        let guard = self.env.lock();
+        let env = &*guard;
+        let mscorlib = env
+            .clr
+            .app_domain
+            .get_assembly(&obf!("mscorlib").as_str())?;
+
+        let (proxy_type, proxy) = self.session_state(env)?;
+        let get_variable = proxy_type
+            .method_signature(&obf!("System.Object GetVariable(System.String)").as_str())?;
+        let args = create_safe_args(vec![BSTR::from(name).into()])?;
+        let value = get_variable.invoke(Some(proxy), Some(&args))?;
+
+        ValueReader::new(&mscorlib, &env.automation)?.read(value, ValueReader::DEFAULT_DEPTH)
+    }
+
+    /// Returns the `SessionStateProxy` of this instance's runspace along with its type.
+    fn session_state(&self, env: &CompiledEnv) -> Result<(com::_Type, VARIANT)> {
+        let runspace = env.bootstrap_type.invoke(
+            &obf!("GetRunspace").as_str(),
+            None,
+            Some(vec![self.instance_id.into()]),
+            Invocation::Static,
+        )?;
+
+        let runspace_type = env
+            .automation
+            .resolve_type(&obf!("System.Management.Automation.Runspaces.Runspace").as_str())?;
+        let proxy = runspace_type.invoke(
+            &obf!("get_SessionStateProxy").as_str(),
+            Some(runspace),
+            None,
+            Invocation::Instance,
+        )?;
+        let proxy_type = env.automation.resolve_type(
+            &obf!("System.Management.Automation.Runspaces.SessionStateProxy").as_str(),
+        )?;
+
+        Ok((proxy_type, proxy))
+    }
+
+    /// Reads the messages the host captured for one of the `HOST_STREAM_*` streams.
+    fn host_stream(&self, env: &CompiledEnv, stream: i32) -> Result<Vec<String>> {
+        let count = env.bootstrap_type.invoke(
+            &obf!("GetHostStreamCount").as_str(),
+            None,
+            Some(vec![self.instance_id.into(), stream.into()]),
+            Invocation::Static,
+        )?;
+        let count = unsafe { count.Anonymous.Anonymous.Anonymous.lVal };
+
+        (0..count)
+            .map(|index| {
+                env.bootstrap_type
+                    .invoke(
+                        &obf!("GetHostStreamItem").as_str(),
+                        None,
+                        Some(vec![self.instance_id.into(), stream.into(), index.into()]),
+                        Invocation::Static,
+                    )
+                    .map(|item| item.to_string())
+            })
+            .collect()
+    }
+
+    /// Describes an `ErrorRecord` or exception taken from the pipeline.
+    fn error_record(&self, env: &CompiledEnv, error: VARIANT) -> Result<ErrorRecord> {
+        let field = |field: i32| {
+            env.bootstrap_type
+                .invoke(
+                    &obf!("GetErrorField").as_str(),
+                    None,
+                    Some(vec![error.clone(), field.into()]),
+                    Invocation::Static,
+                )
+                .map(|value| value.to_string())
+        };
+
+        Ok(ErrorRecord {
+            message: field(ERROR_FIELD_MESSAGE)?,
+            category: field(ERROR_FIELD_CATEGORY)?,
+            fully_qualified_id: field(ERROR_FIELD_ID)?,
+            script_stack_trace: field(ERROR_FIELD_STACK_TRACE)?,
+        })
+    }
+
+    fn execute_inner(&self, command: &str, timeout: Option<Duration>) -> Result<String> {
+        let mscorlib = self.mscorlib()?;
+        let (pipeline, output) = self.run_formatted(&mscorlib, command, timeout)?;
+
+        // Get pipeline output
+        let mut result = output
+            .into_iter()
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        // Check for errors via PipelineStateInfo (PS 2.0 compatible)
+        if let Some(reason) = pipeline.failure_reason()? {
+            let exception_type = mscorlib.resolve_type(&obf!("System.Exception").as_str())?;
+            let get_message =
+                exception_type.method_signature(&obf!("System.String get_Message()").as_str())?;
+
+            if let Ok(msg) = get_message.invoke(Some(reason), None) {
+                let s = msg.to_string();
+                if !s.is_empty() {
+                    if !result.is_empty() {
+                        result.push('\n');
+                    }
+                    result.push_str(&s);
+                }
+            }
+        }
+
+        // Get host-captured output (Write-Host, native commands)
+        let host_output = self.env.lock().bootstrap_type.invoke(
+            &obf!("GetHostOutput").as_str(),
+            None,
+            Some(vec![self.instance_id.into()]),
+            Invocation::Static,
+        )?;
+        let host_str = host_output.to_string();
+
+        // Combine: host output first, then pipeline output
+        let mut combined = String::new();
+        if !host_str.is_empty() {
+            combined.push_str(&host_str);
+        }
+        if !result.is_empty() {
+            if !combined.is_empty() && !combined.ends_with('\n') {
+                combined.push('\n');
+            }
+            combined.push_str(&result);
+        }
+
+        Ok(combined)
+    }
+
+    /// Returns `mscorlib` from the environment's AppDomain.
+    fn mscorlib(&self) -> Result<_Assembly> {
+        self.env
+            .lock()
+            .clr
+            .app_domain
+            .get_assembly(&obf!("mscorlib").as_str())
+    }
+
+    /// Runs `command` formatted by `Out-String` while capturing host and native command
+    /// output, stopping it once `timeout` has elapsed.
+    ///
+    /// The environment is only locked around the calls into it, so other instances can
+    /// use it while the pipeline runs; capturing standard output still takes turns.
+    /// Returns the finished pipeline along with the string form of each output object.
+    fn run_formatted(
+        &self,
+        mscorlib: &_Assembly,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> Result<(Pipeline, Vec<String>)> {
+        let _capture = self.env.capture();
+        let pipeline = {
+            let env = self.env.lock();
+
+            // Clear previous output and streams
+            env.bootstrap_type.invoke(
+                &obf!("ClearHostOutput").as_str(),
+                None,
+                Some(vec![self.instance_id.into()]),
+                Invocation::Static,
+            )?;
+
+            // Create pipeline (no try-catch needed with InvokeAsync)
+            let pipeline = Pipeline::formatted(&env, self.instance_id, command)?;
+
+            // Begin capturing native command output
+            env.bootstrap_type.invoke(
+                &obf!("BeginCapture").as_str(),
+                None,
+                Some(vec![self.instance_id.into()]),
+                Invocation::Static,
+            )?;
+
+            pipeline
+        };
+
+        // Run the pipeline and read its output, waiting no longer than the timeout
+        let output_collection = pipeline
+            .start()
+            .and_then(|_| pipeline.wait(timeout))
+            .and_then(|_| pipeline.read_output());
+
+        // End capturing - restore original stdout
+        let _ = self.env.lock().bootstrap_type.invoke(
+            &obf!("EndCapture").as_str(),
+            None,
+            Some(vec![self.instance_id.into()]),
+            Invocation::Static,
+        );
+
+        let output_collection = output_collection?;
+        let object_type = mscorlib.resolve_type(&obf!("System.Object").as_str())?;
+        let to_string = object_type.method_signature(&obf!("System.String ToString()").as_str())?;
+
+        let mut output = Vec::new();
+        for item in collection_items(mscorlib, &output_collection)? {
+            if unsafe { &item.Anonymous.Anonymous.Anonymous.punkVal }.is_some() {
+                output.push(to_string.invoke(Some(item), None)?.to_string());
+            }
+        }
+
+        Ok((pipeline, output))
+    }
+}
+
+/// A pipeline created on one of the bootstrap's runspaces, disposed on drop.
+struct Pipeline {
+    /// `System.Management.Automation`, held so the pipeline can outlive the environment lock.
+    automation: _Assembly,
+
+    /// `System.Management.Automation.Runspaces.Pipeline`.
+    pipeline_type: com::_Type,
+
+    /// The pipeline instance.
+    pipe: VARIANT,
+}
+
+impl Pipeline {
+    /// Creates a pipeline on the runspace of `instance_id` containing `script`.
+    fn new(env: &CompiledEnv, instance_id: i64, script: &str) -> Result<Self> {
+        let pipeline = Self::empty(env, instance_id)?;
+
+        // Add script
+        let command_collection = env.automation.resolve_type(
+            &obf!("System.Management.Automation.Runspaces.CommandCollection").as_str(),
+        )?;
+        let add_script =
+            command_collection.method_signature(&obf!("Void AddScript(System.String)").as_str())?;
+        let script_args = create_safe_args(vec![BSTR::from(script).into()])?;
+        let _add_result = add_script.invoke(Some(pipeline.commands()?), Some(&script_args))?;
+
+        Ok(pipeline)
+    }
+
+    /// Creates a pipeline running `script` and formatting its output with `Out-String`.
+    ///
+    /// `Out-String` is added as a command rather than spliced into the script so this
+    /// keeps working in runspaces whose language mode forbids script blocks.
+    fn formatted(env: &CompiledEnv, instance_id: i64, script: &str) -> Result<Self> {
+        let pipeline = Self::new(env, instance_id, script)?;
+
+        let command_collection = env.automation.resolve_type(
+            &obf!("System.Management.Automation.Runspaces.CommandCollection").as_str(),
+        )?;
+        let add_command =
+            command_collection.method_signature(&obf!("Void Add(System.String)").as_str())?;
+        let args = create_safe_args(vec![(&*obf!("Out-String").as_str()).into()])?;
+        add_command.invoke(Some(pipeline.commands()?), Some(&args))?;
+
+        Ok(pipeline)
+    }
+
+    /// Creates a pipeline without commands on the runspace of `instance_id`.
+    fn empty(env: &CompiledEnv, instance_id: i64) -> Result<Self> {
+        // Get runspace for this instance
+        let runspace = env.bootstrap_type.invoke(
+            &obf!("GetRunspace").as_str(),
+            None,
+            Some(vec![instance_id.into()]),
+            Invocation::Static,
+        )?;
+
+        // Create pipeline
+        let runspace_type = env
+            .automation
+            .resolve_type(&obf!("System.Management.Automation.Runspaces.Runspace").as_str())?;
+        let create_pipeline = runspace_type.method_signature(
+            &obf!("System.Management.Automation.Runspaces.Pipeline CreatePipeline()").as_str(),
+        )?;
+        let pipe = create_pipeline.invoke(Some(runspace), None)?;
+
+        let pipeline_type = env
+            .automation
+            .resolve_type(&obf!("System.Management.Automation.Runspaces.Pipeline").as_str())?;
+        Ok(Self {
+            automation: env.automation.clone(),
+            pipeline_type,
+            pipe,
+        })
+    }
+
+    /// Returns the pipeline's `CommandCollection`.
+    fn commands(&self) -> Result<VARIANT> {
+        self.call(&obf!("get_Commands").as_str())
+    }
+
+    /// Runs the pipeline to completion and converts its output into [`PsValue`]s.
+    fn values(&self, mscorlib: &_Assembly) -> Result<Vec<PsValue>> {
+        self.start()?;
+        let output_collection = self.read_output()?;
+
+        if let Some(reason) = self.failure_reason()? {
+            return Err(exception_error(&reason));
+        }
+
+        let reader = ValueReader::new(mscorlib, &self.automation)?;
+        collection_items(mscorlib, &output_collection)?
+            .into_iter()
+            .map(|item| reader.read(item, ValueReader::DEFAULT_DEPTH))
+            .collect()
+    }
+
+    /// Invokes a parameterless instance method of the pipeline.
+    fn call(&self, name: &str) -> Result<VARIANT> {
+        self.pipeline_type
+            .invoke(name, Some(self.pipe.clone()), None, Invocation::Instance)
+    }
+
+    /// Starts the pipeline; `InvokeAsync` doesn't throw on script errors.
+    fn start(&self) -> Result<()> {
+        self.call(&obf!("InvokeAsync").as_str()).map(|_| ())
+    }
+
+    /// Waits for the pipeline to finish, stopping it once `timeout` has elapsed.
+    ///
+    /// Without a timeout this returns immediately; reading the output blocks instead.
+    /// The pipeline state is polled, so callers must not hold the environment lock.
+    fn wait(&self, timeout: Option<Duration>) -> Result<()> {
+        if timeout.is_none() {
+            return Ok(());
+        }
+
+        let deadline = Deadline::after(timeout);
+        let get_state_info = self.pipeline_type.method_signature(
+            &obf!(
+                "System.Management.Automation.Runspaces.PipelineStateInfo get_PipelineStateInfo()"
+            )
+            .as_str(),
+        )?;
+        let state_info_type = self.automation.resolve_type(
+            &obf!("System.Management.Automation.Runspaces.PipelineStateInfo").as_str(),
+        )?;
+        let get_state = state_info_type.method_signature(
+            &obf!("System.Management.Automation.Runspaces.PipelineState get_State()").as_str(),
+        )?;
+
+        loop {
+            let state_info = get_state_info.invoke(Some(self.pipe.clone()), None)?;
+            let state = get_state.invoke(Some(state_info), None)?;
+            if matches!(
+                unsafe { state.Anonymous.Anonymous.Anonymous.lVal },
+                PIPELINE_STATE_STOPPED | PIPELINE_STATE_COMPLETED | PIPELINE_STATE_FAILED
+            ) {
+                return Ok(());
+            }
+
+            if deadline.expired() {
+                let _ = self.call(&obf!("Stop").as_str());
+                return Err(ClrError::Timeout);
+            }
+
+            unsafe { Sleep(PIPELINE_POLL_INTERVAL_MS) };
+        }
+    }
+
+    /// Blocks until the pipeline finishes and returns its output collection.
+    fn read_output(&self) -> Result<VARIANT> {
+        // Read output via get_Output().ReadToEnd()
+        let output_reader = self.call(&obf!("get_Output").as_str())?;
+
+        let ps_reader_type = self.automation.resolve_type(&obf!(
+            "System.Management.Automation.Runspaces.PipelineReader`1[System.Management.Automation.PSObject]"
+        ).as_str())?;
+        let read_to_end = ps_reader_type.method_signature(&obf!(
+            "System.Collections.ObjectModel.Collection`1[System.Management.Automation.PSObject] ReadToEnd()"
+        ).as_str())?;
+
+        read_to_end.invoke(Some(output_reader), None)
+    }
+
+    /// Returns the non-terminating errors the pipeline wrote.
+    fn read_errors(&self) -> Result<VARIANT> {
+        let error_reader = self.call(&obf!("get_Error").as_str())?;
+
+        let reader_type = self.automation.resolve_type(
+            &obf!("System.Management.Automation.Runspaces.PipelineReader`1[System.Object]")
+                .as_str(),
+        )?;
+        let read_to_end = reader_type.method_signature(
+            &obf!("System.Collections.ObjectModel.Collection`1[System.Object] ReadToEnd()")
+                .as_str(),
+        )?;
+
+        read_to_end.invoke(Some(error_reader), None)
+    }
+
+    /// Returns the exception that made the pipeline fail, if any.
+    fn failure_reason(&self) -> Result<Option<VARIANT>> {
+        let state_info = self.call(&obf!("get_PipelineStateInfo").as_str())?;
+        if unsafe { &state_info.Anonymous.Anonymous.Anonymous.punkVal }.is_none() {
+            return Ok(None);
+        }
+
+        let state_info_type = self.automation.resolve_type(
+            &obf!("System.Management.Automation.Runspaces.PipelineStateInfo").as_str(),
+        )?;
+        let get_reason =
+            state_info_type.method_signature(&obf!("System.Exception get_Reason()").as_str())?;
+
+        Ok(get_reason
+            .invoke(Some(state_info), None)
+            .ok()
+            .filter(|reason| unsafe { &reason.Anonymous.Anonymous.Anonymous.punkVal }.is_some()))
+    }
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        // Dispose pipeline
+        let _ = self.call(&obf!("Dispose").as_str());
+    }
+}
+
+/// Returns the elements of a `Collection<T>` returned by the pipeline.
+fn collection_items(mscorlib: &_Assembly, collection: &VARIANT) -> Result<Vec<VARIANT>> {
+    let mut items = Vec::new();
+    if unsafe { &collection.Anonymous.Anonymous.Anonymous.punkVal }.is_none() {
+        return Ok(items);
+    }
+
+    let icollection = mscorlib.resolve_type(&obf!("System.Collections.ICollection").as_str())?;
+    let get_count = icollection.method_signature(&obf!("Int32 get_Count()").as_str())?;
+    let count_var = get_count.invoke(Some(collection.clone()), None)?;
+    let count = unsafe { count_var.Anonymous.Anonymous.Anonymous.lVal };
+
+    if count > 0 {
+        let ilist = mscorlib.resolve_type(&obf!("System.Collections.IList").as_str())?;
+        let get_item = ilist.method_signature(&obf!("System.Object get_Item(Int32)").as_str())?;
+
+        for i in 0..count {
+            let item_args = create_safe_args(vec![i.into()])?;
+            items.push(get_item.invoke(Some(collection.clone()), Some(&item_args))?);
+        }
+    }
+
+    Ok(items)
+}
+
+/// Converts an exception object returned by PowerShell into a [`ClrError`].
+fn exception_error(exception: &VARIANT) -> ClrError {
+    unknown_from_variant(exception)
+        .and_then(|unknown| {
+            unknown
+                .cast::<_Exception>()
+                .map_err(|_| ClrError::CastingError(obf!("_Exception").to_string()))
+        })
+        .map_or_else(|err| err, |exception| exception.to_error(0))
+}
+
+impl Drop for PowerShell {
+    fn drop(&mut self) {
+        let _ = self.env.lock().bootstrap_type.invoke(
+            &obf!("CloseRunspace").as_str(),
+            None,
+            Some(vec![self.instance_id.into()]),
+            Invocation::Static,
+        );
+
+        if let Some(token) = self.host_token {
+            host::unregister(token);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ChoiceDescription, LanguageMode, PowerShell, PowerShellConfig, PowerShellPool,
+        ProgressRecord, PsHostHandler, PsValue,
+    };
+    use crate::error::{ClrError, Result};
+    use alloc::{string::String, vec::Vec};
+    use core::time::Duration;
+
+    #[test]
+    fn test_powershell() -> Result<()> {
+        let pwsh = PowerShell::new()?;
+        let output = pwsh.execute("whoami /all")?;
+        assert!(
+            output.contains("\\")
+                || output.contains("User")
+                || output.contains("Account")
+                || output.contains("Authority"),
+            "whoami output does not look valid: {output}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invoke_structured() -> Result<()> {
+        let pwsh = PowerShell::new()?;
+        let values =
+            pwsh.invoke("[PSCustomObject]@{ Name = 'rustclr'; Count = 3; Tags = @('a', 'b') }")?;
+
+        assert_eq!(values.len(), 1);
+        assert_eq!(
+            values[0].get("name").and_then(PsValue::as_str),
+            Some("rustclr")
+        );
+        assert_eq!(values[0].get("Count").and_then(PsValue::as_i64), Some(3));
+        assert_eq!(
+            values[0]
+                .get("Tags")
+                .and_then(PsValue::as_array)
+                .map(<[_]>::len),
+            Some(2)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_parameters_are_not_parsed() -> Result<()> {
+        let pwsh = PowerShell::new()?;
+        let payload = "x'; Write-Output 'injected";
+        let values = pwsh
+            .command("Write-Output")
+            .param("InputObject", payload)
+            .switch("NoEnumerate")
+            .invoke()?;
+
+        assert_eq!(values, [PsValue::String(payload.into())]);
+
+        let values = pwsh
+            .command("Join-Path")
+            .arg("C:\\Temp")
+            .arg("a b")
+            .invoke()?;
+        assert_eq!(values[0].as_str(), Some("C:\\Temp\\a b"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_variables() -> Result<()> {
+        let pwsh = PowerShell::new()?;
+        pwsh.set_variable("RustclrName", "rustclr")?;
+        pwsh.set_variable("RustclrCount", 41)?;
+
+        let output = pwsh.execute("$RustclrResult = $RustclrCount + 1; \"$RustclrName\"")?;
+        assert_eq!(output.trim(), "rustclr");
+        assert_eq!(pwsh.get_variable("RustclrResult")?.as_i64(), Some(42));
+        assert!(pwsh.get_variable("RustclrMissing")?.is_null());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restricted_session_state() -> Result<()> {
+        let config = PowerShellConfig::new()
+            .with_language_mode(LanguageMode::ConstrainedLanguage)
+            .with_commands(["Write-Output", "Get-Greeting"])
+            .with_function("Get-Greeting", "\"hello $GreetingName\"")
+            .with_variable("GreetingName", "rustclr");
+        let pwsh = PowerShell::with_config(&config)?;
+
+        assert_eq!(
+            pwsh.invoke("Get-Greeting")?,
+            [PsValue::String("hello rustclr".into())]
+        );
+        assert_eq!(
+            pwsh.invoke("\"$($ExecutionContext.SessionState.LanguageMode)\"")?[0].as_str(),
+            Some("ConstrainedLanguage")
+        );
+        assert!(pwsh.invoke("Get-Process").is_err());
+        assert!(
+            pwsh.invoke("[System.IO.File]::ReadAllText('C:\\Windows\\win.ini')")
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restricted_module_commands() -> Result<()> {
+        let module = std::env::temp_dir().join("RustClrRestricted.psm1");
+        std::fs::write(
+            &module,
+            "function Get-Answer { 42 }\nfunction Get-Secret { 'hidden' }\n",
+        )
+        .unwrap();
+
+        let config = PowerShellConfig::new()
+            .with_module(&module.to_string_lossy())
+            .with_commands(["Get-Answer"]);
+        let result = PowerShell::with_config(&config).and_then(|pwsh| {
+            assert_eq!(pwsh.invoke("Get-Answer")?, [PsValue::Int(42)]);
+            assert!(pwsh.invoke("Get-Secret").is_err());
+            Ok(())
+        });
+
+        let _ = std::fs::remove_file(&module);
+        result
+    }
+
+    #[test]
+    fn test_host_handler_callbacks() -> Result<()> {
+        struct Scripted(std::sync::Mutex<Vec<ProgressRecord>>);
+
+        impl PsHostHandler for Scripted {
+            fn read_line(&self) -> Option<String> {
+                Some("rustclr".into())
+            }
+
+            fn prompt_for_choice(
+                &self,
+                _caption: &str,
+                _message: &str,
+                choices: &[ChoiceDescription],
+                _default: usize,
+            ) -> Option<usize> {
+                choices.iter().position(|choice| choice.label == "&No")
+            }
+
+            fn progress(&self, record: &ProgressRecord) {
+                self.0.lock().unwrap().push(record.clone());
+            }
+        }
+
+        let handler = std::sync::Arc::new(Scripted(Default::default()));
+        let pwsh = PowerShell::new()?.with_host_handler(handler.clone())?;
+
+        assert_eq!(pwsh.execute("Read-Host")?.trim(), "rustclr");
+        let choices = "[System.Management.Automation.Host.ChoiceDescription[]]('&Yes', '&No')";
+        assert_eq!(
+            pwsh.invoke(&alloc::format!(
+                "$Host.UI.PromptForChoice('t', 'm', {choices}, 0)"
+            ))?,
+            [PsValue::Int(1)]
+        );
+
+        pwsh.execute("Write-Progress -Activity Copy -PercentComplete 50")?;
+        let records = handler.0.lock().unwrap();
+        assert!(
+            records
+                .iter()
+                .any(|record| record.activity == "Copy" && record.percent_complete == 50)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_isolated_domain() -> Result<()> {
+        let shared = PowerShell::new()?;
+        let isolated = PowerShell::isolated()?;
+        isolated.execute("Add-Type -TypeDefinition 'public class RustclrIsolated {}'")?;
+
+        let probe = "[bool]('RustclrIsolated' -as [type])";
+        assert_eq!(isolated.invoke(probe)?, [PsValue::Bool(true)]);
+        assert_eq!(shared.invoke(probe)?, [PsValue::Bool(false)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pool_runs_concurrently() -> Result<()> {
+        let pool = PowerShellPool::new(2)?;
+
+        std::thread::scope(|scope| {
+            let workers = [1, 2].map(|n| {
+                let pool = &pool;
+                scope.spawn(move || pool.checkout().invoke(&alloc::format!("{n} * 21")))
+            });
+            for (worker, expected) in workers.into_iter().zip([21, 42]) {
+                let values = worker.join().unwrap()?;
+                assert_eq!(values[0].as_i64(), Some(expected));
+            }
+
+            Ok::<_, crate::error::ClrError>(())
+        })?;
+
+        assert_eq!(pool.available(), pool.size());
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_detailed_streams() -> Result<()> {
+        let pwsh = PowerShell::new()?;
+        let result = pwsh.execute_detailed(
+            "Write-Warning 'careful'; Get-Item 'C:\\rustclr-missing'; Write-Host 'hi'; 'done'",
+        )?;
+
+        assert!(result.had_errors);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].category, "ObjectNotFound");
+        assert!(
+            result.errors[0]
+                .fully_qualified_id
+                .starts_with("PathNotFound")
+        );
+        assert_eq!(result.warnings, ["careful"]);
+        assert!(result.information.iter().any(|line| line == "hi"));
+        assert_eq!(result.output.trim(), "done");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_detailed_timeout() -> Result<()> {
+        let pwsh = PowerShell::new()?;
+        let result =
+            pwsh.execute_detailed_with_timeout("Start-Sleep -Seconds 30", Duration::from_secs(1));
+        assert!(matches!(result, Err(ClrError::Timeout)));
+
+        // The stopped pipeline leaves the runspace usable
+        let result = pwsh.execute_detailed_with_timeout("'done'", Duration::from_secs(30))?;
+        assert_eq!(result.output.trim(), "done");
+
+        Ok(())
+    }
+}
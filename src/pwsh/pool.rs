@@ -0,0 +1,192 @@
+//! Runspace pools shared by concurrent callers.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use const_encrypt::obf;
+use windows::Win32::System::Variant::VARIANT;
+
+use super::{EnvLock, PsValue, SharedEnv, ValueReader, collection_items, create_env, shared_env};
+use crate::Invocation;
+use crate::clr::thread::Signal;
+use crate::com::{_Assembly, _Type};
+use crate::error::{ClrError, Result};
+use crate::sync::pool::{Lease, Pool};
+
+/// Executes scripts on a managed `RunspacePool` from several threads at once.
+///
+/// [`PowerShellPool::checkout`] reserves one of the pool's runspaces until the returned
+/// [`PooledPowerShell`] is dropped, blocking while all of them are busy. Calls on
+/// different checkouts run concurrently instead of taking turns like [`PowerShell`]
+/// instances do.
+///
+/// Pooled runspaces keep no state between executions and have no capturing host, so
+/// only pipeline output is returned.
+///
+/// # Example
+///
+/// ```
+/// let pool = PowerShellPool::new(4)?;
+/// let output = pool.checkout().execute("Get-Date")?;
+/// ```
+///
+/// [`PowerShell`]: super::PowerShell
+pub struct PowerShellPool {
+    pool: Pool<RunspacePool>,
+}
+
+impl PowerShellPool {
+    /// Opens a pool of `size` runspaces in the AppDomain shared with
+    /// [`PowerShell::new`](super::PowerShell::new).
+    pub fn new(size: usize) -> Result<Self> {
+        Self::with_env(shared_env()?, size)
+    }
+
+    /// Opens a pool of `size` runspaces in an AppDomain of its own, unloaded when the
+    /// pool is dropped.
+    pub fn isolated(size: usize) -> Result<Self> {
+        Self::with_env(EnvLock::new(create_env()?)?, size)
+    }
+
+    fn with_env(env: SharedEnv, size: usize) -> Result<Self> {
+        Ok(Self {
+            pool: Pool::new(RunspacePool::open(env, size)?, size, Signal::boxed)?,
+        })
+    }
+
+    /// Number of runspaces in the pool.
+    pub fn size(&self) -> usize {
+        self.pool.size()
+    }
+
+    /// Number of runspaces not currently checked out.
+    pub fn available(&self) -> usize {
+        self.pool.available()
+    }
+
+    /// Reserves a runspace, waiting for one to be returned if all are in use.
+    pub fn checkout(&self) -> PooledPowerShell<'_> {
+        PooledPowerShell(self.pool.checkout())
+    }
+
+    /// Reserves a runspace, or returns `None` if all are in use.
+    pub fn try_checkout(&self) -> Option<PooledPowerShell<'_>> {
+        self.pool.try_checkout().map(PooledPowerShell)
+    }
+}
+
+/// A runspace checked out of a [`PowerShellPool`], returned to it on drop.
+pub struct PooledPowerShell<'a>(Lease<'a, RunspacePool>);
+
+impl PooledPowerShell<'_> {
+    /// Executes a script and returns its output formatted by `Out-String`.
+    pub fn execute(&self, script: &str) -> Result<String> {
+        self.0.execute(script)
+    }
+
+    /// Executes a script and returns its output objects as [`PsValue`] trees.
+    pub fn invoke(&self, script: &str) -> Result<Vec<PsValue>> {
+        self.0.invoke(script)
+    }
+}
+
+/// A `RunspacePool` opened through `HostBootstrap.CreatePool`.
+struct RunspacePool {
+    /// Keeps the AppDomain hosting the pool alive.
+    env: SharedEnv,
+
+    /// Members of `env` used without taking its lock.
+    bootstrap_type: _Type,
+    automation: _Assembly,
+    mscorlib: _Assembly,
+
+    /// Pool identifier handed out by `HostBootstrap`.
+    id: i64,
+}
+
+// SAFETY: the CLR's COM interfaces are agile, so any thread may call them, as described
+// on `ClrWorker`, and `HostBootstrap.InvokePool` is safe to call concurrently since it
+// hands each call its own runspace.
+unsafe impl Send for RunspacePool {}
+unsafe impl Sync for RunspacePool {}
+
+impl RunspacePool {
+    fn open(env: SharedEnv, size: usize) -> Result<Self> {
+        let size = i32::try_from(size)
+            .ok()
+            .filter(|&size| size > 0)
+            .ok_or(ClrError::Msg(
+                obf!("invalid runspace pool size").to_string(),
+            ))?;
+
+        let (bootstrap_type, automation, mscorlib, id) = {
+            let guard = env.lock();
+            let result = guard.bootstrap_type.invoke(
+                &obf!("CreatePool").as_str(),
+                None,
+                Some(vec![size.into()]),
+                Invocation::Static,
+            )?;
+            let mscorlib = guard
+                .clr
+                .app_domain
+                .get_assembly(&obf!("mscorlib").as_str())?;
+
+            (
+                guard.bootstrap_type.clone(),
+                guard.automation.clone(),
+                mscorlib,
+                unsafe { result.Anonymous.Anonymous.Anonymous.llVal },
+            )
+        };
+
+        if id < 0 {
+            return Err(ClrError::Msg(obf!("CreateRunspacePool failed").to_string()));
+        }
+
+        Ok(Self {
+            env,
+            bootstrap_type,
+            automation,
+            mscorlib,
+            id,
+        })
+    }
+
+    fn execute(&self, script: &str) -> Result<String> {
+        self.call(script, true).map(|output| output.to_string())
+    }
+
+    fn invoke(&self, script: &str) -> Result<Vec<PsValue>> {
+        let output = self.call(script, false)?;
+        let reader = ValueReader::new(&self.mscorlib, &self.automation)?;
+        collection_items(&self.mscorlib, &output)?
+            .into_iter()
+            .map(|item| reader.read(item, ValueReader::DEFAULT_DEPTH))
+            .collect()
+    }
+
+    /// Runs `script` on the pool, formatting its output with `Out-String` if `format` is set.
+    fn call(&self, script: &str, format: bool) -> Result<VARIANT> {
+        self.bootstrap_type.invoke(
+            &obf!("InvokePool").as_str(),
+            None,
+            Some(vec![self.id.into(), script.into(), format.into()]),
+            Invocation::Static,
+        )
+    }
+}
+
+impl Drop for RunspacePool {
+    fn drop(&mut self) {
+        let _ = self.env.lock().bootstrap_type.invoke(
+            &obf!("ClosePool").as_str(),
+            None,
+            Some(vec![self.id.into()]),
+            Invocation::Static,
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! Outcome of a PowerShell execution with each stream kept separate.
+
+use alloc::{string::String, vec::Vec};
+
+/// Everything a command produced, split by PowerShell stream.
+///
+/// Returned by [`PowerShell::execute_detailed`](super::PowerShell::execute_detailed).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExecutionResult {
+    /// Pipeline output, formatted by `Out-String`.
+    pub output: String,
+
+    /// Non-terminating errors, followed by the terminating error if the command failed.
+    pub errors: Vec<ErrorRecord>,
+
+    /// Messages from `Write-Warning`.
+    pub warnings: Vec<String>,
+
+    /// Messages from `Write-Verbose`, when verbose output is enabled.
+    pub verbose: Vec<String>,
+
+    /// Messages from `Write-Debug`, when debug output is enabled.
+    pub debug: Vec<String>,
+
+    /// Lines written to the host, such as `Write-Host` and native command output.
+    pub information: Vec<String>,
+
+    /// `true` when the command reported any error, terminating or not.
+    pub had_errors: bool,
+}
+
+/// A PowerShell `ErrorRecord`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorRecord {
+    /// The error message.
+    pub message: String,
+
+    /// Name of the `ErrorCategory`, e.g. `ObjectNotFound`.
+    pub category: String,
+
+    /// `FullyQualifiedErrorId`, e.g.
+    /// `PathNotFound,Microsoft.PowerShell.Commands.GetChildItemCommand`.
+    pub fully_qualified_id: String,
+
+    /// Script stack trace at the point of the error; empty on PowerShell 2.0.
+    pub script_stack_trace: String,
+}
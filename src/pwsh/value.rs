@@ -0,0 +1,422 @@
+//! Conversion of PowerShell output objects into plain Rust values.
+
+#[cfg(any(windows, test))]
+use alloc::{format, string::ToString, vec};
+use alloc::{string::String, vec::Vec};
+
+#[cfg(windows)]
+use const_encrypt::obf;
+#[cfg(windows)]
+use windows::Win32::System::Variant::{
+    VARIANT, VT_BOOL, VT_BSTR, VT_CY, VT_DATE, VT_DECIMAL, VT_DISPATCH, VT_EMPTY, VT_I1, VT_I2,
+    VT_I4, VT_I8, VT_INT, VT_NULL, VT_R4, VT_R8, VT_UI1, VT_UI2, VT_UI4, VT_UI8, VT_UINT,
+    VT_UNKNOWN,
+};
+
+#[cfg(windows)]
+use crate::com::{_Assembly, _MethodInfo, _Type};
+#[cfg(windows)]
+use crate::error::Result;
+#[cfg(windows)]
+use crate::variant::create_safe_args;
+
+/// A value produced by a PowerShell pipeline, converted into Rust data.
+///
+/// Objects are converted by walking `PSObject.Properties`, so both .NET objects and
+/// `[PSCustomObject]` instances become [`PsValue::Object`]. Values nested deeper than
+/// the conversion depth are represented by their string form.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PsValue {
+    /// `$null`.
+    Null,
+
+    /// A boolean.
+    Bool(bool),
+
+    /// A signed integer of any width.
+    Int(i64),
+
+    /// An unsigned integer of any width.
+    UInt(u64),
+
+    /// A floating point or decimal number.
+    Float(f64),
+
+    /// A string, or the string form of a value with no properties.
+    String(String),
+
+    /// A `DateTime`, formatted as ISO 8601 (`2024-05-01T13:45:00.000`).
+    DateTime(String),
+
+    /// Any enumerable other than a dictionary or a string.
+    Array(Vec<PsValue>),
+
+    /// A dictionary such as `@{}`, as key/value pairs in enumeration order.
+    Hashtable(Vec<(PsValue, PsValue)>),
+
+    /// An object with its PowerShell type names and properties.
+    Object {
+        /// Entries of `PSObject.TypeNames`, most derived first.
+        type_names: Vec<String>,
+
+        /// Property names and values in declaration order.
+        properties: Vec<(String, PsValue)>,
+    },
+}
+
+impl PsValue {
+    /// Returns `true` for [`PsValue::Null`].
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Returns the value of the property or string hashtable key `name`, compared
+    /// case-insensitively like PowerShell does.
+    pub fn get(&self, name: &str) -> Option<&PsValue> {
+        match self {
+            Self::Object { properties, .. } => properties
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value),
+            Self::Hashtable(entries) => entries
+                .iter()
+                .find(|(key, _)| matches!(key, Self::String(key) if key.eq_ignore_ascii_case(name)))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Returns the contained string, including the string form of a `DateTime`.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) | Self::DateTime(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a signed integer if it fits.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Int(i) => Some(*i),
+            Self::UInt(u) => i64::try_from(*u).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns any numeric value as a float.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Int(i) => Some(*i as f64),
+            Self::UInt(u) => Some(*u as f64),
+            Self::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of an array.
+    pub fn as_array(&self) -> Option<&[PsValue]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Walks PowerShell objects through reflection and converts them into [`PsValue`]s.
+#[cfg(windows)]
+pub(crate) struct ValueReader {
+    /// `PSObject`, `IDictionary` and `IEnumerable` types used for type tests.
+    psobject: _Type,
+    dictionary: _Type,
+    enumerable: _Type,
+
+    /// `Type.IsInstanceOfType`.
+    is_instance_of_type: _MethodInfo,
+
+    /// Members of `PSObject`.
+    get_base_object: _MethodInfo,
+    as_psobject: _MethodInfo,
+    get_properties: _MethodInfo,
+    get_type_names: _MethodInfo,
+
+    /// Members of `PSMemberInfo`.
+    get_name: _MethodInfo,
+    get_value: _MethodInfo,
+
+    /// Members of `IDictionary`.
+    get_keys: _MethodInfo,
+    get_item: _MethodInfo,
+
+    /// Members of `IEnumerable` and `IEnumerator`.
+    get_enumerator: _MethodInfo,
+    move_next: _MethodInfo,
+    get_current: _MethodInfo,
+
+    /// `Object.ToString`.
+    to_string: _MethodInfo,
+}
+
+#[cfg(windows)]
+impl ValueReader {
+    /// Default number of object levels converted, matching `ConvertTo-Json`.
+    pub const DEFAULT_DEPTH: usize = 2;
+
+    /// Resolves the reflection members used during conversion.
+    pub fn new(mscorlib: &_Assembly, automation: &_Assembly) -> Result<Self> {
+        let psobject =
+            automation.resolve_type(&obf!("System.Management.Automation.PSObject").as_str())?;
+        let member_info =
+            automation.resolve_type(&obf!("System.Management.Automation.PSMemberInfo").as_str())?;
+        let dictionary = mscorlib.resolve_type(&obf!("System.Collections.IDictionary").as_str())?;
+        let enumerable = mscorlib.resolve_type(&obf!("System.Collections.IEnumerable").as_str())?;
+        let enumerator = mscorlib.resolve_type(&obf!("System.Collections.IEnumerator").as_str())?;
+        let object = mscorlib.resolve_type(&obf!("System.Object").as_str())?;
+        let ty = mscorlib.resolve_type(&obf!("System.Type").as_str())?;
+
+        Ok(Self {
+            is_instance_of_type: ty
+                .method_signature(&obf!("Boolean IsInstanceOfType(System.Object)").as_str())?,
+            get_base_object: psobject
+                .method_signature(&obf!("System.Object get_BaseObject()").as_str())?,
+            as_psobject: psobject.method_signature(
+                &obf!("System.Management.Automation.PSObject AsPSObject(System.Object)").as_str(),
+            )?,
+            get_properties: psobject.method_signature(
+                &obf!("System.Management.Automation.PSMemberInfoCollection`1[System.Management.Automation.PSPropertyInfo] get_Properties()").as_str(),
+            )?,
+            get_type_names: psobject.method_signature(
+                &obf!("System.Collections.ObjectModel.Collection`1[System.String] get_TypeNames()")
+                    .as_str(),
+            )?,
+            get_name: member_info.method_signature(&obf!("System.String get_Name()").as_str())?,
+            get_value: member_info.method_signature(&obf!("System.Object get_Value()").as_str())?,
+            get_keys: dictionary
+                .method_signature(&obf!("System.Collections.ICollection get_Keys()").as_str())?,
+            get_item: dictionary
+                .method_signature(&obf!("System.Object get_Item(System.Object)").as_str())?,
+            get_enumerator: enumerable.method_signature(
+                &obf!("System.Collections.IEnumerator GetEnumerator()").as_str(),
+            )?,
+            move_next: enumerator.method_signature(&obf!("Boolean MoveNext()").as_str())?,
+            get_current: enumerator
+                .method_signature(&obf!("System.Object get_Current()").as_str())?,
+            to_string: object.method_signature(&obf!("System.String ToString()").as_str())?,
+            psobject,
+            dictionary,
+            enumerable,
+        })
+    }
+
+    /// Converts `value`, expanding objects, dictionaries and arrays `depth` levels deep.
+    pub fn read(&self, value: VARIANT, depth: usize) -> Result<PsValue> {
+        if let Some(scalar) = scalar(&value) {
+            return Ok(scalar);
+        }
+
+        // Work with both the PSObject wrapper (for its properties) and the object it wraps
+        let (psobject, base) = if self.is_instance(&self.psobject, &value)? {
+            let base = self.get_base_object.invoke(Some(value.clone()), None)?;
+            if let Some(scalar) = scalar(&base) {
+                return Ok(scalar);
+            }
+            (value, base)
+        } else {
+            let args = create_safe_args(vec![value.clone()])?;
+            (self.as_psobject.invoke(None, Some(&args))?, value)
+        };
+
+        if depth == 0 {
+            return self.string(base);
+        }
+
+        if self.is_instance(&self.dictionary, &base)? {
+            let keys = self.get_keys.invoke(Some(base.clone()), None)?;
+            let mut entries = Vec::new();
+            for key in self.enumerate(keys)? {
+                let args = create_safe_args(vec![key.clone()])?;
+                let value = self.get_item.invoke(Some(base.clone()), Some(&args))?;
+                entries.push((self.read(key, depth - 1)?, self.read(value, depth - 1)?));
+            }
+            return Ok(PsValue::Hashtable(entries));
+        }
+
+        if self.is_instance(&self.enumerable, &base)? {
+            return self
+                .enumerate(base)?
+                .into_iter()
+                .map(|item| self.read(item, depth - 1))
+                .collect::<Result<Vec<_>>>()
+                .map(PsValue::Array);
+        }
+
+        let properties = self.get_properties.invoke(Some(psobject.clone()), None)?;
+        let mut fields = Vec::new();
+        for property in self.enumerate(properties)? {
+            let name = self
+                .get_name
+                .invoke(Some(property.clone()), None)?
+                .to_string();
+
+            // Getters that throw, which many .NET properties do, read as `$null`
+            let value = match self.get_value.invoke(Some(property), None) {
+                Ok(value) => self.read(value, depth - 1)?,
+                Err(_) => PsValue::Null,
+            };
+            fields.push((name, value));
+        }
+
+        if fields.is_empty() {
+            return self.string(base);
+        }
+
+        let type_names = self.get_type_names.invoke(Some(psobject), None)?;
+        let type_names = self
+            .enumerate(type_names)?
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+
+        Ok(PsValue::Object {
+            type_names,
+            properties: fields,
+        })
+    }
+
+    /// Tests whether `value` is an instance of `ty`.
+    fn is_instance(&self, ty: &_Type, value: &VARIANT) -> Result<bool> {
+        let args = create_safe_args(vec![value.clone()])?;
+        let result = self
+            .is_instance_of_type
+            .invoke(Some(ty.to_variant()), Some(&args))?;
+
+        Ok(unsafe { result.Anonymous.Anonymous.Anonymous.boolVal }.as_bool())
+    }
+
+    /// Collects every element of an `IEnumerable`.
+    fn enumerate(&self, collection: VARIANT) -> Result<Vec<VARIANT>> {
+        let enumerator = self.get_enumerator.invoke(Some(collection), None)?;
+        let mut items = Vec::new();
+        loop {
+            let has_next = self.move_next.invoke(Some(enumerator.clone()), None)?;
+            if !unsafe { has_next.Anonymous.Anonymous.Anonymous.boolVal }.as_bool() {
+                break;
+            }
+            items.push(self.get_current.invoke(Some(enumerator.clone()), None)?);
+        }
+
+        Ok(items)
+    }
+
+    /// Converts an object into its string form.
+    fn string(&self, value: VARIANT) -> Result<PsValue> {
+        let value = self.to_string.invoke(Some(value), None)?;
+        Ok(PsValue::String(value.to_string()))
+    }
+}
+
+/// Converts VARIANTs holding primitive values; returns `None` for COM objects.
+#[cfg(windows)]
+pub(crate) fn scalar(value: &VARIANT) -> Option<PsValue> {
+    let inner = unsafe { &value.Anonymous.Anonymous };
+    let data = &inner.Anonymous;
+
+    let scalar = unsafe {
+        match inner.vt {
+            VT_UNKNOWN | VT_DISPATCH if data.punkVal.is_some() => return None,
+            VT_UNKNOWN | VT_DISPATCH | VT_EMPTY | VT_NULL => PsValue::Null,
+            VT_BOOL => PsValue::Bool(data.boolVal.as_bool()),
+            VT_I1 => PsValue::Int(i64::from(data.bVal as i8)),
+            VT_I2 => PsValue::Int(i64::from(data.iVal)),
+            VT_I4 | VT_INT => PsValue::Int(i64::from(data.lVal)),
+            VT_I8 => PsValue::Int(data.llVal),
+            VT_UI1 => PsValue::UInt(u64::from(data.bVal)),
+            VT_UI2 => PsValue::UInt(u64::from(data.uiVal)),
+            VT_UI4 | VT_UINT => PsValue::UInt(u64::from(data.ulVal)),
+            VT_UI8 => PsValue::UInt(data.ullVal),
+            VT_R4 => PsValue::Float(f64::from(data.fltVal)),
+            VT_R8 => PsValue::Float(data.dblVal),
+            VT_DECIMAL | VT_CY => f64::try_from(value)
+                .map_or_else(|_| PsValue::String(value.to_string()), PsValue::Float),
+            VT_DATE => PsValue::DateTime(ole_date_to_iso(data.date)),
+            VT_BSTR => PsValue::String(data.bstrVal.to_string()),
+            _ => PsValue::String(value.to_string()),
+        }
+    };
+
+    Some(scalar)
+}
+
+/// Formats an OLE Automation date (days since 1899-12-30) as ISO 8601.
+#[cfg(any(windows, test))]
+fn ole_date_to_iso(date: f64) -> String {
+    const MS_PER_DAY: i64 = 86_400_000;
+
+    // The integer part counts days, the fraction is the time of day even for negative dates
+    let days = date as i64;
+    let fraction = date - days as f64;
+    let fraction = if fraction < 0.0 { -fraction } else { fraction };
+    let ms = ((fraction * MS_PER_DAY as f64 + 0.5) as i64).min(MS_PER_DAY - 1);
+
+    let (year, month, day) = civil_from_days(days - 25_569);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1_000 % 60,
+        ms % 1_000
+    )
+}
+
+/// Converts days since 1970-01-01 into a proleptic Gregorian date.
+#[cfg(any(windows, test))]
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = (if z >= 0 { z } else { z - 146_096 }) / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ole_date_to_iso() {
+        assert_eq!(ole_date_to_iso(0.0), "1899-12-30T00:00:00.000");
+        assert_eq!(ole_date_to_iso(45_413.5), "2024-05-01T12:00:00.000");
+        assert_eq!(ole_date_to_iso(25_569.75), "1970-01-01T18:00:00.000");
+        assert_eq!(ole_date_to_iso(-1.25), "1899-12-29T06:00:00.000");
+    }
+
+    #[test]
+    fn test_get_is_case_insensitive() {
+        let value = PsValue::Object {
+            type_names: vec!["System.Diagnostics.Process".to_string()],
+            properties: vec![(
+                "ProcessName".to_string(),
+                PsValue::String("pwsh".to_string()),
+            )],
+        };
+
+        assert_eq!(
+            value.get("processname").and_then(PsValue::as_str),
+            Some("pwsh")
+        );
+        assert!(value.get("Id").is_none());
+    }
+}
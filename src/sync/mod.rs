@@ -0,0 +1,56 @@
+//! Blocking protocols that hand work between threads, kept free of Win32 and COM.
+//!
+//! Threads are woken through a [`Wake`] flag picked by the caller: a Win32 event in the
+//! crate itself and a std one in tests, so the protocols are also tested off Windows.
+
+use alloc::boxed::Box;
+
+use crate::error::Result;
+
+pub(crate) mod pool;
+pub(crate) mod worker;
+
+/// Auto-resetting wake-up flag; each raise releases one waiting thread.
+pub(crate) trait Wake: Send + Sync {
+    /// Releases one waiting thread, or the next one to wait if none is waiting.
+    fn raise(&self);
+
+    /// Blocks until the flag is raised, then lowers it again.
+    fn wait(&self);
+}
+
+/// Creates the wake-up flags of a protocol.
+pub(crate) type NewWake = fn() -> Result<Box<dyn Wake>>;
+
+/// [`Wake`] flag built on std, so tests can exercise the protocols without Win32.
+#[cfg(test)]
+pub(crate) struct StdSignal {
+    raised: std::sync::Mutex<bool>,
+    cond: std::sync::Condvar,
+}
+
+#[cfg(test)]
+impl StdSignal {
+    pub fn boxed() -> Result<Box<dyn Wake>> {
+        Ok(Box::new(Self {
+            raised: std::sync::Mutex::new(false),
+            cond: std::sync::Condvar::new(),
+        }))
+    }
+}
+
+#[cfg(test)]
+impl Wake for StdSignal {
+    fn raise(&self) {
+        *self.raised.lock().unwrap() = true;
+        self.cond.notify_one();
+    }
+
+    fn wait(&self) {
+        let mut raised = self.raised.lock().unwrap();
+        while !*raised {
+            raised = self.cond.wait(raised).unwrap();
+        }
+        *raised = false;
+    }
+}
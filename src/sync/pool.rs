@@ -0,0 +1,188 @@
+//! Bounded leases on a shared backend, handed out to concurrent callers.
+
+use alloc::{boxed::Box, string::ToString};
+use core::ops::Deref;
+
+use const_encrypt::obf;
+use spin::Mutex;
+
+use super::{NewWake, Wake};
+use crate::error::{ClrError, Result};
+
+/// Hands out at most `size` leases on a backend at a time.
+pub(crate) struct Pool<B> {
+    backend: B,
+    size: usize,
+    available: Mutex<usize>,
+
+    /// Raised whenever a lease is returned.
+    returned: Box<dyn Wake>,
+}
+
+impl<B> Pool<B> {
+    /// Creates a pool whose waiters are woken through a flag made by `new_wake`.
+    pub fn new(backend: B, size: usize, new_wake: NewWake) -> Result<Self> {
+        if size == 0 {
+            return Err(ClrError::Msg(
+                obf!("pool size must be at least 1").to_string(),
+            ));
+        }
+
+        Ok(Self {
+            backend,
+            size,
+            available: Mutex::new(size),
+            returned: new_wake()?,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn available(&self) -> usize {
+        *self.available.lock()
+    }
+
+    /// Takes a lease, blocking until one is returned if none are left.
+    pub fn checkout(&self) -> Lease<'_, B> {
+        loop {
+            if let Some(lease) = self.try_checkout() {
+                return lease;
+            }
+
+            self.returned.wait();
+        }
+    }
+
+    /// Takes a lease if one is left.
+    pub fn try_checkout(&self) -> Option<Lease<'_, B>> {
+        let mut available = self.available.lock();
+        if *available == 0 {
+            return None;
+        }
+        *available -= 1;
+
+        // Returns that raced each other may have raised the signal only once; pass the
+        // wake-up on so another waiter can take what is left
+        if *available > 0 {
+            self.returned.raise();
+        }
+
+        Some(Lease { pool: self })
+    }
+
+    fn checkin(&self) {
+        *self.available.lock() += 1;
+        self.returned.raise();
+    }
+}
+
+/// Exclusive use of one slot of a [`Pool`] until dropped.
+pub(crate) struct Lease<'a, B> {
+    pool: &'a Pool<B>,
+}
+
+impl<B> Deref for Lease<'_, B> {
+    type Target = B;
+
+    fn deref(&self) -> &B {
+        &self.pool.backend
+    }
+}
+
+impl<B> Drop for Lease<'_, B> {
+    fn drop(&mut self) {
+        self.pool.checkin();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::StdSignal;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::{sync::mpsc, thread, time::Duration};
+
+    /// Backend recording how many leases use it at once.
+    #[derive(Default)]
+    struct FakeBackend {
+        active: AtomicUsize,
+        peak: AtomicUsize,
+        calls: AtomicUsize,
+    }
+
+    impl FakeBackend {
+        fn execute(&self) {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+            thread::yield_now();
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.active.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_checkout_and_return() -> Result<()> {
+        let pool = Pool::new(FakeBackend::default(), 2, StdSignal::boxed)?;
+
+        let first = pool.checkout();
+        let second = pool.try_checkout().expect("second lease");
+        assert_eq!(pool.available(), 0);
+        assert!(pool.try_checkout().is_none());
+
+        drop(first);
+        assert_eq!(pool.available(), 1);
+        drop(second);
+        assert_eq!(pool.available(), pool.size());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_empty_pool() {
+        assert!(Pool::new(FakeBackend::default(), 0, StdSignal::boxed).is_err());
+    }
+
+    #[test]
+    fn test_checkout_waits_for_return() -> Result<()> {
+        let pool = Pool::new(FakeBackend::default(), 1, StdSignal::boxed)?;
+        let lease = pool.checkout();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let lease = pool.checkout();
+                lease.execute();
+                sender.send(()).unwrap();
+            });
+
+            assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+            drop(lease);
+            receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        });
+
+        assert_eq!(pool.available(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_use_is_bounded() -> Result<()> {
+        let pool = Pool::new(FakeBackend::default(), 3, StdSignal::boxed)?;
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..50 {
+                        pool.checkout().execute();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(pool.backend.calls.load(Ordering::SeqCst), 400);
+        assert!(pool.backend.peak.load(Ordering::SeqCst) <= 3);
+        assert_eq!(pool.available(), 3);
+        Ok(())
+    }
+}
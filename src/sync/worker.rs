@@ -0,0 +1,445 @@
+//! Request/response protocol between [`ClrWorker`] handles and the thread serving them.
+
+use alloc::{
+    boxed::Box,
+    collections::VecDeque,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use const_encrypt::obf;
+
+use super::{NewWake, Wake};
+use crate::error::{ClrError, Result};
+
+/// Identifies an assembly loaded into a [`ClrWorker`] through [`ClrWorker::load`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AssemblyId(pub(crate) usize);
+
+/// Handle to a CLR runtime that lives on its own thread.
+///
+/// The CLR's COM interfaces such as `_AppDomain` and `_Assembly` are agile, so any
+/// thread may call them: threads that never initialised COM belong to the multithreaded
+/// apartment the runtime keeps alive. Their wrappers are neither `Send` nor `Sync`
+/// though, so the worker keeps all of them on a single thread, which joins the
+/// multithreaded apartment explicitly, and only exchanges owned Rust values with callers.
+/// The handle is cheap to clone and can be shared freely between threads; the worker
+/// shuts down once [`ClrWorker::shutdown`] is called or the last handle is dropped.
+///
+/// # Example
+///
+/// ```
+/// use rustclr::{ClrWorker, RuntimeVersion};
+/// use std::fs;
+///
+/// let worker = ClrWorker::spawn(Some(RuntimeVersion::V4))?;
+/// let buffer = fs::read("examples/sample.exe")?;
+///
+/// let output = worker.run(buffer.clone(), vec!["arg1".into()])?;
+/// println!("Output: {}", output);
+///
+/// let assembly = worker.load(buffer)?;
+/// let result = worker.invoke(assembly, "Sample.Program", "Greet", vec!["world".into()])?;
+/// ```
+#[derive(Clone)]
+pub struct ClrWorker {
+    sender: Arc<Sender>,
+}
+
+impl ClrWorker {
+    /// Loads `buffer` into the worker's application domain.
+    pub fn load(&self, buffer: Vec<u8>) -> Result<AssemblyId> {
+        match self.sender.request(Request::Load(buffer))? {
+            Response::Loaded(id) => Ok(id),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Invokes the static method `method` of `type_name` in a loaded assembly, passing
+    /// `args` as strings, and returns the string form of its result.
+    pub fn invoke(
+        &self,
+        assembly: AssemblyId,
+        type_name: &str,
+        method: &str,
+        args: Vec<String>,
+    ) -> Result<String> {
+        let request = Request::Invoke {
+            assembly,
+            type_name: type_name.to_string(),
+            method: method.to_string(),
+            args,
+        };
+
+        match self.sender.request(request)? {
+            Response::Output(output) => Ok(output),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Runs the entry point of `buffer` with `args` and returns its console output.
+    ///
+    /// `System.Environment.Exit` is patched first so the assembly cannot terminate
+    /// the process hosting the worker.
+    pub fn run(&self, buffer: Vec<u8>, args: Vec<String>) -> Result<String> {
+        match self.sender.request(Request::Run { buffer, args })? {
+            Response::Output(output) => Ok(output),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Stops the worker once every request queued before this one has been served.
+    ///
+    /// Requests sent afterwards, from any clone of the handle, fail with
+    /// [`ClrError::WorkerStopped`].
+    pub fn shutdown(&self) -> Result<()> {
+        match self.sender.request(Request::Shutdown)? {
+            Response::Stopped => Ok(()),
+            _ => Err(unexpected_response()),
+        }
+    }
+}
+
+/// Requests understood by the worker.
+pub(crate) enum Request {
+    /// Load an assembly into the worker's domain.
+    Load(Vec<u8>),
+
+    /// Invoke a static method of a loaded assembly.
+    Invoke {
+        assembly: AssemblyId,
+        type_name: String,
+        method: String,
+        args: Vec<String>,
+    },
+
+    /// Run an assembly's entry point.
+    Run { buffer: Vec<u8>, args: Vec<String> },
+
+    /// Stop serving requests.
+    Shutdown,
+}
+
+/// Responses produced by the worker.
+pub(crate) enum Response {
+    /// The worker's runtime is up and it is ready for requests.
+    Started,
+
+    /// The assembly was loaded under the given id.
+    Loaded(AssemblyId),
+
+    /// Output or return value of an invocation.
+    Output(String),
+
+    /// The worker acknowledged a shutdown.
+    Stopped,
+}
+
+/// Something able to serve worker requests; the CLR in production, a fake in tests.
+pub(crate) trait Backend {
+    /// Handles a single request other than [`Request::Shutdown`].
+    fn handle(&mut self, request: Request) -> Result<Response>;
+}
+
+/// Serves requests from `mailbox` until it is shut down or every handle is dropped.
+pub(crate) fn serve<B: Backend>(mut backend: B, mailbox: &Mailbox) {
+    while let Some(envelope) = mailbox.next() {
+        if let Request::Shutdown = envelope.request {
+            mailbox.close();
+            envelope.reply.send(Ok(Response::Stopped));
+            break;
+        }
+
+        let response = backend.handle(envelope.request);
+        envelope.reply.send(response);
+    }
+
+    // Fail anything queued behind a shutdown instead of leaving callers blocked
+    for envelope in mailbox.drain() {
+        envelope.reply.send(Err(ClrError::WorkerStopped));
+    }
+}
+
+/// Creates a handle together with the mailbox its worker serves, with every wake-up
+/// flag of the protocol made by `new_wake`.
+pub(crate) fn channel(new_wake: NewWake) -> Result<(ClrWorker, Arc<Mailbox>)> {
+    let mailbox = Arc::new(Mailbox {
+        queue: spin::Mutex::new(VecDeque::new()),
+        closed: AtomicBool::new(false),
+        wake: new_wake()?,
+        new_wake,
+    });
+
+    let worker = ClrWorker {
+        sender: Arc::new(Sender {
+            mailbox: mailbox.clone(),
+        }),
+    };
+
+    Ok((worker, mailbox))
+}
+
+/// Sending side shared by every clone of a [`ClrWorker`].
+struct Sender {
+    mailbox: Arc<Mailbox>,
+}
+
+impl Sender {
+    /// Queues `request` and blocks until the worker answers it.
+    fn request(&self, request: Request) -> Result<Response> {
+        let reply = Arc::new(self.mailbox.reply()?);
+        self.mailbox.post(Envelope {
+            request,
+            reply: reply.clone(),
+        })?;
+
+        reply.recv()
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        // Last handle gone: let the worker finish what is queued and exit
+        self.mailbox.close();
+    }
+}
+
+/// Queue of pending requests.
+pub(crate) struct Mailbox {
+    queue: spin::Mutex<VecDeque<Envelope>>,
+    closed: AtomicBool,
+    wake: Box<dyn Wake>,
+
+    /// Makes the flags replies are signalled through.
+    new_wake: NewWake,
+}
+
+impl Mailbox {
+    /// Creates a reply slot signalled through this mailbox's kind of flag.
+    pub fn reply(&self) -> Result<Reply> {
+        Reply::new(self.new_wake)
+    }
+
+    fn post(&self, envelope: Envelope) -> Result<()> {
+        {
+            let mut queue = self.queue.lock();
+            if self.closed.load(Ordering::Acquire) {
+                return Err(ClrError::WorkerStopped);
+            }
+            queue.push_back(envelope);
+        }
+
+        self.wake.raise();
+        Ok(())
+    }
+
+    /// Blocks until a request is available, or returns `None` once the mailbox is
+    /// closed and empty.
+    fn next(&self) -> Option<Envelope> {
+        loop {
+            {
+                let mut queue = self.queue.lock();
+                if let Some(envelope) = queue.pop_front() {
+                    return Some(envelope);
+                }
+                if self.closed.load(Ordering::Acquire) {
+                    return None;
+                }
+            }
+
+            self.wake.wait();
+        }
+    }
+
+    fn close(&self) {
+        {
+            let _queue = self.queue.lock();
+            self.closed.store(true, Ordering::Release);
+        }
+
+        self.wake.raise();
+    }
+
+    fn drain(&self) -> Vec<Envelope> {
+        self.queue.lock().drain(..).collect()
+    }
+}
+
+/// A request paired with the slot its response goes to.
+struct Envelope {
+    request: Request,
+    reply: Arc<Reply>,
+}
+
+/// Single-use slot a caller blocks on until the worker fills it.
+pub(crate) struct Reply {
+    value: spin::Mutex<Option<Result<Response>>>,
+    ready: Box<dyn Wake>,
+}
+
+impl Reply {
+    fn new(new_wake: NewWake) -> Result<Self> {
+        Ok(Self {
+            value: spin::Mutex::new(None),
+            ready: new_wake()?,
+        })
+    }
+
+    pub fn send(&self, value: Result<Response>) {
+        *self.value.lock() = Some(value);
+        self.ready.raise();
+    }
+
+    pub fn recv(&self) -> Result<Response> {
+        loop {
+            if let Some(value) = self.value.lock().take() {
+                return value;
+            }
+
+            self.ready.wait();
+        }
+    }
+}
+
+fn unexpected_response() -> ClrError {
+    ClrError::Msg(obf!("unexpected worker response").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::StdSignal;
+    use alloc::{format, vec};
+    use std::thread;
+
+    /// Backend answering requests without a runtime.
+    #[derive(Default)]
+    struct FakeBackend {
+        loaded: Vec<Vec<u8>>,
+    }
+
+    impl Backend for FakeBackend {
+        fn handle(&mut self, request: Request) -> Result<Response> {
+            match request {
+                Request::Load(buffer) => {
+                    self.loaded.push(buffer);
+                    Ok(Response::Loaded(AssemblyId(self.loaded.len() - 1)))
+                }
+                Request::Invoke {
+                    assembly,
+                    type_name,
+                    method,
+                    args,
+                } => {
+                    let buffer = self
+                        .loaded
+                        .get(assembly.0)
+                        .ok_or(ClrError::Msg("unknown assembly id".to_string()))?;
+                    Ok(Response::Output(format!(
+                        "{}:{type_name}.{method}({})",
+                        buffer.len(),
+                        args.join(",")
+                    )))
+                }
+                Request::Run { args, .. } if args.is_empty() => {
+                    Err(ClrError::Msg("no args".to_string()))
+                }
+                Request::Run { args, .. } => Ok(Response::Output(args.join(" "))),
+                Request::Shutdown => Ok(Response::Stopped),
+            }
+        }
+    }
+
+    fn spawn_fake() -> (ClrWorker, thread::JoinHandle<()>) {
+        let (worker, mailbox) = channel(StdSignal::boxed).unwrap();
+        let thread = thread::spawn(move || serve(FakeBackend::default(), &mailbox));
+        (worker, thread)
+    }
+
+    #[test]
+    fn test_handle_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync + Clone>() {}
+        assert_send_sync::<ClrWorker>();
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let (worker, thread) = spawn_fake();
+
+        let id = worker.load(vec![0; 4])?;
+        assert_eq!(id, AssemblyId(0));
+        assert_eq!(
+            worker.invoke(id, "Ns.Type", "Method", vec!["a".into(), "b".into()])?,
+            "4:Ns.Type.Method(a,b)"
+        );
+        assert_eq!(worker.run(vec![], vec!["x".into(), "y".into()])?, "x y");
+
+        worker.shutdown()?;
+        thread.join().unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_backend_errors_are_returned() -> Result<()> {
+        let (worker, thread) = spawn_fake();
+
+        assert!(matches!(worker.run(vec![], vec![]), Err(ClrError::Msg(_))));
+        assert!(worker.invoke(AssemblyId(7), "T", "M", vec![]).is_err());
+
+        // The worker keeps serving after a failed request
+        assert!(worker.load(vec![1]).is_ok());
+
+        drop(worker);
+        thread.join().unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_between_threads() -> Result<()> {
+        let (worker, thread) = spawn_fake();
+
+        let clients = (0..8)
+            .map(|i| {
+                let worker = worker.clone();
+                thread::spawn(move || worker.run(vec![], vec![format!("{i}")]))
+            })
+            .collect::<Vec<_>>();
+
+        for (i, client) in clients.into_iter().enumerate() {
+            assert_eq!(client.join().unwrap()?, format!("{i}"));
+        }
+
+        drop(worker);
+        thread.join().unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_requests_after_shutdown_fail() -> Result<()> {
+        let (worker, thread) = spawn_fake();
+        let other = worker.clone();
+
+        worker.shutdown()?;
+        thread.join().unwrap();
+
+        assert!(matches!(other.load(vec![]), Err(ClrError::WorkerStopped)));
+        assert!(matches!(worker.shutdown(), Err(ClrError::WorkerStopped)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dropping_last_handle_stops_worker() -> Result<()> {
+        let (worker, thread) = spawn_fake();
+        let clone = worker.clone();
+
+        // Another handle is still alive, so the worker keeps serving
+        drop(worker);
+        clone.load(vec![])?;
+
+        drop(clone);
+        thread.join().unwrap();
+        Ok(())
+    }
+}
@@ -0,0 +1,502 @@
+//! Parser for the CLR reflection type name syntax.
+//!
+//! Supports the grammar accepted by `Type.GetType`, including closed generics
+//! such as ``System.Collections.Generic.List`1[[System.String]]``, assembly
+//! qualified arguments, arrays, pointers and by-ref types.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use const_encrypt::obf;
+
+use crate::error::{ClrError, Result};
+
+/// A parsed CLR type name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeName {
+    /// Full name of the type or generic type definition (e.g. ``System.Collections.Generic.List`1``).
+    pub name: String,
+
+    /// Generic arguments used to close the type, in declaration order.
+    pub generic_args: Vec<TypeName>,
+
+    /// Array, pointer and by-ref modifiers, applied from left to right.
+    pub modifiers: Vec<TypeModifier>,
+
+    /// Optional assembly name the type belongs to.
+    pub assembly: Option<String>,
+}
+
+/// A modifier applied to a type name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeModifier {
+    /// Single-dimensional, zero-based array (`[]`).
+    SzArray,
+
+    /// Multi-dimensional array with the given rank (`[,]`, or `[*]` for rank 1).
+    Array(u32),
+
+    /// Unmanaged pointer (`*`).
+    Pointer,
+
+    /// Managed reference (`&`).
+    ByRef,
+}
+
+impl TypeName {
+    /// Parses a type name written in the CLR reflection syntax.
+    pub fn parse(input: &str) -> Result<TypeName> {
+        let mut parser = Parser {
+            chars: input.chars().collect(),
+            pos: 0,
+        };
+
+        let type_name = parser.parse_type(Context::TopLevel)?;
+        parser.skip_whitespace();
+        if parser.peek().is_some() {
+            return Err(parser.error(&obf!("unexpected trailing characters").as_str()));
+        }
+
+        Ok(type_name)
+    }
+
+    /// Returns `true` if the name refers to a generic type that is closed over arguments.
+    pub fn is_generic(&self) -> bool {
+        !self.generic_args.is_empty()
+    }
+
+    /// Returns the simple name of the assembly, without version, culture or key token.
+    pub fn assembly_simple_name(&self) -> Option<&str> {
+        self.assembly
+            .as_deref()
+            .map(|assembly| assembly.split(',').next().unwrap_or(assembly).trim())
+    }
+}
+
+impl fmt::Display for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.name.chars() {
+            if matches!(c, '[' | ']' | ',' | '*' | '&' | '\\') {
+                f.write_str("\\")?;
+            }
+            write!(f, "{c}")?;
+        }
+
+        if !self.generic_args.is_empty() {
+            f.write_str("[")?;
+            for (i, arg) in self.generic_args.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(",")?;
+                }
+                write!(f, "[{arg}]")?;
+            }
+            f.write_str("]")?;
+        }
+
+        for modifier in &self.modifiers {
+            match modifier {
+                TypeModifier::SzArray => f.write_str("[]")?,
+                TypeModifier::Array(1) => f.write_str("[*]")?,
+                TypeModifier::Array(rank) => {
+                    f.write_str("[")?;
+                    for _ in 1..*rank {
+                        f.write_str(",")?;
+                    }
+                    f.write_str("]")?;
+                }
+                TypeModifier::Pointer => f.write_str("*")?,
+                TypeModifier::ByRef => f.write_str("&")?,
+            }
+        }
+
+        if let Some(assembly) = &self.assembly {
+            write!(f, ", {assembly}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Where a type name appears, which decides whether it may carry an assembly name.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// The outermost name; an assembly name runs to the end of the input.
+    TopLevel,
+
+    /// A generic argument wrapped in its own brackets; an assembly name runs to `]`.
+    Bracketed,
+
+    /// A generic argument without brackets; it cannot be assembly qualified.
+    Bare,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        match self.bump() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&alloc::format!("{} '{expected}'", obf!("expected")))),
+        }
+    }
+
+    fn error(&self, reason: &str) -> ClrError {
+        let input = self.chars.iter().collect::<String>();
+        ClrError::InvalidTypeName(alloc::format!(
+            "{reason} {} {} in '{input}'",
+            obf!("at position"),
+            self.pos
+        ))
+    }
+
+    fn parse_type(&mut self, context: Context) -> Result<TypeName> {
+        self.skip_whitespace();
+        let name = self.parse_identifier()?;
+
+        self.skip_whitespace();
+        let generic_args = if self.peek() == Some('[') && !self.at_array_spec() {
+            self.parse_generic_args()?
+        } else {
+            Vec::new()
+        };
+
+        let arity = generic_arity(&name);
+        if !generic_args.is_empty() && arity != generic_args.len() {
+            return Err(self.error(&alloc::format!(
+                "{} {arity} {}, {} {}",
+                obf!("type declares"),
+                obf!("generic parameters"),
+                obf!("got"),
+                generic_args.len()
+            )));
+        }
+
+        let modifiers = self.parse_modifiers()?;
+
+        self.skip_whitespace();
+        let assembly = if context != Context::Bare && self.peek() == Some(',') {
+            self.pos += 1;
+            Some(self.parse_assembly(context)?)
+        } else {
+            None
+        };
+
+        Ok(TypeName {
+            name,
+            generic_args,
+            modifiers,
+            assembly,
+        })
+    }
+
+    fn parse_identifier(&mut self) -> Result<String> {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '[' | ']' | ',' | '*' | '&' => break,
+                '\\' => {
+                    self.pos += 1;
+                    let escaped = self
+                        .bump()
+                        .ok_or_else(|| self.error(&obf!("dangling escape character").as_str()))?;
+                    name.push(escaped);
+                }
+                _ => {
+                    name.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        let name = name.trim_end().to_string();
+        if name.is_empty() {
+            return Err(self.error(&obf!("expected a type name").as_str()));
+        }
+
+        Ok(name)
+    }
+
+    /// Checks whether the `[` at the cursor opens an array specifier rather than
+    /// a generic argument list.
+    fn at_array_spec(&self) -> bool {
+        self.chars[self.pos + 1..]
+            .iter()
+            .find(|c| !c.is_whitespace())
+            .is_some_and(|c| matches!(c, ']' | ',' | '*'))
+    }
+
+    fn parse_generic_args(&mut self) -> Result<Vec<TypeName>> {
+        self.expect('[')?;
+
+        let mut args = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let arg = if self.peek() == Some('[') {
+                self.pos += 1;
+                let arg = self.parse_type(Context::Bracketed)?;
+                self.expect(']')?;
+                arg
+            } else {
+                self.parse_type(Context::Bare)?
+            };
+            args.push(arg);
+
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some(']') => break,
+                _ => return Err(self.error(&obf!("unterminated generic argument list").as_str())),
+            }
+        }
+
+        Ok(args)
+    }
+
+    fn parse_modifiers(&mut self) -> Result<Vec<TypeModifier>> {
+        let mut modifiers = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('*') => {
+                    self.pos += 1;
+                    modifiers.push(TypeModifier::Pointer);
+                }
+                Some('&') => {
+                    self.pos += 1;
+                    modifiers.push(TypeModifier::ByRef);
+                }
+                Some('[') if self.at_array_spec() => {
+                    self.pos += 1;
+                    modifiers.push(self.parse_array_spec()?);
+                }
+                _ => break,
+            }
+        }
+
+        Ok(modifiers)
+    }
+
+    fn parse_array_spec(&mut self) -> Result<TypeModifier> {
+        self.skip_whitespace();
+        if self.peek() == Some('*') {
+            self.pos += 1;
+            self.expect(']')?;
+            return Ok(TypeModifier::Array(1));
+        }
+
+        let mut rank = 1;
+        loop {
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => rank += 1,
+                Some(']') => break,
+                _ => return Err(self.error(&obf!("malformed array specifier").as_str())),
+            }
+        }
+
+        Ok(if rank == 1 {
+            TypeModifier::SzArray
+        } else {
+            TypeModifier::Array(rank)
+        })
+    }
+
+    fn parse_assembly(&mut self, context: Context) -> Result<String> {
+        let start = self.pos;
+        if context == Context::Bracketed {
+            while self.peek().is_some_and(|c| c != ']') {
+                self.pos += 1;
+            }
+        } else {
+            self.pos = self.chars.len();
+        }
+
+        let assembly = self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .trim()
+            .to_string();
+
+        if assembly.is_empty() {
+            return Err(self.error(&obf!("expected an assembly name").as_str()));
+        }
+
+        Ok(assembly)
+    }
+}
+
+/// Returns the number of generic parameters declared by a type name, summing the
+/// arity of every nested type (e.g. ``Outer`1+Inner`2`` declares three).
+fn generic_arity(name: &str) -> usize {
+    name.split('+')
+        .filter_map(|segment| segment.rsplit_once('`'))
+        .filter_map(|(_, arity)| arity.parse::<usize>().ok())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn simple(name: &str) -> TypeName {
+        TypeName {
+            name: name.to_string(),
+            generic_args: Vec::new(),
+            modifiers: Vec::new(),
+            assembly: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_simple_name() -> Result<()> {
+        let type_name = TypeName::parse("System.String")?;
+        assert_eq!(type_name, simple("System.String"));
+        assert!(!type_name.is_generic());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bracketed_generic() -> Result<()> {
+        let type_name = TypeName::parse("System.Collections.Generic.List`1[[System.String]]")?;
+        assert_eq!(type_name.name, "System.Collections.Generic.List`1");
+        assert_eq!(type_name.generic_args, vec![simple("System.String")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bare_generic_args() -> Result<()> {
+        let type_name =
+            TypeName::parse("System.Collections.Generic.Dictionary`2[System.String,System.Int32]")?;
+        assert_eq!(
+            type_name.generic_args,
+            vec![simple("System.String"), simple("System.Int32")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_assembly_qualified_args() -> Result<()> {
+        let type_name = TypeName::parse(
+            "System.Collections.Generic.Dictionary`2[[System.String, mscorlib, Version=4.0.0.0], \
+             [System.Int32, mscorlib]], mscorlib, Version=4.0.0.0, Culture=neutral",
+        )?;
+
+        assert_eq!(type_name.generic_args.len(), 2);
+        assert_eq!(
+            type_name.generic_args[0].assembly.as_deref(),
+            Some("mscorlib, Version=4.0.0.0")
+        );
+        assert_eq!(
+            type_name.generic_args[1].assembly_simple_name(),
+            Some("mscorlib")
+        );
+        assert_eq!(
+            type_name.assembly.as_deref(),
+            Some("mscorlib, Version=4.0.0.0, Culture=neutral")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_nested_generics() -> Result<()> {
+        let type_name = TypeName::parse(
+            "System.Collections.Generic.List`1[[System.Collections.Generic.List`1[[System.Int32]]]]",
+        )?;
+        let inner = &type_name.generic_args[0];
+        assert_eq!(inner.name, "System.Collections.Generic.List`1");
+        assert_eq!(inner.generic_args, vec![simple("System.Int32")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_modifiers() -> Result<()> {
+        let type_name = TypeName::parse("System.Int32[][,]*&")?;
+        assert_eq!(
+            type_name.modifiers,
+            vec![
+                TypeModifier::SzArray,
+                TypeModifier::Array(2),
+                TypeModifier::Pointer,
+                TypeModifier::ByRef
+            ]
+        );
+
+        let type_name = TypeName::parse("System.Collections.Generic.List`1[[System.String]][]")?;
+        assert!(type_name.is_generic());
+        assert_eq!(type_name.modifiers, vec![TypeModifier::SzArray]);
+
+        let type_name = TypeName::parse("System.Int32[*]")?;
+        assert_eq!(type_name.modifiers, vec![TypeModifier::Array(1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_nested_type_arity() -> Result<()> {
+        let type_name = TypeName::parse("Outer`1+Inner`1[[System.String],[System.Int32]]")?;
+        assert_eq!(type_name.generic_args.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_escaped_name() -> Result<()> {
+        let type_name = TypeName::parse(r"Weird\,Name\[0\]")?;
+        assert_eq!(type_name.name, "Weird,Name[0]");
+        assert_eq!(type_name.to_string(), r"Weird\,Name\[0\]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for input in [
+            "",
+            "System.Collections.Generic.List`1[[System.String]",
+            "System.Collections.Generic.List`1[[System.String],[System.Int32]]",
+            "System.Int32[,",
+            "System.String,",
+            "System.String]",
+            r"System.String\",
+        ] {
+            assert!(
+                matches!(TypeName::parse(input), Err(ClrError::InvalidTypeName(_))),
+                "expected '{input}' to be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_display_round_trip() -> Result<()> {
+        for input in [
+            "System.String",
+            "System.Collections.Generic.List`1[[System.String]]",
+            "System.Collections.Generic.Dictionary`2[[System.String, mscorlib],[System.Int32]][], mscorlib",
+            "System.Int32[,]*&",
+        ] {
+            let parsed = TypeName::parse(input)?;
+            assert_eq!(parsed.to_string(), input);
+            assert_eq!(TypeName::parse(&parsed.to_string())?, parsed);
+        }
+        Ok(())
+    }
+}
@@ -10,8 +10,10 @@ use windows::Win32::System::Ole::{
     SafeArrayAccessData, SafeArrayCreate, SafeArrayCreateVector, SafeArrayDestroy,
     SafeArrayPutElement, SafeArrayUnaccessData,
 };
-use windows::Win32::System::Variant::{InitVariantFromStringArray, VARIANT, VT_UI1, VT_VARIANT};
-use windows::core::PCWSTR;
+use windows::Win32::System::Variant::{
+    InitVariantFromStringArray, VARIANT, VT_DISPATCH, VT_UI1, VT_UNKNOWN, VT_VARIANT,
+};
+use windows::core::{IUnknown, PCWSTR};
 
 use const_encrypt::obf;
 
@@ -111,3 +113,18 @@ pub fn create_string_array_variant(strings: Vec<String>) -> Result<VARIANT> {
 
     Ok(variant)
 }
+
+/// Extracts the COM object held by a `VT_UNKNOWN` or `VT_DISPATCH` VARIANT.
+/// The returned interface holds its own reference, independent of the VARIANT.
+pub fn unknown_from_variant(variant: &VARIANT) -> Result<IUnknown> {
+    unsafe {
+        let inner = &variant.Anonymous.Anonymous;
+        if inner.vt != VT_UNKNOWN && inner.vt != VT_DISPATCH {
+            return Err(ClrError::VariantUnsupported);
+        }
+
+        (*inner.Anonymous.punkVal)
+            .clone()
+            .ok_or(ClrError::NullPointerError(obf!("VARIANT").to_string()))
+    }
+}